@@ -0,0 +1,83 @@
+//! Compare the throughput of validating `docker-compose.yml` files using a
+//! cached `Validator` against compiling the schema from scratch for every
+//! file, which is what we used to do.  Run with:
+//!
+//! ```sh
+//! cargo bench --bench validate
+//! ```
+
+extern crate compose_yml;
+extern crate serde_json;
+extern crate url;
+extern crate valico;
+
+use compose_yml::v2 as dc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// The number of times to validate our sample file in each benchmark.
+const ITERATIONS: u32 = 200;
+
+/// The schema for `docker-compose.yml` version 2.1.
+const SCHEMA: &'static str =
+    include_str!("../src/v2/validate/config_schema_v2.1.json");
+
+/// A typical small `docker-compose.yml` file.
+const SAMPLE: &'static str = r#"---
+version: "2.1"
+services:
+  web:
+    build: "."
+    ports:
+      - "80:3000"
+    environment:
+      DATABASE_URL: "postgres://db/app"
+    depends_on:
+      - "db"
+  db:
+    image: "postgres:9.6"
+    volumes:
+      - "pgdata:/var/lib/postgresql/data"
+volumes:
+  pgdata: {}
+"#;
+
+/// Validate `file` the old way, compiling the schema every time.
+fn validate_uncached(file: &dc::File, schema: &serde_json::Value) {
+    let mut scope = valico::json_schema::Scope::new();
+    let id = Url::parse("http://example.com/config_schema.json").unwrap();
+    let compiled = scope.compile_and_return_with_id(&id, schema.clone(), false)
+        .unwrap();
+    let value = serde_json::to_value(file);
+    assert!(compiled.validate(&value).is_strictly_valid());
+}
+
+/// Convert a `Duration` to fractional seconds.
+fn secs(d: Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9
+}
+
+/// Time `f`, and print how many files per second it validates.
+fn bench<F: FnMut()>(label: &str, mut f: F) -> f64 {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_sec = ITERATIONS as f64 / secs(start.elapsed());
+    println!("{:>10}: {:>10.1} files/sec", label, per_sec);
+    per_sec
+}
+
+fn main() {
+    let file = dc::File::from_str(SAMPLE).unwrap();
+    let schema: serde_json::Value = serde_json::from_str(SCHEMA).unwrap();
+
+    let before = bench("uncached", || validate_uncached(&file, &schema));
+
+    let validator = dc::Validator::new();
+    validator.precompile();
+    let after = bench("cached", || validator.validate(&file).unwrap());
+
+    println!("speedup: {:.1}x", after / before);
+}
@@ -10,9 +10,10 @@
 #![cfg_attr(feature="clippy", allow(redundant_closure))]
 
 use serde_yaml;
-use std::io::Write;
+use std::fmt;
 use std::path::PathBuf;
-use valico::json_schema::ValidationState;
+
+use v2::{ConversionChange, SchemaViolation, Version, VersionRequirement};
 
 error_chain! {
     // These are external, non-`error_chain` error types that we can
@@ -32,11 +33,36 @@ error_chain! {
             display("could not convert '{}' to the equivalent Windows path", &path)
         }
 
-        /// A value did not conform to a JSON schema.
-        DoesNotConformToSchema(state: ValidationState) {
+        /// A value did not conform to a JSON schema.  Each violation
+        /// includes a JSON pointer to the offending value and, where
+        /// available, its location in the original YAML source.
+        DoesNotConformToSchema(violations: Vec<SchemaViolation>) {
             description("data did not conform to schema")
-            display("data did not confirm to schema:{}",
-                    validation_state_to_string(&state))
+            display("data did not conform to schema:{}",
+                    list_to_string(&violations))
+        }
+
+        /// Interpolating the keys of a map, such as the service names in
+        /// `depends_on`, gave two keys the same value.
+        DuplicateInterpolatedKey(key: String) {
+            description("interpolated map keys are not unique")
+            display("more than one map key was interpolated as '{}'", &key)
+        }
+
+        /// A field was used in a file whose version doesn't support it.
+        /// `field` is a JSON pointer to the offending value.
+        FieldRequiresVersion(field: String,
+                             required: VersionRequirement,
+                             version: Option<Version>) {
+            description("field not supported by this version")
+            display("field {} requires {}, but the file has {}",
+                    &field, &required, version_to_string(&version))
+        }
+
+        /// A secret file may be read by any user on the system.
+        InsecureSecretFile(path: PathBuf) {
+            description("secret file is world-readable")
+            display("secret file '{}' is readable by all users", path.display())
         }
 
         /// The interpolation syntax in the specified string was invalid.
@@ -73,6 +99,20 @@ error_chain! {
             display("invalid {} '{}'", &wanted, &input)
         }
 
+        /// We could not convert a file to another format version without
+        /// losing the settings listed here.
+        LossyConversion(version: Version, changes: Vec<ConversionChange>) {
+            description("conversion would lose information")
+            display("converting to version {} would lose information:{}",
+                    &version, list_to_string(&changes))
+        }
+
+        /// A service has no `image`, but we need one.
+        MissingImage(service: String) {
+            description("service has no image")
+            display("service '{}' has no image", &service)
+        }
+
         /// An `.env` file could not be parsed.
         ParseEnv(line: String) {
             description("cannot parse env variable declaration")
@@ -92,6 +132,35 @@ error_chain! {
             display("error reading file '{}'", path.display())
         }
 
+        /// A secret file is larger than we're willing to load.
+        SecretFileTooLarge(path: PathBuf, size: u64, limit: u64) {
+            description("secret file is too large")
+            display("secret file '{}' is {} bytes, but the limit is {} bytes",
+                    path.display(), size, limit)
+        }
+
+        /// The `length` of a generated secret is outside the range allowed
+        /// for its `kind`.  `pointer` is a JSON pointer to the length.
+        SecretLengthOutOfRange(pointer: String, length: u32, min: u32, max: u32) {
+            description("secret length out of range")
+            display("{} is {}, but must be between {} and {}",
+                    &pointer, length, min, max)
+        }
+
+        /// A service refers to something, such as a secret, which isn't
+        /// declared at the top level of the file.  `pointer` is a JSON
+        /// pointer to the reference.
+        UndefinedReference(pointer: String, kind: String, name: String) {
+            description("reference to undefined value")
+            display("{} refers to undefined {} '{}'", &pointer, &kind, &name)
+        }
+
+        /// An `ImageResolver` could not find a digest for this image.
+        UnresolvedImage(image: String) {
+            description("no digest known for image")
+            display("no digest known for image '{}'", &image)
+        }
+
         /// We don't support the specified version of `docker-compose.yml`.
         UnsupportedVersion(version: String) {
             description("unsupported docker-compose.yml version")
@@ -120,25 +189,63 @@ impl Error {
     {
         ErrorKind::InvalidValue(wanted.into(), input.into()).into()
     }
-}
 
-impl From<ValidationState> for Error {
-    fn from(state: ValidationState) -> Self {
-        assert!(!state.is_strictly_valid());
-        ErrorKind::DoesNotConformToSchema(state).into()
+    /// If this error, or any error which caused it, reports that a file
+    /// failed schema validation, return the list of schema violations.
+    pub fn schema_violations(&self) -> Option<&[SchemaViolation]> {
+        let mut err = Some(self);
+        while let Some(e) = err {
+            if let ErrorKind::DoesNotConformToSchema(ref violations) = *e.kind() {
+                return Some(violations);
+            }
+            err = e.chained_error();
+        }
+        None
+    }
+
+    /// The error which `chain_err` wrapped to create this one, if it was
+    /// one of ours.  The errors returned by `iter` aren't `'static`, so
+    /// they can't be downcast, but the boxed cause which `error_chain`
+    /// stores can be.
+    fn chained_error(&self) -> Option<&Error> {
+        let Error(_, (ref cause, _)) = *self;
+        cause.as_ref().and_then(|cause| cause.downcast_ref::<Error>())
     }
 }
 
-/// Convert a `ValidationState` into a human-readable error message.
-fn validation_state_to_string(state: &ValidationState) -> String {
-    let mut out: Vec<u8> = vec![];
-    for err in &state.errors {
-        write!(&mut out, "\n- validation error: {:?}", err)
-            .expect("cannot format validation error");
+/// Convert a list of `SchemaViolation` or `ConversionChange` values into a
+/// human-readable error message.
+fn list_to_string<T: fmt::Display>(items: &[T]) -> String {
+    let mut out = String::new();
+    for item in items {
+        out.push_str(&format!("\n- {}", item));
     }
-    for url in &state.missing {
-        write!(&mut out, "\n- missing {}", url)
-            .expect("cannot format URL");
+    out
+}
+
+/// Describe the version of a file for use in an error message.  Files
+/// without a version use the unversioned Compose Spec.
+fn version_to_string(version: &Option<Version>) -> String {
+    match *version {
+        Some(ref version) => format!("version {}", version),
+        None => "no version (Compose Spec)".to_owned(),
     }
-    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[test]
+fn schema_violations_are_found_anywhere_in_the_chain() {
+    let violation = SchemaViolation {
+        pointer: "/services".to_owned(),
+        message: "bad".to_owned(),
+        location: None,
+    };
+    let err: Result<()> =
+        Err(ErrorKind::DoesNotConformToSchema(vec![violation.clone()]).into());
+    let err = err.chain_err(|| ErrorKind::ValidationFailed)
+        .chain_err(|| "could not read file")
+        .unwrap_err();
+    assert_eq!(err.schema_violations(), Some(&[violation][..]));
+
+    let err: Error = ErrorKind::ValidationFailed.into();
+    assert_eq!(err.schema_violations(), None);
 }
@@ -91,8 +91,11 @@
 extern crate error_chain;
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "preserve_order")]
+extern crate linked_hash_map;
 #[macro_use]
 extern crate log;
+extern crate rand;
 extern crate regex;
 extern crate serde;
 #[cfg(feature = "serde_derive")]
@@ -100,9 +103,11 @@ extern crate serde;
 extern crate serde_derive;
 extern crate serde_json;
 extern crate serde_yaml;
+extern crate tar;
 extern crate url;
 extern crate valico;
 extern crate void;
+extern crate yaml_rust;
 
 pub mod errors;
 pub mod v2;
@@ -0,0 +1,341 @@
+//! A security audit of the risky settings used by each service.
+//!
+//! Many `docker-compose.yml` options effectively give a container control
+//! over its host.  This module looks for the most common ones and explains
+//! why each is dangerous.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+
+use super::{File, HostVolume, IpcMode, NetworkMode, PidMode, Service};
+use super::validate::escape_pointer_segment;
+
+/// Linux capabilities which give a container significant control over its
+/// host, without the `CAP_` prefix.
+const DANGEROUS_CAPABILITIES: &'static [&'static str] =
+    &["ALL", "SYS_ADMIN", "SYS_MODULE", "SYS_PTRACE", "SYS_RAWIO", "SYS_BOOT",
+      "SYS_TIME", "NET_ADMIN", "DAC_READ_SEARCH", "MAC_ADMIN", "MAC_OVERRIDE"];
+
+/// How much risk does a setting pose to the host?
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiskLevel {
+    /// Exposes the container more than necessary, but doesn't directly
+    /// endanger the host.
+    Low,
+    /// Weakens isolation between the container and the host.
+    Medium,
+    /// Allows the container to interfere with the host or other
+    /// containers.
+    High,
+    /// Equivalent to giving the container root access to the host.
+    Critical,
+}
+
+impl fmt::Display for RiskLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RiskLevel::Low => write!(f, "low"),
+            RiskLevel::Medium => write!(f, "medium"),
+            RiskLevel::High => write!(f, "high"),
+            RiskLevel::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// A risky setting found in a service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskySetting {
+    /// `privileged: true`.
+    Privileged,
+    /// A dangerous capability granted using `cap_add`.
+    DangerousCapability(String),
+    /// `network_mode: host`.
+    HostNetwork,
+    /// `pid: host`.
+    HostPid,
+    /// `ipc: host`.
+    HostIpc,
+    /// A `security_opt` entry which disables AppArmor, seccomp or SELinux
+    /// labeling.
+    SecurityProfileDisabled(String),
+    /// A host device mapped into the container.
+    Device(String),
+    /// The Docker daemon's control socket mounted into the container.
+    DockerSocketMount,
+    /// The host's root directory mounted into the container.
+    HostRootMount,
+    /// A port published on all of the host's network interfaces.
+    PortOnAllInterfaces(String),
+}
+
+impl RiskySetting {
+    /// How risky is this setting?
+    pub fn risk_level(&self) -> RiskLevel {
+        match *self {
+            RiskySetting::Privileged |
+            RiskySetting::DockerSocketMount |
+            RiskySetting::HostRootMount => RiskLevel::Critical,
+            RiskySetting::DangerousCapability(_) |
+            RiskySetting::HostNetwork |
+            RiskySetting::HostPid |
+            RiskySetting::SecurityProfileDisabled(_) => RiskLevel::High,
+            RiskySetting::HostIpc |
+            RiskySetting::Device(_) => RiskLevel::Medium,
+            RiskySetting::PortOnAllInterfaces(_) => RiskLevel::Low,
+        }
+    }
+
+    /// Explain why this setting is risky.
+    pub fn explanation(&self) -> String {
+        match *self {
+            RiskySetting::Privileged => {
+                "privileged containers have full access to all host devices and \
+                 can trivially take over the host".to_owned()
+            }
+            RiskySetting::DangerousCapability(ref cap) => {
+                format!("capability {} allows the container to bypass important \
+                         kernel protections", cap)
+            }
+            RiskySetting::HostNetwork => {
+                "the container shares the host's network stack and can bind to \
+                 or sniff any host interface".to_owned()
+            }
+            RiskySetting::HostPid => {
+                "the container can see and signal every process on the host"
+                    .to_owned()
+            }
+            RiskySetting::HostIpc => {
+                "the container shares the host's IPC namespace, including \
+                 shared memory segments".to_owned()
+            }
+            RiskySetting::SecurityProfileDisabled(ref opt) => {
+                format!("security option {} disables a mandatory access control \
+                         or syscall filtering profile", opt)
+            }
+            RiskySetting::Device(ref device) => {
+                format!("host device {} is directly accessible from the container",
+                        device)
+            }
+            RiskySetting::DockerSocketMount => {
+                "access to the Docker socket allows the container to start \
+                 privileged containers on the host".to_owned()
+            }
+            RiskySetting::HostRootMount => {
+                "the host's entire filesystem is mounted into the container"
+                    .to_owned()
+            }
+            RiskySetting::PortOnAllInterfaces(ref port) => {
+                format!("port {} is published on every host interface, not just \
+                         a specific address", port)
+            }
+        }
+    }
+}
+
+/// A single risky setting found by a security audit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditFinding {
+    /// The name of the service which uses this setting.
+    pub service: String,
+    /// A JSON pointer to the offending field, such as
+    /// `/services/web/privileged`.
+    pub path: String,
+    /// The setting we found.
+    pub setting: RiskySetting,
+    /// How risky is this setting?
+    pub risk_level: RiskLevel,
+    /// Why is this setting risky?
+    pub explanation: String,
+}
+
+impl fmt::Display for AuditFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} risk at {}: {}",
+               self.risk_level, &self.path, &self.explanation)
+    }
+}
+
+/// The results of auditing a `File` or a `Service`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AuditReport {
+    /// Everything we found, in the order we found it.
+    pub findings: Vec<AuditFinding>,
+}
+
+impl AuditReport {
+    /// Audit every service in `file`.
+    pub fn for_file(file: &File) -> AuditReport {
+        let mut report = AuditReport::default();
+        for (name, service) in &file.services {
+            report.audit_service(name, service);
+        }
+        report
+    }
+
+    /// Audit a single service named `name`.
+    pub fn for_service(name: &str, service: &Service) -> AuditReport {
+        let mut report = AuditReport::default();
+        report.audit_service(name, service);
+        report
+    }
+
+    /// Did we find anything at all?
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// The highest risk level of any finding, if we found anything.
+    pub fn max_risk_level(&self) -> Option<RiskLevel> {
+        self.findings.iter().map(|f| f.risk_level).max()
+    }
+
+    /// All findings with a risk level of at least `level`.
+    pub fn at_least(&self, level: RiskLevel) -> Vec<&AuditFinding> {
+        self.findings.iter().filter(|f| f.risk_level >= level).collect()
+    }
+
+    /// Record a finding.
+    fn add(&mut self, service: &str, field: &str, setting: RiskySetting) {
+        self.findings.push(AuditFinding {
+            service: service.to_owned(),
+            path: format!("/services/{}/{}", escape_pointer_segment(service), field),
+            risk_level: setting.risk_level(),
+            explanation: setting.explanation(),
+            setting: setting,
+        });
+    }
+
+    /// Look for risky settings in `service`.  Fields which still require
+    /// interpolation are skipped, because we can't know what they contain.
+    fn audit_service(&mut self, name: &str, service: &Service) {
+        if service.privileged {
+            self.add(name, "privileged", RiskySetting::Privileged);
+        }
+
+        for (i, cap) in service.cap_add.iter().enumerate() {
+            if let Ok(cap) = cap.value() {
+                let normalized = cap.to_uppercase();
+                let normalized = normalized.trim_left_matches("CAP_");
+                if DANGEROUS_CAPABILITIES.contains(&normalized) {
+                    self.add(name,
+                             &format!("cap_add/{}", i),
+                             RiskySetting::DangerousCapability(cap.to_owned()));
+                }
+            }
+        }
+
+        if let Some(Ok(&NetworkMode::Host)) = service.network_mode
+            .as_ref()
+            .map(|m| m.value()) {
+            self.add(name, "network_mode", RiskySetting::HostNetwork);
+        }
+        if let Some(Ok(&PidMode::Host)) = service.pid.as_ref().map(|m| m.value()) {
+            self.add(name, "pid", RiskySetting::HostPid);
+        }
+        if let Some(Ok(&IpcMode::Host)) = service.ipc.as_ref().map(|m| m.value()) {
+            self.add(name, "ipc", RiskySetting::HostIpc);
+        }
+
+        for (i, opt) in service.security_opt.iter().enumerate() {
+            if let Ok(opt) = opt.value() {
+                // Docker accepts both `:` and `=` as separators here.
+                let normalized = opt.replace("=", ":");
+                let disabled = normalized == "apparmor:unconfined" ||
+                               normalized == "seccomp:unconfined" ||
+                               normalized == "label:disable";
+                if disabled {
+                    self.add(name,
+                             &format!("security_opt/{}", i),
+                             RiskySetting::SecurityProfileDisabled(opt.to_owned()));
+                }
+            }
+        }
+
+        for (i, device) in service.devices.iter().enumerate() {
+            if let Ok(device) = device.value() {
+                self.add(name,
+                         &format!("devices/{}", i),
+                         RiskySetting::Device(device.to_string()));
+            }
+        }
+
+        for (i, volume) in service.volumes.iter().enumerate() {
+            if let Ok(&Some(HostVolume::Path(ref path))) = volume.value()
+                .map(|v| &v.host) {
+                let setting = if path == Path::new("/var/run/docker.sock") ||
+                                 path == Path::new("/run/docker.sock") {
+                    RiskySetting::DockerSocketMount
+                } else if path == Path::new("/") {
+                    RiskySetting::HostRootMount
+                } else {
+                    continue;
+                };
+                self.add(name, &format!("volumes/{}", i), setting);
+            }
+        }
+
+        for (i, port) in service.ports.iter().enumerate() {
+            if let Ok(port) = port.value() {
+                let all_interfaces = match port.host_address {
+                    None => true,
+                    Some(IpAddr::V4(ref addr)) => addr.is_unspecified(),
+                    Some(IpAddr::V6(ref addr)) => addr.is_unspecified(),
+                };
+                if all_interfaces {
+                    self.add(name,
+                             &format!("ports/{}", i),
+                             RiskySetting::PortOnAllInterfaces(port.to_string()));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn audit_finds_risky_settings() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "2"
+services:
+  agent:
+    image: "example/agent"
+    privileged: true
+    cap_add:
+      - "SYS_ADMIN"
+      - "CHOWN"
+    network_mode: "host"
+    pid: "host"
+    security_opt:
+      - "seccomp:unconfined"
+    volumes:
+      - "/var/run/docker.sock:/var/run/docker.sock"
+      - "./data:/data"
+  web:
+    image: "nginx"
+    ports:
+      - "127.0.0.1:8080:80"
+      - "443:443"
+"#;
+    let file = File::from_str(yaml).unwrap();
+    let report = AuditReport::for_file(&file);
+    let found: Vec<(&str, &RiskySetting)> = report.findings
+        .iter()
+        .map(|f| (&f.path[..], &f.setting))
+        .collect();
+    assert_eq!(found,
+               vec![("/services/agent/privileged", &RiskySetting::Privileged),
+                    ("/services/agent/cap_add/0",
+                     &RiskySetting::DangerousCapability("SYS_ADMIN".to_owned())),
+                    ("/services/agent/network_mode", &RiskySetting::HostNetwork),
+                    ("/services/agent/pid", &RiskySetting::HostPid),
+                    ("/services/agent/security_opt/0",
+                     &RiskySetting::SecurityProfileDisabled("seccomp:unconfined"
+                         .to_owned())),
+                    ("/services/agent/volumes/0", &RiskySetting::DockerSocketMount),
+                    ("/services/web/ports/1",
+                     &RiskySetting::PortOnAllInterfaces("443:443".to_owned()))]);
+    assert_eq!(report.max_risk_level(), Some(RiskLevel::Critical));
+    assert_eq!(report.at_least(RiskLevel::High).len(), 6);
+}
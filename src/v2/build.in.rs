@@ -14,9 +14,9 @@ pub struct Build {
     pub dockerfile: Option<RawOr<String>>,
 
     /// Build arguments.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
+    #[serde(default, skip_serializing_if = "Map::is_empty",
             deserialize_with = "deserialize_map_or_key_value_list")]
-    pub args: BTreeMap<String, RawOr<String>>,
+    pub args: Map<String, RawOr<String>>,
 
     /// PRIVATE.  Mark this struct as having unknown fields for future
     /// compatibility.  This prevents direct construction and exhaustive
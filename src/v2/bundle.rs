@@ -0,0 +1,242 @@
+//! Exporting a file as a self-contained tarball, together with every local
+//! file and directory it refers to, and importing it again on another
+//! machine.  This is useful for air-gapped deployments.
+//!
+//! A bundle contains the normalized file as `docker-compose.yml`, with
+//! every path rewritten to point inside the archive.  Paths inside the
+//! file's directory keep their relative location, and paths elsewhere are
+//! stored under `_external/`.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tar;
+
+use errors::*;
+use super::{File, HostVolume};
+use super::paths::{normalize, rewrite_paths};
+
+/// The name of the file inside a bundle.
+const BUNDLE_FILE_NAME: &'static str = "docker-compose.yml";
+
+/// Write `file` to `w` as a tarball, along with every local file and
+/// directory it refers to.  Relative paths are resolved against `base`,
+/// which should be the directory containing `file`.
+///
+/// Like `make_standalone`, this interpolates environment variables and
+/// inlines `env_file` entries.  Host paths in `volumes` which lie outside
+/// `base`, such as `/var/run/docker.sock`, refer to the host and are left
+/// alone.  Paths which don't exist are rewritten but not bundled, because
+/// Docker creates missing bind mount sources itself.
+pub fn export_bundle<W>(file: &File, base: &Path, w: W) -> Result<()>
+    where W: io::Write
+{
+    let base = normalize(&absolute(base)?);
+    let mut file = file.clone();
+    file.make_standalone(&base)?;
+    file.absolutize_paths(&base, None)?;
+
+    let mut host_paths = vec![];
+    for service in file.services.values() {
+        for mount in &service.volumes {
+            if let Some(HostVolume::Path(ref path)) = mount.value()?.host {
+                if !path.starts_with(&base) {
+                    host_paths.push(path.to_owned());
+                }
+            }
+        }
+    }
+
+    // Find every local path we need to bundle.
+    let referenced = RefCell::new(BTreeSet::new());
+    {
+        let record = |path: &Path| {
+            if !host_paths.iter().any(|host_path| host_path == path) {
+                referenced.borrow_mut().insert(path.to_owned());
+            }
+            path.to_owned()
+        };
+        rewrite_paths(&mut file, &record, None)?;
+    }
+
+    // Choose a name inside the archive for each path.  Because the paths
+    // are sorted, we see directories before their contents, and we only
+    // need to bundle the outermost directory.
+    let mut names = BTreeMap::new();
+    let mut dirs: Vec<(PathBuf, PathBuf)> = vec![];
+    let mut entries = vec![];
+    for source in referenced.into_inner() {
+        let enclosing = dirs.iter()
+            .find(|&&(ref dir, _)| source.starts_with(dir))
+            .map(|&(ref dir, ref name)| {
+                let rest = source.strip_prefix(dir).expect("dir should be a prefix");
+                normalize(&name.join(rest))
+            });
+        let name = match enclosing {
+            Some(name) => name,
+            None => {
+                let name = archive_name(&source, &base, names.len());
+                if source.is_dir() {
+                    dirs.push((source.clone(), name.clone()));
+                }
+                entries.push((source.clone(), name.clone()));
+                name
+            }
+        };
+        names.insert(source, name);
+    }
+    {
+        let to_archive = |path: &Path| {
+            match names.get(path) {
+                Some(name) if name == Path::new(".") => PathBuf::from("."),
+                Some(name) => Path::new(".").join(name),
+                None => path.to_owned(),
+            }
+        };
+        rewrite_paths(&mut file, &to_archive, None)?;
+    }
+
+    let mut builder = tar::Builder::new(w);
+    for (source, name) in entries {
+        let mkerr = || ErrorKind::ReadFile(source.clone());
+        let metadata = match fs::metadata(&source) {
+            Ok(metadata) => metadata,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err).chain_err(&mkerr),
+        };
+        if metadata.is_dir() {
+            builder.append_dir_all(&name, &source).chain_err(&mkerr)?;
+        } else if metadata.is_file() {
+            let mut f = fs::File::open(&source).chain_err(&mkerr)?;
+            builder.append_file(&name, &mut f).chain_err(&mkerr)?;
+        }
+    }
+
+    // Add the file itself last, so that it replaces any file of the same
+    // name in a bundled directory.
+    let mut yaml = vec![];
+    file.write(&mut yaml)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_path(BUNDLE_FILE_NAME).chain_err(|| "I/O error")?;
+    header.set_size(yaml.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append(&header, &yaml[..]).chain_err(|| "I/O error")?;
+    builder.finish().chain_err(|| "I/O error")?;
+    Ok(())
+}
+
+/// Unpack a bundle written by `export_bundle` into `dest`, and return the
+/// file it contains, with every path resolved against `dest`.
+pub fn import_bundle<R>(r: R, dest: &Path) -> Result<File>
+    where R: io::Read
+{
+    let mkerr = || ErrorKind::WriteFile(dest.to_owned());
+    tar::Archive::new(r).unpack(dest).chain_err(&mkerr)?;
+    let mut file = File::read_from_path(dest.join(BUNDLE_FILE_NAME))?;
+    file.absolutize_paths(&normalize(&absolute(dest)?), None)?;
+    Ok(file)
+}
+
+/// The name inside the archive of `path`, which is the `index`th path we
+/// bundle.
+fn archive_name(path: &Path, base: &Path, index: usize) -> PathBuf {
+    match path.strip_prefix(base) {
+        Ok(rel) if rel.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(rel) => rel.to_owned(),
+        Err(_) => {
+            let file_name = path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "root".to_owned());
+            Path::new("_external").join(index.to_string()).join(file_name)
+        }
+    }
+}
+
+/// Resolve `path` against the current directory.
+fn absolute(path: &Path) -> Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path.to_owned())
+    } else {
+        let cwd = env::current_dir()
+            .chain_err(|| "could not get the current directory")?;
+        Ok(cwd.join(path))
+    }
+}
+
+#[cfg(not(windows))]
+#[test]
+fn bundles_can_be_exported_and_imported() {
+    use std::io::{Read, Write};
+    use std::str::FromStr;
+
+    use super::TempDir;
+
+    let tmp = TempDir::new("compose_yml_bundle_test");
+    let root = tmp.path();
+    let project = root.join("project");
+    fs::create_dir_all(project.join("app")).unwrap();
+    fs::create_dir_all(project.join("secrets")).unwrap();
+    let write = |path: &Path, content: &str| {
+        fs::File::create(path).unwrap().write_all(content.as_bytes()).unwrap();
+    };
+    write(&project.join("app/Dockerfile"), "FROM alpine\n");
+    write(&project.join("secrets/token.txt"), "s3cret");
+    write(&project.join("web.env"), "GREETING=hello\n");
+    write(&root.join("shared.conf"), "listen 80;\n");
+
+    let file = File::from_str(r#"---
+version: "3.3"
+services:
+  web:
+    build: "./app"
+    env_file: ["web.env"]
+    volumes:
+      - "./data:/data"
+      - "/var/run/docker.sock:/var/run/docker.sock"
+configs:
+  shared:
+    file: "../shared.conf"
+secrets:
+  token:
+    file: "./secrets/token.txt"
+"#)
+        .unwrap();
+    let mut tarball = vec![];
+    file.export_bundle(&project, &mut tarball).unwrap();
+
+    let dest = root.join("imported");
+    let imported = File::import_bundle(&tarball[..], &dest).unwrap();
+    let read = |path: &Path| {
+        let mut content = String::new();
+        fs::File::open(path).unwrap().read_to_string(&mut content).unwrap();
+        content
+    };
+    let web = imported.services.get("web").unwrap();
+    assert_eq!(web.build.as_ref().unwrap().context.value().unwrap().to_string(),
+               dest.join("app").display().to_string());
+    assert_eq!(read(&dest.join("app/Dockerfile")), "FROM alpine\n");
+    assert!(web.env_files.is_empty());
+    assert_eq!(web.environment.get("GREETING").unwrap().value().unwrap(),
+               "hello");
+    let hosts: Vec<String> = web.volumes
+        .iter()
+        .map(|v| v.value().unwrap().host.as_ref().unwrap().to_string())
+        .collect();
+    assert_eq!(hosts,
+               vec![dest.join("data").display().to_string(),
+                    "/var/run/docker.sock".to_owned()]);
+    let secret = imported.secrets.get("token").unwrap().file.as_ref().unwrap();
+    assert_eq!(read(Path::new(secret)), "s3cret");
+    let config = imported.configs.get("shared").unwrap().file.as_ref().unwrap();
+    let config = config.value().unwrap();
+    assert!(config.starts_with(dest.join("_external")));
+    assert_eq!(read(config), "listen 80;\n");
+
+    // The bundled file only contains paths inside the bundle.
+    let yaml = read(&dest.join("docker-compose.yml"));
+    assert!(!yaml.contains(&project.display().to_string()));
+}
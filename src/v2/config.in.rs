@@ -0,0 +1,255 @@
+// This is not a normal Rust module! It's included directly into v2.rs,
+// possibly after build-time preprocessing.  See v2.rs for an explanation
+// of how this works.
+
+/// A configuration file which can be made available to services, as
+/// declared in the top-level `configs` section.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The contents of this config, embedded directly in the file.  Only
+    /// supported by the Compose Spec.  See `Config::inline_content`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+
+    /// If this is true, then the config was created outside of
+    /// `docker-compose`.  This option is mutually exclusive with `file`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external: Option<bool>,
+
+    /// The path of a file containing this config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<RawOr<PathBuf>>,
+
+    /// Docker labels for this config, specifying various sorts of custom
+    /// metadata.
+    #[serde(default, skip_serializing_if = "Map::is_empty",
+            deserialize_with = "deserialize_map_or_key_value_list")]
+    pub labels: Map<String, RawOr<String>>,
+
+    /// The name of this config in Docker, if it's different from the key
+    /// used in this file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<RawOr<String>>,
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    #[serde(default, skip_serializing, skip_deserializing)]
+    pub _hidden: (),
+}
+
+derive_standard_impls_for!(Config, {
+    content, external, file, labels, name, _hidden
+});
+
+impl Config {
+    /// Replace `file` with the contents of the file it points to, looking
+    /// up relative paths relative to `base`.  The resulting `content`
+    /// field is only supported by the Compose Spec.
+    pub fn inline_content(&mut self, base: &Path) -> Result<()> {
+        let path = match self.file {
+            Some(ref file) => base.join(file.value()?),
+            None => return Ok(()),
+        };
+        let mkerr = || ErrorKind::ReadFile(path.clone());
+        let mut content = String::new();
+        let mut f = fs::File::open(&path).chain_err(&mkerr)?;
+        io::Read::read_to_string(&mut f, &mut content).chain_err(&mkerr)?;
+        self.content = Some(content);
+        self.file = None;
+        Ok(())
+    }
+}
+
+/// A reference from a service to one of the configs in the top-level
+/// `configs` section, and where to mount it in the container.
+///
+/// ```
+/// use std::str::FromStr;
+/// use compose_yml::v2 as dc;
+///
+/// let config = dc::ServiceConfig::from_str("nginx_conf").unwrap();
+/// assert_eq!(config.source, "nginx_conf");
+/// assert_eq!(config.target, None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceConfig {
+    /// The name of the config in the top-level `configs` section.
+    pub source: String,
+    /// Where to mount the config in the container.  Defaults to
+    /// `/<source>`.
+    pub target: Option<String>,
+    /// The user ID which should own the mounted config.
+    pub uid: Option<String>,
+    /// The group ID which should own the mounted config.
+    pub gid: Option<String>,
+    /// The permissions of the mounted config, such as `0o440`.
+    pub mode: Option<u32>,
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    pub _hidden: (),
+}
+
+impl ServiceConfig {
+    /// Refer to the config named `source`, using the default mount
+    /// options.
+    pub fn new<S: Into<String>>(source: S) -> ServiceConfig {
+        ServiceConfig {
+            source: source.into(),
+            target: None,
+            uid: None,
+            gid: None,
+            mode: None,
+            _hidden: (),
+        }
+    }
+}
+
+impl_interpolatable_value!(ServiceConfig);
+
+/// The long syntax for a `ServiceConfig`, as a map.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServiceConfigLongSyntax {
+    /// The group ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gid: Option<String>,
+    /// The permissions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<u32>,
+    /// The name of the config.
+    source: String,
+    /// The mount point.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    /// The user ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uid: Option<String>,
+}
+
+impl LongSyntax for ServiceConfig {
+    fn requires_long_syntax(&self) -> bool {
+        self.target.is_some() || self.uid.is_some() || self.gid.is_some() ||
+        self.mode.is_some()
+    }
+
+    fn serialize_long<S>(&self, serializer: &mut S) -> result::Result<(), S::Error>
+        where S: Serializer
+    {
+        ServiceConfigLongSyntax {
+                gid: self.gid.clone(),
+                mode: self.mode,
+                source: self.source.clone(),
+                target: self.target.clone(),
+                uid: self.uid.clone(),
+            }
+            .serialize(serializer)
+    }
+
+    fn deserialize_long<M>(visitor: M) -> result::Result<Self, M::Error>
+        where M: de::MapVisitor
+    {
+        let mut mvd = de::value::MapVisitorDeserializer::new(visitor);
+        let long = ServiceConfigLongSyntax::deserialize(&mut mvd)?;
+        Ok(ServiceConfig {
+            source: long.source,
+            target: long.target,
+            uid: long.uid,
+            gid: long.gid,
+            mode: long.mode,
+            _hidden: (),
+        })
+    }
+}
+
+/// Formats a `ServiceConfig` using the short syntax, which only includes
+/// `source`.
+impl fmt::Display for ServiceConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", &self.source)
+    }
+}
+
+impl FromStr for ServiceConfig {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Err(Error::invalid_value("config", s));
+        }
+        Ok(ServiceConfig::new(s))
+    }
+}
+
+#[test]
+fn config_can_be_converted_from_and_to_yaml() {
+    let yaml = r#"---
+"file": "./nginx.conf"
+"labels":
+  "com.example": "foo"
+"#;
+    assert_roundtrip!(Config, yaml);
+
+    let yaml = r#"---
+"external": true
+"#;
+    assert_roundtrip!(Config, yaml);
+}
+
+#[test]
+fn service_configs_support_short_and_long_syntax() {
+    let yaml = r#"---
+"configs":
+- "nginx_conf"
+- "mode": 288
+  "source": "site_conf"
+  "target": "/etc/nginx/conf.d/site.conf"
+"#;
+    assert_roundtrip!(Service, yaml);
+
+    let service: Service = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(service.configs[0].value().unwrap(),
+               &ServiceConfig::new("nginx_conf"));
+    let site = service.configs[1].value().unwrap();
+    assert_eq!(site.source, "site_conf");
+    assert_eq!(site.mode, Some(0o440));
+}
+
+#[test]
+fn config_contents_are_inlined_for_compose_spec_files() {
+    let dir = TempDir::new("compose_yml_inline_config_test");
+    let path = dir.path().join("nginx.conf");
+    let mut f = fs::File::create(&path).unwrap();
+    io::Write::write_all(&mut f, b"listen 80;\n").unwrap();
+
+    let yaml = format!(r#"---
+services:
+  web:
+    image: "nginx"
+    configs:
+      - "nginx_conf"
+configs:
+  nginx_conf:
+    file: "{}"
+"#,
+                       path.display());
+    let mut file = File::from_str(&yaml).unwrap();
+    file.inline_all(Path::new(".")).unwrap();
+    let config = file.configs.get("nginx_conf").unwrap();
+    assert_eq!(config.file, None);
+    assert_eq!(config.content, Some("listen 80;\n".to_owned()));
+    validate_file(&file).unwrap();
+
+    // Numbered versions have no `content` field, so we leave them alone.
+    let yaml = yaml.replace("services:", "version: \"3.3\"\nservices:");
+    let mut file = File::from_str(&yaml).unwrap();
+    file.inline_all(Path::new(".")).unwrap();
+    assert!(file.configs.get("nginx_conf").unwrap().file.is_some());
+}
@@ -0,0 +1,692 @@
+//! Converting a `docker-compose.yml` file from one format version to
+//! another.
+//!
+//! Versions 2 and 3 of the format overlap heavily, but each supports
+//! settings which the other doesn't.  Where there's an equivalent setting
+//! in the target version, we translate it.  Where there isn't, we drop the
+//! setting, and record what we lost so that the caller can decide whether
+//! that's acceptable.
+
+use std::fmt;
+
+use errors::*;
+use super::{Dependency, DependencyCondition, Deploy, File, HostVolume, ResourceSpec,
+            Resources, Service, Version, value};
+use super::validate::{escape_pointer_segment, validate_file};
+
+/// The default CFS scheduler period, in microseconds.  A `cpu_quota` of
+/// this size corresponds to one full CPU.
+const CPU_PERIOD: f64 = 100000.0;
+
+/// The default number of `cpu_shares` given to a container.  We treat this
+/// as the equivalent of one CPU when approximating `cpus`.
+const CPU_SHARES_PER_CPU: f64 = 1024.0;
+
+/// What happened to a setting when we converted a file?
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversionChangeKind {
+    /// The setting was replaced with an exact equivalent.
+    Translated,
+    /// The setting was replaced with something similar, but which may
+    /// behave differently.
+    Approximated,
+    /// The setting has no equivalent in the target version and was
+    /// removed.
+    Dropped,
+}
+
+impl ConversionChangeKind {
+    /// Does this change lose any information?
+    pub fn is_lossy(self) -> bool {
+        self != ConversionChangeKind::Translated
+    }
+}
+
+impl fmt::Display for ConversionChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConversionChangeKind::Translated => write!(f, "translated"),
+            ConversionChangeKind::Approximated => write!(f, "approximated"),
+            ConversionChangeKind::Dropped => write!(f, "dropped"),
+        }
+    }
+}
+
+/// A single setting which was changed while converting a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionChange {
+    /// A JSON pointer to the setting in the original file, such as
+    /// `/services/web/mem_limit`.
+    pub path: String,
+    /// What happened to the setting?
+    pub kind: ConversionChangeKind,
+    /// A human-readable description of the change.
+    pub message: String,
+}
+
+impl fmt::Display for ConversionChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}: {}", self.kind, &self.path, &self.message)
+    }
+}
+
+/// Everything we changed while converting a file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConversionReport {
+    /// The changes we made, in the order we made them.
+    pub changes: Vec<ConversionChange>,
+}
+
+impl ConversionReport {
+    /// Did the conversion preserve everything in the original file?
+    pub fn is_lossless(&self) -> bool {
+        !self.changes.iter().any(|c| c.kind.is_lossy())
+    }
+
+    /// All changes which lost information.
+    pub fn lossy_changes(&self) -> Vec<&ConversionChange> {
+        self.changes.iter().filter(|c| c.kind.is_lossy()).collect()
+    }
+
+    /// Record a change to a field of the service `name`.
+    fn add(&mut self,
+           name: &str,
+           field: &str,
+           kind: ConversionChangeKind,
+           message: String) {
+        self.changes.push(ConversionChange {
+            path: format!("/services/{}/{}", escape_pointer_segment(name), field),
+            kind: kind,
+            message: message,
+        });
+    }
+}
+
+/// The major format versions, which determine how services are
+/// described.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Family {
+    /// Versions 2 and 2.1, which are used by standalone `docker-compose`.
+    V2,
+    /// Version 3 and later, which are also used by `docker stack deploy`.
+    V3,
+}
+
+/// Which family does `version` belong to?
+fn family(version: &Version) -> Result<Family> {
+    match version.major {
+        2 => Ok(Family::V2),
+        3 => Ok(Family::V3),
+        _ => Err(ErrorKind::UnsupportedVersion(version.to_string()).into()),
+    }
+}
+
+/// Convert `file` to `version`, returning the converted file and a report
+/// of what we changed.  Unless `force` is true, we refuse to make any
+/// changes which would lose information.
+pub fn convert_file(file: &File,
+                    version: &Version,
+                    force: bool)
+                    -> Result<(File, ConversionReport)> {
+    if let Some(ref source) = file.version {
+        family(source)?;
+    }
+    let target = family(version)?;
+
+    let mut converted = file.clone();
+    converted.version = Some(version.clone());
+    let mut report = ConversionReport::default();
+
+    // Project names and profiles only exist in the unversioned Compose
+    // Spec.
+    if converted.name.take().is_some() {
+        report.changes.push(ConversionChange {
+            path: "/name".to_owned(),
+            kind: ConversionChangeKind::Dropped,
+            message: format!("version {} does not support project names", version),
+        });
+    }
+
+    if !converted.secrets.is_empty() && *version < Version::new(3, 1) {
+        converted.secrets.clear();
+        report.changes.push(ConversionChange {
+            path: "/secrets".to_owned(),
+            kind: ConversionChangeKind::Dropped,
+            message: format!("version {} does not support secrets", version),
+        });
+    }
+
+    let configs_supported = *version >= Version::new(3, 3);
+    if !converted.configs.is_empty() && !configs_supported {
+        converted.configs.clear();
+        report.changes.push(ConversionChange {
+            path: "/configs".to_owned(),
+            kind: ConversionChangeKind::Dropped,
+            message: format!("version {} does not support configs", version),
+        });
+    }
+
+    let names: Vec<String> = converted.services.keys().cloned().collect();
+    for name in &names {
+        let mut service = converted.services
+            .remove(name)
+            .expect("service should be present");
+        convert_depends_on(name, &mut service, version, &mut report);
+        if !service.configs.is_empty() && !configs_supported {
+            service.configs.clear();
+            report.add(name,
+                       "configs",
+                       ConversionChangeKind::Dropped,
+                       format!("version {} does not support configs", version));
+        }
+        if !service.secrets.is_empty() && *version < Version::new(3, 1) {
+            service.secrets.clear();
+            report.add(name,
+                       "secrets",
+                       ConversionChangeKind::Dropped,
+                       format!("version {} does not support secrets", version));
+        }
+        if service.healthcheck.is_some() && *version < Version::new(2, 1) {
+            service.healthcheck = None;
+            report.add(name,
+                       "healthcheck",
+                       ConversionChangeKind::Dropped,
+                       format!("version {} does not support healthchecks", version));
+        }
+        if *version < Version::new(3, 4) {
+            let start_period = service.healthcheck
+                .as_mut()
+                .and_then(|healthcheck| healthcheck.start_period.take());
+            if start_period.is_some() {
+                let message = format!("version {} does not support start_period",
+                                      version);
+                report.add(name,
+                           "healthcheck/start_period",
+                           ConversionChangeKind::Dropped,
+                           message);
+            }
+        }
+        if !service.profiles.is_empty() {
+            service.profiles.clear();
+            report.add(name,
+                       "profiles",
+                       ConversionChangeKind::Dropped,
+                       format!("version {} does not support profiles", version));
+        }
+        if !version.has_dialect("scone") {
+            drop_scone_fields(name, &mut service, version, &mut report);
+        }
+        match target {
+            Family::V2 => convert_to_v2(name, &mut service, &mut report),
+            Family::V3 => {
+                convert_to_v3(name, &mut service, &mut converted, &mut report)
+            }
+        }
+        converted.services.insert(name.to_owned(), service);
+    }
+
+    if !force && !report.is_lossless() {
+        let lossy = report.lossy_changes().into_iter().cloned().collect();
+        return Err(ErrorKind::LossyConversion(version.clone(), lossy).into());
+    }
+    validate_file(&converted)?;
+    Ok((converted, report))
+}
+
+/// Only version 2.1 supports `depends_on` conditions, so replace them with
+/// a plain list of services everywhere else.
+fn convert_depends_on(name: &str,
+                      service: &mut Service,
+                      version: &Version,
+                      report: &mut ConversionReport) {
+    if version.major == 2 && version.minor >= 1 {
+        return;
+    }
+    for (dep_name, dep) in service.depends_on.iter_mut() {
+        let kind = match dep.condition {
+            None => continue,
+            Some(ref cond) if cond.value().ok() ==
+                              Some(&DependencyCondition::ServiceStarted) => {
+                ConversionChangeKind::Translated
+            }
+            Some(_) => ConversionChangeKind::Dropped,
+        };
+        report.add(name,
+                   &format!("depends_on/{}/condition",
+                            escape_pointer_segment(&dep_name.to_string())),
+                   kind,
+                   format!("version {} only waits for {} to start",
+                           version,
+                           dep_name));
+        *dep = Dependency::default();
+    }
+}
+
+/// Remove any SCONE-specific settings, which are only supported by the
+/// `.scone` versions.
+fn drop_scone_fields(name: &str,
+                     service: &mut Service,
+                     version: &Version,
+                     report: &mut ConversionReport) {
+    let fields = [("mrenclave", service.scone.mrenclave.take().is_some()),
+                  ("fspf_path", service.scone.fspf_path.take().is_some()),
+                  ("fspf_key", service.scone.fspf_key.take().is_some()),
+                  ("fspf_tag", service.scone.fspf_tag.take().is_some()),
+                  ("not_scone", service.scone.not_scone.take().is_some())];
+    for &(field, dropped) in &fields {
+        if dropped {
+            report.add(name,
+                       field,
+                       ConversionChangeKind::Dropped,
+                       format!("version {} does not support SCONE settings", version));
+        }
+    }
+}
+
+/// Rewrite the settings which only exist in version 3 into their version 2
+/// equivalents.
+fn convert_to_v2(name: &str, service: &mut Service, report: &mut ConversionReport) {
+    let deploy = match service.deploy.take() {
+        Some(deploy) => deploy,
+        None => return,
+    };
+    let dropped = |report: &mut ConversionReport, field: &str| {
+        report.add(name,
+                   &format!("deploy/{}", field),
+                   ConversionChangeKind::Dropped,
+                   "version 2 does not support swarm deployment settings".to_owned());
+    };
+    if deploy.mode.is_some() {
+        dropped(report, "mode");
+    }
+    if deploy.replicas.is_some() {
+        dropped(report, "replicas");
+    }
+    if !deploy.labels.is_empty() {
+        dropped(report, "labels");
+    }
+
+    let resources = deploy.resources.unwrap_or_default();
+    let limits = resources.limits.unwrap_or_default();
+    if let Some(memory) = limits.memory {
+        service.mem_limit = Some(memory);
+        report.add(name,
+                   "deploy/resources/limits/memory",
+                   ConversionChangeKind::Translated,
+                   "moved to mem_limit".to_owned());
+    }
+    if let Some(cpus) = limits.cpus {
+        match parse_cpus(cpus.value().ok()) {
+            Some(cpus) => {
+                service.cpu_quota = Some((cpus * CPU_PERIOD).round() as u32);
+                report.add(name,
+                           "deploy/resources/limits/cpus",
+                           ConversionChangeKind::Translated,
+                           "moved to cpu_quota".to_owned());
+            }
+            None => {
+                report.add(name,
+                           "deploy/resources/limits/cpus",
+                           ConversionChangeKind::Dropped,
+                           "could not convert to cpu_quota".to_owned());
+            }
+        }
+    }
+
+    let reservations = resources.reservations.unwrap_or_default();
+    if let Some(cpus) = reservations.cpus {
+        match parse_cpus(cpus.value().ok()) {
+            Some(cpus) => {
+                let shares = (cpus * CPU_SHARES_PER_CPU).round() as u32;
+                service.cpu_shares = Some(shares);
+                report.add(name,
+                           "deploy/resources/reservations/cpus",
+                           ConversionChangeKind::Approximated,
+                           "replaced by a relative cpu_shares weight".to_owned());
+            }
+            None => {
+                report.add(name,
+                           "deploy/resources/reservations/cpus",
+                           ConversionChangeKind::Dropped,
+                           "could not convert to cpu_shares".to_owned());
+            }
+        }
+    }
+    if reservations.memory.is_some() {
+        report.add(name,
+                   "deploy/resources/reservations/memory",
+                   ConversionChangeKind::Dropped,
+                   "version 2 does not support memory reservations".to_owned());
+    }
+}
+
+/// Parse a `cpus` value, if it's available and valid.
+fn parse_cpus(cpus: Option<&String>) -> Option<f64> {
+    cpus.and_then(|c| c.parse::<f64>().ok()).and_then(|c| {
+        if c >= 0.0 { Some(c) } else { None }
+    })
+}
+
+/// Format a number of CPUs the way `docker-compose` expects.
+fn format_cpus(cpus: f64) -> String {
+    format!("{}", cpus)
+}
+
+/// Rewrite the settings which only exist in version 2 into their version 3
+/// equivalents.  This may also need to update the top-level volumes in
+/// `file`.
+fn convert_to_v3(name: &str,
+                 service: &mut Service,
+                 file: &mut File,
+                 report: &mut ConversionReport) {
+    let mut limits = ResourceSpec::default();
+    let mut reservations = ResourceSpec::default();
+
+    // `deploy` is only used by swarm mode, and plain `docker-compose`
+    // ignores it, so these are only approximations.
+    if let Some(memory) = service.mem_limit.take() {
+        limits.memory = Some(memory);
+        report.add(name,
+                   "mem_limit",
+                   ConversionChangeKind::Approximated,
+                   "moved to deploy.resources.limits.memory, which only applies \
+                    in swarm mode"
+                       .to_owned());
+    }
+    if let Some(quota) = service.cpu_quota.take() {
+        limits.cpus = Some(value(format_cpus(quota as f64 / CPU_PERIOD)));
+        report.add(name,
+                   "cpu_quota",
+                   ConversionChangeKind::Approximated,
+                   "moved to deploy.resources.limits.cpus, which only applies in \
+                    swarm mode"
+                       .to_owned());
+    }
+    if let Some(shares) = service.cpu_shares.take() {
+        let cpus = shares as f64 / CPU_SHARES_PER_CPU;
+        reservations.cpus = Some(value(format_cpus(cpus)));
+        report.add(name,
+                   "cpu_shares",
+                   ConversionChangeKind::Approximated,
+                   "relative weight replaced by deploy.resources.reservations.cpus, \
+                    which only applies in swarm mode"
+                       .to_owned());
+    }
+    if limits != ResourceSpec::default() || reservations != ResourceSpec::default() {
+        if service.deploy.is_none() {
+            service.deploy = Some(Deploy::default());
+        }
+        let deploy = service.deploy.as_mut().expect("deploy should be present");
+        if deploy.resources.is_none() {
+            deploy.resources = Some(Resources::default());
+        }
+        let resources = deploy.resources
+            .as_mut()
+            .expect("resources should be present");
+        merge_resource_spec(name, "limits", &mut resources.limits, limits, report);
+        merge_resource_spec(name,
+                            "reservations",
+                            &mut resources.reservations,
+                            reservations,
+                            report);
+    }
+
+    if service.memswap_limit.take().is_some() {
+        report.add(name,
+                   "memswap_limit",
+                   ConversionChangeKind::Dropped,
+                   "version 3 does not support swap limits".to_owned());
+    }
+    if service.oom_score_adj.take().is_some() {
+        report.add(name,
+                   "oom_score_adj",
+                   ConversionChangeKind::Dropped,
+                   "version 3 does not support oom_score_adj".to_owned());
+    }
+    if !service.group_add.is_empty() {
+        service.group_add.clear();
+        report.add(name,
+                   "group_add",
+                   ConversionChangeKind::Dropped,
+                   "version 3 does not support group_add".to_owned());
+    }
+    if !service.volumes_from.is_empty() {
+        service.volumes_from.clear();
+        report.add(name,
+                   "volumes_from",
+                   ConversionChangeKind::Dropped,
+                   "version 3 does not support volumes_from; use named volumes \
+                    instead"
+                       .to_owned());
+    }
+    if service.extends.take().is_some() {
+        report.add(name,
+                   "extends",
+                   ConversionChangeKind::Dropped,
+                   "version 3 does not support extends; merge the base service \
+                    in first"
+                       .to_owned());
+    }
+    if let Some(driver) = service.volume_driver.take() {
+        convert_volume_driver(name, service, file, driver.value().ok(), report);
+    }
+}
+
+/// Merge the resources we computed from version 2 settings into any which
+/// were already present in `deploy.resources`.  Existing settings win.
+fn merge_resource_spec(name: &str,
+                       field: &str,
+                       existing: &mut Option<ResourceSpec>,
+                       new: ResourceSpec,
+                       report: &mut ConversionReport) {
+    if existing.is_none() {
+        *existing = Some(ResourceSpec::default());
+    }
+    let existing = existing.as_mut().expect("resource spec should be present");
+    if let Some(cpus) = new.cpus {
+        if existing.cpus.is_none() {
+            existing.cpus = Some(cpus);
+        } else if existing.cpus.as_ref() != Some(&cpus) {
+            conflict(name, field, "cpus", report);
+        }
+    }
+    if let Some(memory) = new.memory {
+        if existing.memory.is_none() {
+            existing.memory = Some(memory);
+        } else if existing.memory.as_ref() != Some(&memory) {
+            conflict(name, field, "memory", report);
+        }
+    }
+}
+
+/// Record that a translated resource setting conflicted with one already
+/// present in `deploy.resources`.
+fn conflict(name: &str, field: &str, resource: &str, report: &mut ConversionReport) {
+    report.add(name,
+               &format!("deploy/resources/{}/{}", field, resource),
+               ConversionChangeKind::Dropped,
+               "conflicts with the existing deploy.resources setting".to_owned());
+}
+
+/// Version 3 has no per-service `volume_driver`, but we can get the same
+/// effect by setting the driver of each named volume the service mounts.
+/// This doesn't work for anonymous volumes (including those declared by
+/// the image), or for named volumes which already have a different driver.
+fn convert_volume_driver(name: &str,
+                         service: &Service,
+                         file: &mut File,
+                         driver: Option<&String>,
+                         report: &mut ConversionReport) {
+    let driver = match driver {
+        Some(driver) => driver,
+        None => {
+            report.add(name,
+                       "volume_driver",
+                       ConversionChangeKind::Dropped,
+                       "cannot translate an interpolated volume_driver".to_owned());
+            return;
+        }
+    };
+
+    let mut lossless = true;
+    let mut any_named = false;
+    for mount in &service.volumes {
+        match mount.value().ok().map(|m| &m.host) {
+            Some(&Some(HostVolume::Name(ref volume_name))) => {
+                let translated = match file.volumes.get_mut(volume_name) {
+                    Some(volume) => {
+                        let same = volume.driver
+                            .as_ref()
+                            .map_or(false, |d| d.value().ok() == Some(driver));
+                        if volume.driver.is_none() && volume.external != Some(true) {
+                            volume.driver = Some(value(driver.to_owned()));
+                            true
+                        } else {
+                            same
+                        }
+                    }
+                    None => false,
+                };
+                any_named = true;
+                lossless = lossless && translated;
+            }
+            Some(&Some(_)) => {}
+            // Either an anonymous volume, or a mount we can't interpret.
+            _ => lossless = false,
+        }
+    }
+
+    let (kind, message) = if lossless && any_named {
+        (ConversionChangeKind::Translated,
+         format!("moved to the driver of each named volume: {}", driver))
+    } else {
+        (ConversionChangeKind::Dropped,
+         format!("could not apply volume driver {} to every volume", driver))
+    };
+    report.add(name, "volume_driver", kind, message);
+}
+
+#[test]
+fn convert_file_translates_between_versions_2_and_3() {
+    use std::str::FromStr;
+    use super::MemorySize;
+
+    let yaml = r#"---
+version: "2.1"
+services:
+  web:
+    image: "example/web"
+    mem_limit: "512m"
+    cpu_quota: 50000
+    depends_on:
+      db:
+        condition: service_started
+  db:
+    image: "postgres"
+    volume_driver: "flocker"
+    volumes:
+      - "data:/var/lib/postgresql/data"
+volumes:
+  data: {}
+"#;
+    let file = File::from_str(yaml).unwrap();
+
+    // Resource limits only apply to swarm mode in version 3.
+    assert!(convert_file(&file, &Version::new(3, 0), false).is_err());
+    let (v3, report) = convert_file(&file, &Version::new(3, 0), true).unwrap();
+    let lossy: Vec<&str> = report.lossy_changes()
+        .iter()
+        .map(|c| &c.path[..])
+        .collect();
+    assert_eq!(lossy, vec!["/services/web/mem_limit", "/services/web/cpu_quota"]);
+    let web = v3.services.get("web").unwrap();
+    assert_eq!(web.mem_limit, None);
+    let resources = web.deploy.clone().unwrap().resources.unwrap();
+    let limits = resources.limits.unwrap();
+    assert_eq!(limits.memory, Some(value(MemorySize::mb(512))));
+    assert_eq!(limits.cpus, Some(value("0.5".to_owned())));
+    assert_eq!(web.depends_on.get(&value("db".to_owned())),
+               Some(&Dependency::default()));
+    assert_eq!(v3.volumes.get("data").unwrap().driver,
+               Some(value("flocker".to_owned())));
+
+    let (v2, report) = convert_file(&v3, &Version::new(2, 0), false).unwrap();
+    assert!(report.is_lossless());
+    let web = v2.services.get("web").unwrap();
+    assert_eq!(web.mem_limit, Some(value(MemorySize::mb(512))));
+    assert_eq!(web.cpu_quota, Some(50000));
+    assert_eq!(web.deploy, None);
+}
+
+#[test]
+fn convert_file_refuses_lossy_conversions_unless_forced() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "2.1"
+services:
+  web:
+    image: "example/web"
+    cpu_shares: 512
+    volumes_from:
+      - "data"
+    depends_on:
+      db:
+        condition: service_healthy
+  db:
+    image: "postgres"
+  data:
+    image: "example/data"
+"#;
+    let file = File::from_str(yaml).unwrap();
+    let v3 = Version::new(3, 0);
+    assert!(convert_file(&file, &v3, false).is_err());
+
+    let (v3, report) = convert_file(&file, &v3, true).unwrap();
+    let lossy: Vec<&str> = report.lossy_changes()
+        .iter()
+        .map(|c| &c.path[..])
+        .collect();
+    assert_eq!(lossy,
+               vec!["/services/web/depends_on/db/condition",
+                    "/services/web/cpu_shares",
+                    "/services/web/volumes_from"]);
+    let web = v3.services.get("web").unwrap();
+    assert!(web.volumes_from.is_empty());
+    let resources = web.deploy.clone().unwrap().resources.unwrap();
+    assert_eq!(resources.reservations.unwrap().cpus,
+               Some(value("0.5".to_owned())));
+}
+
+#[test]
+fn convert_file_drops_healthchecks_where_unsupported() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "3.4"
+services:
+  web:
+    image: "example/web"
+    healthcheck:
+      test: "curl -f http://localhost/"
+      start_period: 30s
+"#;
+    let file = File::from_str(yaml).unwrap();
+    let (v2_1, report) = convert_file(&file, &Version::new(2, 1), true).unwrap();
+    let lossy: Vec<&str> = report.lossy_changes()
+        .iter()
+        .map(|c| &c.path[..])
+        .collect();
+    assert_eq!(lossy, vec!["/services/web/healthcheck/start_period"]);
+    let healthcheck = v2_1.services.get("web").unwrap().healthcheck.clone().unwrap();
+    assert_eq!(healthcheck.start_period, None);
+    assert!(healthcheck.test.is_some());
+
+    let (v2, report) = convert_file(&file, &Version::new(2, 0), true).unwrap();
+    let lossy: Vec<&str> = report.lossy_changes()
+        .iter()
+        .map(|c| &c.path[..])
+        .collect();
+    assert_eq!(lossy, vec!["/services/web/healthcheck"]);
+    assert_eq!(v2.services.get("web").unwrap().healthcheck, None);
+}
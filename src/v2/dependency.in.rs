@@ -0,0 +1,113 @@
+// This is not a normal Rust module! It's included directly into v2.rs,
+// possibly after build-time preprocessing.  See v2.rs for an explanation
+// of how this works.
+
+/// How a `Service` depends on another service listed in `depends_on`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// What state does the other service need to be in before we start
+    /// this one?  Only supported by version 2.1 files.  If this is
+    /// omitted, we only wait for the other service to be started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<RawOr<DependencyCondition>>,
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    #[serde(default, skip_serializing, skip_deserializing)]
+    pub _hidden: (),
+}
+
+derive_standard_impls_for!(Dependency, {
+    condition, _hidden
+});
+
+impl Dependency {
+    /// Create a `Dependency` which waits until the other service is in
+    /// the specified state.
+    ///
+    /// ```
+    /// use compose_yml::v2 as dc;
+    /// let healthy = dc::DependencyCondition::ServiceHealthy;
+    /// let dep = dc::Dependency::with_condition(healthy.clone());
+    /// assert_eq!(dep.condition, Some(dc::value(healthy)));
+    /// ```
+    pub fn with_condition(condition: DependencyCondition) -> Dependency {
+        Dependency {
+            condition: Some(value(condition)),
+            _hidden: (),
+        }
+    }
+}
+
+#[test]
+fn depends_on_can_be_converted_from_and_to_a_list() {
+    let yaml = r#"---
+"depends_on":
+  - "db"
+  - "cache"
+"image": "example/web"
+"#;
+    assert_roundtrip!(Service, yaml);
+
+    let service: Service = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(service.depends_on.get(&value("db".to_owned())),
+               Some(&Dependency::default()));
+}
+
+#[test]
+fn depends_on_service_names_can_be_interpolated() {
+    use std::env;
+
+    env::set_var("COMPOSE_YML_DEPENDENCY", "db");
+    let yaml = r#"---
+"depends_on":
+  - "$COMPOSE_YML_DEPENDENCY"
+"image": "example/web"
+"#;
+    assert_roundtrip!(Service, yaml);
+
+    let mut service: Service = serde_yaml::from_str(yaml).unwrap();
+    service.interpolate_all().unwrap();
+    assert_eq!(service.depends_on.keys().collect::<Vec<_>>(),
+               vec![&value("db".to_owned())]);
+}
+
+#[test]
+fn depends_on_service_names_must_be_unique_after_interpolation() {
+    use std::env;
+
+    env::set_var("COMPOSE_YML_DUPLICATE_DEPENDENCY", "db");
+    let yaml = r#"---
+"depends_on":
+  - "$COMPOSE_YML_DUPLICATE_DEPENDENCY"
+  - "db"
+"image": "example/web"
+"#;
+    let mut service: Service = serde_yaml::from_str(yaml).unwrap();
+    let err = service.interpolate_all().unwrap_err();
+    match *err.kind() {
+        ErrorKind::DuplicateInterpolatedKey(ref key) => assert_eq!(key, "db"),
+        ref kind => panic!("unexpected error: {}", kind),
+    }
+}
+
+#[test]
+fn depends_on_can_be_converted_from_and_to_a_map_with_conditions() {
+    let yaml = r#"---
+"depends_on":
+  "cache":
+    "condition": "service_started"
+  "db":
+    "condition": "service_healthy"
+"image": "example/web"
+"#;
+    assert_roundtrip!(Service, yaml);
+
+    let service: Service = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(service.depends_on.get(&value("db".to_owned())),
+               Some(&Dependency::with_condition(DependencyCondition::ServiceHealthy)));
+}
@@ -0,0 +1,113 @@
+// This is not a normal Rust module! It's included directly into v2.rs,
+// possibly after build-time preprocessing.  See v2.rs for an explanation
+// of how this works.
+
+/// How to deploy a service to a swarm.  Only supported by version 3
+/// files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Deploy {
+    /// Either `replicated` (the default) or `global`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<RawOr<String>>,
+
+    /// How many containers should we run for a replicated service?
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replicas: Option<u32>,
+
+    /// Docker labels for the service itself, as opposed to its containers.
+    #[serde(default, skip_serializing_if = "Map::is_empty",
+            deserialize_with = "deserialize_map_or_key_value_list")]
+    pub labels: Map<String, RawOr<String>>,
+
+    /// Resource limits and reservations for each container.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Resources>,
+
+    // TODO LOW: update_config
+    // TODO LOW: restart_policy
+    // TODO LOW: placement
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    #[serde(default, skip_serializing, skip_deserializing)]
+    pub _hidden: (),
+}
+
+derive_standard_impls_for!(Deploy, {
+    mode, replicas, labels, resources, _hidden
+});
+
+/// The resources available to a deployed container.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Resources {
+    /// The most the container may use.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ResourceSpec>,
+
+    /// The amount which will be set aside for the container.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reservations: Option<ResourceSpec>,
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    #[serde(default, skip_serializing, skip_deserializing)]
+    pub _hidden: (),
+}
+
+derive_standard_impls_for!(Resources, {
+    limits, reservations, _hidden
+});
+
+/// An amount of CPU and memory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceSpec {
+    /// A number of CPUs, which may be fractional, such as `"0.5"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<RawOr<String>>,
+
+    /// An amount of memory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<RawOr<MemorySize>>,
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    #[serde(default, skip_serializing, skip_deserializing)]
+    pub _hidden: (),
+}
+
+derive_standard_impls_for!(ResourceSpec, {
+    cpus, memory, _hidden
+});
+
+#[test]
+fn deploy_can_be_converted_from_and_to_yaml() {
+    let yaml = r#"---
+"labels":
+  "com.example.tier": "frontend"
+"mode": "replicated"
+"replicas": 3
+"resources":
+  "limits":
+    "cpus": "0.5"
+    "memory": "512m"
+  "reservations":
+    "memory": "128m"
+"#;
+    assert_roundtrip!(Deploy, yaml);
+
+    let deploy: Deploy = serde_yaml::from_str(yaml).unwrap();
+    let limits = deploy.resources.unwrap().limits.unwrap();
+    assert_eq!(limits.memory, Some(value(MemorySize::mb(512))));
+}
@@ -0,0 +1,645 @@
+//! Translating a `Service` into the equivalent `docker run` or `docker
+//! create` command line, which is handy when debugging a single service.
+//!
+//! Not everything in a `docker-compose.yml` file can be expressed as a
+//! single `docker` command.  Some fields, like `build` or `depends_on`,
+//! only make sense for `docker-compose` itself, and others refer to other
+//! services by name.  We leave these out of the command line, and report
+//! them so the caller can decide whether that matters.
+
+use std::fmt;
+use std::fmt::Write;
+
+use errors::*;
+use super::{CommandLine, Healthcheck, HostVolume, NetworkMode, RawOr, Service,
+            ServiceOrContainer, VolumeMount, VolumePermissions};
+use super::interpolation::InterpolatableValue;
+use super::string_or_struct::LongSyntax;
+use super::validate::escape_pointer_segment;
+
+/// A `docker run` or `docker create` command line for a single service.
+///
+/// ```
+/// use std::str::FromStr;
+/// use compose_yml::v2 as dc;
+///
+/// let file = dc::File::from_str(r#"---
+/// version: "2"
+/// services:
+///   web:
+///     image: "nginx"
+///     ports:
+///       - "8080:80"
+/// "#).unwrap();
+/// let web = file.services.get("web").unwrap();
+/// let cmd = dc::DockerRunCommand::run("web", web).unwrap();
+/// assert_eq!(cmd.to_string(), "docker run -p 8080:80 nginx");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DockerRunCommand {
+    /// The full command line, starting with `docker`.
+    pub args: Vec<String>,
+    /// JSON pointers to every field of the service which we couldn't
+    /// express on the command line, such as `/services/web/depends_on`.
+    pub unsupported: Vec<String>,
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    pub _hidden: (),
+}
+
+impl DockerRunCommand {
+    /// Build a `docker run` command for the service named `name`.
+    pub fn run(name: &str, service: &Service) -> Result<DockerRunCommand> {
+        Self::new("run", name, service)
+    }
+
+    /// Build a `docker create` command for the service named `name`.
+    pub fn create(name: &str, service: &Service) -> Result<DockerRunCommand> {
+        Self::new("create", name, service)
+    }
+
+    /// Was every field of the service included in the command line?
+    pub fn is_complete(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+
+    /// Build a command using the specified `docker` subcommand.  All
+    /// fields must already be interpolated.
+    fn new(subcommand: &str, name: &str, service: &Service)
+           -> Result<DockerRunCommand> {
+        let mut builder = Builder {
+            name: name,
+            cmd: DockerRunCommand {
+                args: vec!["docker".to_owned(), subcommand.to_owned()],
+                unsupported: vec![],
+                _hidden: (),
+            },
+        };
+        builder.add_service(service)?;
+        Ok(builder.cmd)
+    }
+}
+
+/// Formats the command line as a single string, quoted so that it can be
+/// pasted into a POSIX shell.
+impl fmt::Display for DockerRunCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let quoted: Vec<String> =
+            self.args.iter().map(|arg| shell_quote(arg)).collect();
+        write!(f, "{}", quoted.join(" "))
+    }
+}
+
+/// Quote `arg` for a POSIX shell, leaving it alone if it only contains
+/// characters which the shell treats literally.
+fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| {
+        ((c as u32) < 128 && c.is_alphanumeric()) || "-_./:=@%+,".contains(c)
+    };
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace("'", "'\\''"))
+    }
+}
+
+/// Split shell code into words the way `docker-compose` does, handling
+/// quotes and backslash escapes, but not variables, globs or any other
+/// shell features.
+fn split_shell_words(code: &str) -> Result<Vec<String>> {
+    let mkerr = || Error::invalid_value("shell code", code);
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if in_word {
+                    words.push(word.clone());
+                    word.clear();
+                    in_word = false;
+                }
+                continue;
+            }
+            '\'' => {
+                loop {
+                    match chars.next().ok_or_else(&mkerr)? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                loop {
+                    match chars.next().ok_or_else(&mkerr)? {
+                        '"' => break,
+                        '\\' => {
+                            // Inside double quotes, backslashes only escape
+                            // a few special characters.
+                            let c = chars.next().ok_or_else(&mkerr)?;
+                            if !"\\\"$`".contains(c) {
+                                word.push('\\');
+                            }
+                            word.push(c);
+                        }
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.push(chars.next().ok_or_else(&mkerr)?),
+            c => word.push(c),
+        }
+        in_word = true;
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Convert a `CommandLine` to a list of arguments, splitting shell code
+/// into words.
+pub fn command_line_args(cmd: &CommandLine) -> Result<Vec<String>> {
+    match *cmd {
+        CommandLine::ShellCode(ref code) => split_shell_words(code.value()?),
+        CommandLine::Parsed(ref args) => {
+            args.iter().map(|arg| arg.value().map(|a| a.to_owned())).collect()
+        }
+    }
+}
+
+/// Format `value` as a string, reporting values which can't be formatted
+/// as errors instead of panicking like `to_string` would.
+fn format_arg<T>(what: &str, value: &T) -> Result<String>
+    where T: fmt::Display + fmt::Debug
+{
+    let mut s = String::new();
+    write!(s, "{}", value)
+        .map_err(|_| Error::invalid_value(what, format!("{:?}", value)))?;
+    Ok(s)
+}
+
+/// Format a volume mount using the `--mount` syntax, which supports all
+/// the options of the long syntax.  `--mount` options are separated by
+/// commas, and are parsed as CSV, so we quote any which contain commas or
+/// quotes.
+fn mount_arg(mount: &VolumeMount) -> Result<String> {
+    let mut opts = vec![format!("type={}", mount.effective_mount_type())];
+    if let Some(ref host) = mount.host {
+        opts.push(format!("source={}", format_arg("--mount", host)?));
+    }
+    opts.push(format!("target={}", &mount.container));
+    if mount.permissions == VolumePermissions::ReadOnly {
+        opts.push("readonly".to_owned());
+    }
+    if let Some(ref consistency) = mount.consistency {
+        opts.push(format!("consistency={}", consistency));
+    }
+    if mount.nocopy {
+        opts.push("volume-nocopy".to_owned());
+    }
+    if let Some(ref propagation) = mount.propagation {
+        opts.push(format!("bind-propagation={}", propagation));
+    }
+    if let Some(size) = mount.tmpfs_size {
+        opts.push(format!("tmpfs-size={}", size));
+    }
+    let quoted: Vec<String> = opts.iter().map(|opt| csv_quote(opt)).collect();
+    Ok(quoted.join(","))
+}
+
+/// Quote `field` for use in a line of CSV, if necessary.
+fn csv_quote(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Accumulates a `DockerRunCommand`.
+struct Builder<'a> {
+    /// The name of the service we're translating.
+    name: &'a str,
+    /// The command we're building.
+    cmd: DockerRunCommand,
+}
+
+impl<'a> Builder<'a> {
+    /// Add a flag and its value.
+    fn flag<S: Into<String>>(&mut self, flag: &str, value: S) {
+        self.cmd.args.push(flag.to_owned());
+        self.cmd.args.push(value.into());
+    }
+
+    /// Add a flag for an optional value.
+    fn opt_flag<T>(&mut self, flag: &str, value: &Option<RawOr<T>>) -> Result<()>
+        where T: InterpolatableValue + fmt::Display + fmt::Debug
+    {
+        if let Some(ref value) = *value {
+            let value = format_arg(flag, value.value()?)?;
+            self.flag(flag, value);
+        }
+        Ok(())
+    }
+
+    /// Add a flag for each value in a list.
+    fn list_flag<T>(&mut self, flag: &str, values: &[RawOr<T>]) -> Result<()>
+        where T: InterpolatableValue + fmt::Display + fmt::Debug
+    {
+        for value in values {
+            let value = format_arg(flag, value.value()?)?;
+            self.flag(flag, value);
+        }
+        Ok(())
+    }
+
+    /// Record that `field` can't be expressed on the command line.
+    fn unsupported(&mut self, field: &str) {
+        let pointer =
+            format!("/services/{}/{}", escape_pointer_segment(self.name), field);
+        self.cmd.unsupported.push(pointer);
+    }
+
+    /// Translate every field of `service`.
+    fn add_service(&mut self, service: &Service) -> Result<()> {
+        // Fields which only make sense to `docker-compose`.
+        if service.build.is_some() {
+            self.unsupported("build");
+        }
+        if !service.configs.is_empty() {
+            self.unsupported("configs");
+        }
+        if !service.secrets.is_empty() {
+            self.unsupported("secrets");
+        }
+        if !service.depends_on.is_empty() {
+            self.unsupported("depends_on");
+        }
+        if service.deploy.is_some() {
+            self.unsupported("deploy");
+        }
+        if service.extends.is_some() {
+            self.unsupported("extends");
+        }
+        if service.scone.mrenclave.is_some() {
+            self.unsupported("mrenclave");
+        }
+        if service.scone.fspf_path.is_some() {
+            self.unsupported("fspf_path");
+        }
+        if service.scone.fspf_key.is_some() {
+            self.unsupported("fspf_key");
+        }
+        if service.scone.fspf_tag.is_some() {
+            self.unsupported("fspf_tag");
+        }
+        if service.scone.not_scone.is_some() {
+            self.unsupported("not_scone");
+        }
+
+        // Container identity.
+        self.opt_flag("--name", &service.container_name)?;
+        self.opt_flag("--hostname", &service.hostname)?;
+        self.opt_flag("--domainname", &service.domainname)?;
+        self.opt_flag("--mac-address", &service.mac_address)?;
+        for (key, value) in &service.labels {
+            let label = format!("{}={}", key, value.value()?);
+            self.flag("--label", label);
+        }
+
+        // Process settings.
+        for path in &service.env_files {
+            let path = path.value()?.display().to_string();
+            self.flag("--env-file", path);
+        }
+        for (key, value) in &service.environment {
+            let var = format!("{}={}", key, value.value()?);
+            self.flag("-e", var);
+        }
+        self.opt_flag("--user", &service.user)?;
+        for group in &service.group_add {
+            self.flag("--group-add", group.to_owned());
+        }
+        self.opt_flag("--workdir", &service.working_dir)?;
+        if service.init == Some(true) {
+            self.cmd.args.push("--init".to_owned());
+        }
+        if service.stdin_open {
+            self.cmd.args.push("-i".to_owned());
+        }
+        if service.tty {
+            self.cmd.args.push("-t".to_owned());
+        }
+        self.opt_flag("--stop-signal", &service.stop_signal)?;
+        self.opt_flag("--restart", &service.restart)?;
+        if let Some(ref healthcheck) = service.healthcheck {
+            self.add_healthcheck(healthcheck)?;
+        }
+
+        // Security.
+        if service.privileged {
+            self.cmd.args.push("--privileged".to_owned());
+        }
+        self.list_flag("--cap-add", &service.cap_add)?;
+        self.list_flag("--cap-drop", &service.cap_drop)?;
+        self.list_flag("--security-opt", &service.security_opt)?;
+        self.opt_flag("--isolation", &service.isolation)?;
+
+        // Namespaces.
+        self.opt_flag("--pid", &service.pid)?;
+        self.opt_flag("--ipc", &service.ipc)?;
+
+        // Resources.
+        self.opt_flag("--cgroup-parent", &service.cgroup_parent)?;
+        if let Some(shares) = service.cpu_shares {
+            self.flag("--cpu-shares", shares.to_string());
+        }
+        if let Some(quota) = service.cpu_quota {
+            self.flag("--cpu-quota", quota.to_string());
+        }
+        self.opt_flag("--memory", &service.mem_limit)?;
+        self.opt_flag("--memory-swap", &service.memswap_limit)?;
+        self.opt_flag("--shm-size", &service.shm_size)?;
+        if let Some(score) = service.oom_score_adj {
+            self.flag("--oom-score-adj", score.to_string());
+        }
+        self.list_flag("--device", &service.devices)?;
+
+        // Logging.
+        if let Some(ref logging) = service.logging {
+            self.opt_flag("--log-driver", &logging.driver)?;
+            for (key, value) in &logging.options {
+                let opt = format!("{}={}", key, value.value()?);
+                self.flag("--log-opt", opt);
+            }
+        }
+
+        self.add_networking(service)?;
+        self.add_storage(service)?;
+
+        // The image and command come last.
+        let image = match service.image {
+            Some(ref image) => format_arg("image", image.value()?)?,
+            None => return Err(ErrorKind::MissingImage(self.name.to_owned()).into()),
+        };
+        let mut trailing = vec![];
+        if let Some(ref entrypoint) = service.entrypoint {
+            let mut entrypoint = command_line_args(entrypoint)?.into_iter();
+            // `--entrypoint` only takes a single program, so any other
+            // arguments are passed before `command`.
+            self.flag("--entrypoint", entrypoint.next().unwrap_or_default());
+            trailing.extend(entrypoint);
+        }
+        self.cmd.args.push(image);
+        self.cmd.args.extend(trailing);
+        if let Some(ref command) = service.command {
+            self.cmd.args.extend(command_line_args(command)?);
+        }
+        Ok(())
+    }
+
+    /// Translate networking-related fields.
+    fn add_healthcheck(&mut self, healthcheck: &Healthcheck) -> Result<()> {
+        if let Some(test) = healthcheck.docker_test()? {
+            let (kind, args) = test.split_at(1);
+            match &kind[0][..] {
+                "NONE" => self.cmd.args.push("--no-healthcheck".to_owned()),
+                // `--health-cmd` is always run by the shell, so quote each
+                // argument of a parsed command.
+                "CMD" => {
+                    let args: Vec<String> =
+                        args.iter().map(|arg| shell_quote(arg)).collect();
+                    self.flag("--health-cmd", args.join(" "));
+                }
+                _ => self.flag("--health-cmd", args.join(" ")),
+            }
+        }
+        self.opt_flag("--health-interval", &healthcheck.interval)?;
+        self.opt_flag("--health-timeout", &healthcheck.timeout)?;
+        if let Some(retries) = healthcheck.retries {
+            self.flag("--health-retries", retries.to_string());
+        }
+        self.opt_flag("--health-start-period", &healthcheck.start_period)?;
+        Ok(())
+    }
+
+    fn add_networking(&mut self, service: &Service) -> Result<()> {
+        if let Some(ref mode) = service.network_mode {
+            match *mode.value()? {
+                // `docker` only knows about containers, not services.
+                NetworkMode::Service(_) => self.unsupported("network_mode"),
+                ref mode => self.flag("--network", format_arg("--network", mode)?),
+            }
+        }
+        // Older versions of `docker run` can only attach a container to a
+        // single network, so we only include the first.
+        for (i, (name, interface)) in service.networks.iter().enumerate() {
+            if i == 0 {
+                self.flag("--network", name.to_owned());
+                self.list_flag("--network-alias", &interface.aliases)?;
+            } else {
+                let field = format!("networks/{}", escape_pointer_segment(name));
+                self.unsupported(&field);
+            }
+        }
+        self.list_flag("--dns", &service.dns)?;
+        self.list_flag("--dns-search", &service.dns_search)?;
+        self.list_flag("--add-host", &service.extra_hosts)?;
+        self.list_flag("--link", &service.links)?;
+        self.list_flag("--link", &service.external_links)?;
+        self.list_flag("--expose", &service.expose)?;
+        for (i, port) in service.ports.iter().enumerate() {
+            let port = port.value()?;
+            // `-p` has no equivalent of the long syntax's `mode`.
+            if port.mode.is_some() {
+                self.unsupported(&format!("ports/{}/mode", i));
+            }
+            let arg = format_arg("-p", port)?;
+            self.flag("-p", arg);
+        }
+        Ok(())
+    }
+
+    /// Translate volume-related fields.
+    fn add_storage(&mut self, service: &Service) -> Result<()> {
+        for (i, mount) in service.volumes.iter().enumerate() {
+            let mount = mount.value()?;
+            // Mounts which need the long syntax in a `docker-compose.yml`
+            // file also need `--mount` on the command line.
+            if mount.requires_long_syntax() {
+                // `--mount` only accepts absolute paths, and won't expand
+                // `~`, so we can't pass relative bind sources along.
+                let relative = match mount.host {
+                    Some(HostVolume::Path(ref path)) => !path.is_absolute(),
+                    Some(HostVolume::UserRelativePath(_)) => true,
+                    _ => false,
+                };
+                if relative {
+                    self.unsupported(&format!("volumes/{}", i));
+                    continue;
+                }
+                let arg = mount_arg(mount)?;
+                self.flag("--mount", arg);
+            } else {
+                let arg = format_arg("-v", mount)?;
+                self.flag("-v", arg);
+            }
+        }
+        for (i, from) in service.volumes_from.iter().enumerate() {
+            let from = from.value()?;
+            match from.source {
+                ServiceOrContainer::Service(_) => {
+                    self.unsupported(&format!("volumes_from/{}", i))
+                }
+                ServiceOrContainer::Container(ref name) => {
+                    let mut arg = name.to_owned();
+                    if from.permissions != VolumePermissions::default() {
+                        arg.push_str(&format!(":{}", from.permissions));
+                    }
+                    self.flag("--volumes-from", arg);
+                }
+            }
+        }
+        self.opt_flag("--volume-driver", &service.volume_driver)?;
+        self.list_flag("--tmpfs", &service.tmpfs)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn docker_run_command_includes_every_supported_field() {
+    use std::str::FromStr;
+    use super::File;
+
+    let file = File::from_str(r#"---
+version: "2"
+services:
+  db:
+    image: "postgres"
+  web:
+    image: "example/web:1.0"
+    command: "bundle exec 'rails server' -p 3000"
+    entrypoint: ["/sbin/tini", "--"]
+    environment:
+      GREETING: "hello world"
+    labels:
+      com.example.team: "web"
+    cap_add: ["NET_ADMIN"]
+    devices: ["/dev/fuse"]
+    dns: ["8.8.8.8"]
+    extra_hosts: ["db.local:10.0.0.2"]
+    logging:
+      driver: "syslog"
+      options:
+        tag: "web"
+    restart: "on-failure:3"
+    mem_limit: "512m"
+    user: "app"
+    working_dir: "/app"
+    ports: ["8080:80"]
+    volumes: ["./src:/app:ro", "data:/data"]
+    depends_on: ["db"]
+"#)
+        .unwrap();
+    let web = file.services.get("web").unwrap();
+    let cmd = DockerRunCommand::create("web", web).unwrap();
+    assert_eq!(cmd.to_string(),
+               "docker create --label com.example.team=web \
+                -e 'GREETING=hello world' --user app --workdir /app \
+                --restart on-failure:3 --cap-add NET_ADMIN --memory 512m \
+                --device /dev/fuse --log-driver syslog --log-opt tag=web \
+                --dns 8.8.8.8 --add-host db.local:10.0.0.2 -p 8080:80 \
+                -v ./src:/app:ro -v data:/data --entrypoint /sbin/tini \
+                example/web:1.0 -- bundle exec 'rails server' -p 3000");
+    assert_eq!(cmd.unsupported, vec!["/services/web/depends_on"]);
+    assert!(!cmd.is_complete());
+}
+
+#[test]
+fn docker_run_command_includes_healthchecks() {
+    use std::str::FromStr;
+    use super::File;
+
+    let file = File::from_str(r#"---
+version: "3.4"
+services:
+  web:
+    image: "nginx"
+    healthcheck:
+      test: ["CMD", "echo", "it's ok"]
+      interval: 30s
+      timeout: 5s
+      retries: 3
+      start_period: 1m
+  worker:
+    image: "worker"
+    healthcheck:
+      disable: true
+"#)
+        .unwrap();
+    let web = file.services.get("web").unwrap();
+    let cmd = DockerRunCommand::run("web", web).unwrap();
+    assert_eq!(cmd.args[2..4], ["--health-cmd", r#"echo 'it'\''s ok'"#]);
+    assert_eq!(cmd.args[4..].join(" "),
+               "--health-interval 30s --health-timeout 5s --health-retries 3 \
+                --health-start-period 1m nginx");
+    assert!(cmd.is_complete());
+
+    let worker = file.services.get("worker").unwrap();
+    let cmd = DockerRunCommand::run("worker", worker).unwrap();
+    assert_eq!(cmd.to_string(), "docker run --no-healthcheck worker");
+}
+
+#[test]
+fn docker_run_command_requires_an_image() {
+    let service = Service::default();
+    let err = DockerRunCommand::run("app", &service).unwrap_err();
+    assert_eq!(err.to_string(), "service 'app' has no image");
+}
+
+#[test]
+fn shell_words_are_split_and_quoted_like_a_posix_shell() {
+    assert_eq!(split_shell_words(r#"a 'b c' "d \"e\"" f\ g"#).unwrap(),
+               vec!["a", "b c", "d \"e\"", "f g"]);
+    assert!(split_shell_words("echo 'oops").is_err());
+    assert_eq!(shell_quote("it's"), r#"'it'\''s'"#);
+    assert_eq!(shell_quote(""), "''");
+    assert_eq!(shell_quote("/usr/bin"), "/usr/bin");
+}
+
+#[test]
+fn long_syntax_mounts_use_mount_flag() {
+    use std::str::FromStr;
+    use super::File;
+
+    let file = File::from_str(r#"---
+version: "3.8"
+services:
+  web:
+    image: "example/web"
+    volumes:
+    - type: "bind"
+      source: "/srv/a,b"
+      target: "/data"
+      consistency: "cached"
+    - type: "bind"
+      source: "./src"
+      target: "/app"
+      consistency: "cached"
+"#)
+        .unwrap();
+    let web = file.services.get("web").unwrap();
+    let cmd = DockerRunCommand::run("web", web).unwrap();
+    assert_eq!(cmd.args,
+               vec!["docker",
+                    "run",
+                    "--mount",
+                    "type=bind,\"source=/srv/a,b\",target=/data,consistency=cached",
+                    "example/web"]);
+    assert_eq!(cmd.unsupported, vec!["/services/web/volumes/1"]);
+}
@@ -0,0 +1,800 @@
+//! Editing `docker-compose.yml` files without losing comments or
+//! formatting.
+//!
+//! `File::write` regenerates the entire file from our data structures, so
+//! comments disappear and keys are sorted alphabetically.  A `Document`
+//! instead keeps the original YAML source around.  When we write it out,
+//! we compare the edited `File` against the original one, and apply only
+//! the differences to the source text.  Untouched fields keep their
+//! comments, key order, quoting style and anchors.
+//!
+//! Edits are made at the smallest level we can manage: a changed scalar is
+//! replaced in place, new keys and sequence items are appended to block
+//! collections, and removed keys are deleted.  When a change can't be
+//! expressed this way (for example, because the original value used flow
+//! style, or a shorthand like `build: .` which we normalize), we
+//! regenerate the nearest enclosing mapping entry or sequence item.
+
+use std::fs;
+use std::io;
+use std::iter;
+use std::path::Path;
+use std::str::FromStr;
+use serde_yaml;
+use yaml_rust::Yaml;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::{Marker, TScalarStyle};
+use yaml_rust::yaml::Hash;
+
+use errors::*;
+use super::File;
+use super::lenient::{dump_yaml, load_yaml};
+use super::validate::{validate_file, validate_file_with_source};
+
+/// A `docker-compose.yml` file which remembers the YAML source it was read
+/// from, so that it can be written back out with minimal changes.
+///
+/// ```
+/// use std::str::FromStr;
+/// use compose_yml::v2 as dc;
+///
+/// let yaml = "# Our app\n\
+///             version: \"2\"\n\
+///             services:\n  web:\n    image: 'nginx'\n";
+/// let mut doc = dc::Document::from_str(yaml).unwrap();
+/// doc.file.services.get_mut("web").unwrap().privileged = true;
+/// assert_eq!(doc.render().unwrap(),
+///            format!("{}    privileged: true\n", yaml));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Document {
+    /// The parsed file.  Any changes made here will be applied to the
+    /// original source when the document is written.
+    pub file: File,
+    /// The file as originally parsed, which we compare against `file` to
+    /// find out what changed.
+    original: File,
+    /// The original YAML source.
+    source: String,
+}
+
+impl Document {
+    /// Read a document from an input stream containing YAML.
+    pub fn read<R>(mut r: R) -> Result<Self>
+        where R: io::Read
+    {
+        let mut yaml = String::new();
+        r.read_to_string(&mut yaml).chain_err(|| "I/O error")?;
+        let file: File = serde_yaml::from_str(&yaml)?;
+        validate_file_with_source(&file, Some(&yaml))?;
+        Ok(Document {
+            file: file.clone(),
+            original: file,
+            source: yaml,
+        })
+    }
+
+    /// Read a document from the specified path.
+    pub fn read_from_path<P>(path: P) -> Result<Self>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mkerr = || ErrorKind::ReadFile(path.to_owned());
+        let f = fs::File::open(path).chain_err(&mkerr)?;
+        Self::read(io::BufReader::new(f)).chain_err(&mkerr)
+    }
+
+    /// The original YAML source of this document.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Apply any changes made to `file` to the original source, and return
+    /// the updated YAML.
+    pub fn render(&self) -> Result<String> {
+        validate_file(&self.file)?;
+        let old = file_to_yaml(&self.original)?;
+        let new = file_to_yaml(&self.file)?;
+        if old == new {
+            return Ok(self.source.clone());
+        }
+
+        let tree = parse_source(&self.source)?;
+        let mut editor = Editor {
+            source: &self.source,
+            edits: vec![],
+        };
+        let handled = match (tree.as_ref().map(|root| &root.kind), &old, &new) {
+            (Some(&SourceKind::Mapping { flow: false, ref entries, end }),
+             &Yaml::Hash(ref old),
+             &Yaml::Hash(ref new)) => editor.edit_mapping(old, new, entries, end)?,
+            _ => false,
+        };
+        if handled {
+            if let Some(output) = editor.apply() {
+                return Ok(output);
+            }
+        }
+        // We couldn't edit the source, so just regenerate everything.
+        dump_yaml(&new)
+    }
+
+    /// Write a document to an output stream as YAML.
+    pub fn write<W>(&self, w: &mut W) -> Result<()>
+        where W: io::Write
+    {
+        w.write_all(self.render()?.as_bytes()).chain_err(|| "I/O error")
+    }
+
+    /// Write a document to the specified path.
+    pub fn write_to_path<P>(&self, path: P) -> Result<()>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mkerr = || ErrorKind::WriteFile(path.to_owned());
+        let f = fs::File::create(path).chain_err(&mkerr)?;
+        self.write(&mut io::BufWriter::new(f)).chain_err(&mkerr)
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Document> {
+        Self::read(io::Cursor::new(s))
+    }
+}
+
+/// Convert `file` to a generic YAML tree, in the same form that
+/// `File::write` would produce.
+fn file_to_yaml(file: &File) -> Result<Yaml> {
+    load_yaml(&serde_yaml::to_string(file)?)
+}
+
+/// A node in the original YAML source.
+#[derive(Debug)]
+struct SourceNode {
+    /// The byte offset at which this node starts.
+    start: usize,
+    /// What kind of node is this?
+    kind: SourceKind,
+}
+
+/// The different kinds of `SourceNode`.
+#[derive(Debug)]
+enum SourceKind {
+    /// A scalar value, and the style it was written in.
+    Scalar {
+        value: String,
+        style: TScalarStyle,
+    },
+    /// A reference to an anchor.
+    Alias,
+    /// A mapping.  `end` is the byte offset of the token which follows it.
+    Mapping {
+        flow: bool,
+        entries: Vec<SourceEntry>,
+        end: usize,
+    },
+    /// A sequence.  `end` is the byte offset of the token which follows it.
+    Sequence {
+        flow: bool,
+        items: Vec<SourceNode>,
+        end: usize,
+    },
+}
+
+/// A key-value pair in a `SourceKind::Mapping`.
+#[derive(Debug)]
+struct SourceEntry {
+    /// The key, if it's a scalar.
+    key: Option<String>,
+    /// The byte offset at which the key starts.
+    key_start: usize,
+    /// The value.
+    value: SourceNode,
+}
+
+/// A collection which we're in the middle of parsing.
+#[derive(Debug)]
+enum Partial {
+    /// A mapping, plus the key and start offset of the entry whose value
+    /// we're waiting for, if any.
+    Mapping {
+        start: usize,
+        flow: bool,
+        entries: Vec<SourceEntry>,
+        key: Option<(Option<String>, usize)>,
+    },
+    /// A sequence.
+    Sequence {
+        start: usize,
+        flow: bool,
+        items: Vec<SourceNode>,
+    },
+}
+
+/// Receives events from the YAML parser and builds a tree of
+/// `SourceNode` values.
+#[derive(Debug)]
+struct TreeBuilder<'a> {
+    /// The source we're parsing.
+    source: &'a str,
+    /// The byte offset of each character in `source`, plus the length of
+    /// `source`.  `yaml_rust` reports positions in characters.
+    offsets: Vec<usize>,
+    /// The collections we're currently inside.
+    stack: Vec<Partial>,
+    /// The root node of the first document, once we've seen it.
+    root: Option<SourceNode>,
+}
+
+impl<'a> TreeBuilder<'a> {
+    /// Convert a marker to a byte offset.
+    fn offset(&self, mark: Marker) -> usize {
+        self.offsets.get(mark.index()).cloned().unwrap_or(self.source.len())
+    }
+
+    /// Add a completed node to the current collection.
+    fn finish_node(&mut self, node: SourceNode) {
+        match self.stack.last_mut() {
+            None => {
+                if self.root.is_none() {
+                    self.root = Some(node);
+                }
+            }
+            Some(&mut Partial::Sequence { ref mut items, .. }) => items.push(node),
+            Some(&mut Partial::Mapping { ref mut entries, ref mut key, .. }) => {
+                match key.take() {
+                    Some((k, key_start)) => {
+                        entries.push(SourceEntry {
+                            key: k,
+                            key_start: key_start,
+                            value: node,
+                        })
+                    }
+                    None => {
+                        let k = match node.kind {
+                            SourceKind::Scalar { ref value, .. } => {
+                                Some(value.clone())
+                            }
+                            _ => None,
+                        };
+                        *key = Some((k, node.start));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> MarkedEventReceiver for TreeBuilder<'a> {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        let offset = self.offset(mark);
+        match event {
+            Event::Scalar(value, style, _, _) => {
+                self.finish_node(SourceNode {
+                    start: offset,
+                    kind: SourceKind::Scalar {
+                        value: value,
+                        style: style,
+                    },
+                });
+            }
+            Event::Alias(_) => {
+                self.finish_node(SourceNode {
+                    start: offset,
+                    kind: SourceKind::Alias,
+                });
+            }
+            Event::MappingStart(_) => {
+                self.stack.push(Partial::Mapping {
+                    start: offset,
+                    flow: self.source[offset..].starts_with('{'),
+                    entries: vec![],
+                    key: None,
+                });
+            }
+            Event::SequenceStart(_) => {
+                self.stack.push(Partial::Sequence {
+                    start: offset,
+                    flow: self.source[offset..].starts_with('['),
+                    items: vec![],
+                });
+            }
+            Event::MappingEnd => {
+                if let Some(Partial::Mapping { start, flow, entries, .. }) =
+                    self.stack.pop() {
+                    self.finish_node(SourceNode {
+                        start: start,
+                        kind: SourceKind::Mapping {
+                            flow: flow,
+                            entries: entries,
+                            end: offset,
+                        },
+                    });
+                }
+            }
+            Event::SequenceEnd => {
+                if let Some(Partial::Sequence { start, flow, items }) =
+                    self.stack.pop() {
+                    self.finish_node(SourceNode {
+                        start: start,
+                        kind: SourceKind::Sequence {
+                            flow: flow,
+                            items: items,
+                            end: offset,
+                        },
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse the first document in `source` into a tree of `SourceNode`
+/// values.
+fn parse_source(source: &str) -> Result<Option<SourceNode>> {
+    let mut builder = TreeBuilder {
+        source: source,
+        offsets: source.char_indices()
+            .map(|(i, _)| i)
+            .chain(Some(source.len()))
+            .collect(),
+        stack: vec![],
+        root: None,
+    };
+    let mut parser = Parser::new(source.chars());
+    parser.load(&mut builder, false)
+        .map_err(|err| Error::invalid_value("YAML", format!("{}", err)))?;
+    Ok(builder.root)
+}
+
+/// Render `node` as block-style YAML, without a document header or a
+/// trailing newline.
+fn render_yaml(node: &Yaml) -> Result<String> {
+    let output = dump_yaml(node)?;
+    Ok(output["---\n".len()..].trim_right().to_owned())
+}
+
+/// Indent every line of `text` by `col` spaces, except possibly the first,
+/// and end it with a newline.
+fn indent(text: &str, col: usize, indent_first: bool) -> String {
+    let pad: String = iter::repeat(' ').take(col).collect();
+    let mut output = String::new();
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 || indent_first {
+            output.push_str(&pad);
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
+
+/// Build a mapping containing a single entry.
+fn single_entry(key: Yaml, value: Yaml) -> Yaml {
+    let mut hash = Hash::new();
+    hash.insert(key, value);
+    Yaml::Hash(hash)
+}
+
+/// Quote `s` as a YAML double-quoted string.
+fn double_quote(s: &str) -> String {
+    let mut output = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c.is_control() => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+/// The length of the quoted scalar at the start of `s`, including quotes.
+fn quoted_len(s: &str, quote: char) -> Option<usize> {
+    let mut chars = s.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' && quote == '"' {
+            chars.next();
+        } else if c == quote {
+            // In single-quoted strings, `''` is an escaped quote.
+            if quote == '\'' && chars.peek().map(|&(_, c)| c) == Some('\'') {
+                chars.next();
+            } else {
+                return Some(i + 1);
+            }
+        }
+    }
+    None
+}
+
+/// The length of the plain scalar at the start of `s`, assuming it fits on
+/// a single line.
+fn plain_len(s: &str) -> usize {
+    let line = &s[..s.find('\n').unwrap_or(s.len())];
+    let line = match line.find(" #") {
+        Some(idx) => &line[..idx],
+        None => line,
+    };
+    line.trim_right().len()
+}
+
+/// A single change to our source text.
+#[derive(Debug)]
+struct Edit {
+    /// The first byte to replace.
+    start: usize,
+    /// One past the last byte to replace.
+    end: usize,
+    /// The replacement text.
+    text: String,
+}
+
+/// Computes the edits needed to update a YAML source document.
+#[derive(Debug)]
+struct Editor<'a> {
+    /// The original source.
+    source: &'a str,
+    /// The edits we've decided to make.
+    edits: Vec<Edit>,
+}
+
+impl<'a> Editor<'a> {
+    /// The byte offset of the start of the line containing `pos`.
+    fn line_start(&self, pos: usize) -> usize {
+        self.source[..pos].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// The byte offset of the start of the line after the one containing
+    /// `pos`.
+    fn line_end(&self, pos: usize) -> usize {
+        self.source[pos..].find('\n').map_or(self.source.len(), |i| pos + i + 1)
+    }
+
+    /// The column of `pos`, in characters.
+    fn column(&self, pos: usize) -> usize {
+        self.source[self.line_start(pos)..pos].chars().count()
+    }
+
+    /// Is `pos` preceded by anything other than indentation on its line?
+    /// This happens with mappings nested directly inside sequences.
+    fn is_inline(&self, pos: usize) -> bool {
+        !self.source[self.line_start(pos)..pos].trim().is_empty()
+    }
+
+    /// The start of the region belonging to an entry or item at `pos`.
+    fn region_start(&self, pos: usize) -> usize {
+        if self.is_inline(pos) {
+            pos
+        } else {
+            self.line_start(pos)
+        }
+    }
+
+    /// The end of the region belonging to an entry or item which is
+    /// followed by a token at `next`.  Trailing blank lines and comments
+    /// are excluded, because they usually belong to whatever follows.  We
+    /// never return anything before `min`.
+    fn region_end(&self, next: usize, min: usize) -> usize {
+        let mut pos = if next >= self.source.len() {
+            self.source.len()
+        } else {
+            self.line_start(next)
+        };
+        while pos > min {
+            let start = self.line_start(pos - 1);
+            let line = self.source[start..pos].trim();
+            if line.is_empty() || line.starts_with('#') {
+                pos = start;
+            } else {
+                break;
+            }
+        }
+        if pos < min { min } else { pos }
+    }
+
+    /// If the item at `pos` is preceded by a `-` sequence entry marker,
+    /// return the position of the marker.
+    fn dash_before(&self, pos: usize) -> Option<usize> {
+        let before = self.source[..pos].trim_right_matches(' ');
+        if before.ends_with('-') {
+            Some(before.len() - 1)
+        } else {
+            None
+        }
+    }
+
+    /// Record an edit.
+    fn push(&mut self, start: usize, end: usize, text: String) {
+        self.edits.push(Edit {
+            start: start,
+            end: end,
+            text: text,
+        });
+    }
+
+    /// Insert `text` (which ends in a newline) at `pos`.
+    fn insert(&mut self, pos: usize, text: String) {
+        let text = if pos == self.source.len() && !self.source.ends_with('\n') {
+            format!("\n{}", text)
+        } else {
+            text
+        };
+        self.push(pos, pos, text);
+    }
+
+    /// Replace the region from the entry or item at `pos` to `end` with
+    /// `node`, which should be either a single-entry mapping or a
+    /// single-item sequence.
+    fn replace(&mut self, pos: usize, end: usize, node: &Yaml) -> Result<()> {
+        let inline = self.is_inline(pos);
+        let text = indent(&render_yaml(node)?, self.column(pos), !inline);
+        let start = self.region_start(pos);
+        self.push(start, end, text);
+        Ok(())
+    }
+
+    /// Update the block mapping with `entries` from `old` to `new`.  Returns
+    /// `false` if we can't.
+    fn edit_mapping(&mut self,
+                    old: &Hash,
+                    new: &Hash,
+                    entries: &[SourceEntry],
+                    end: usize)
+                    -> Result<bool> {
+        if new.is_empty() || entries.is_empty() {
+            return Ok(false);
+        }
+
+        for (i, entry) in entries.iter().enumerate() {
+            let key = match entry.key {
+                Some(ref key) => Yaml::String(key.clone()),
+                None => continue,
+            };
+            let next = entries.get(i + 1).map_or(end, |e| e.key_start);
+            let stop = self.region_end(next, self.line_end(entry.key_start));
+            match (old.get(&key), new.get(&key)) {
+                (Some(_), None) => {
+                    // Removing the first key of a mapping nested in a
+                    // sequence would leave the rest misaligned.
+                    if self.is_inline(entry.key_start) {
+                        return Ok(false);
+                    }
+                    let start = self.region_start(entry.key_start);
+                    self.push(start, stop, "".to_owned());
+                }
+                (Some(o), Some(n)) => {
+                    if o != n {
+                        let mark = self.edits.len();
+                        if !self.edit_value(&entry.value, o, n)? {
+                            self.edits.truncate(mark);
+                            self.replace(entry.key_start,
+                                         stop,
+                                         &single_entry(key, n.clone()))?;
+                        }
+                    }
+                }
+                // We didn't serialize this key originally, probably
+                // because it had a default value.
+                (None, Some(n)) => {
+                    self.replace(entry.key_start,
+                                 stop,
+                                 &single_entry(key, n.clone()))?;
+                }
+                (None, None) => {}
+            }
+        }
+
+        // Append any new keys after the last existing entry.
+        let last = &entries[entries.len() - 1];
+        let col = self.column(entries[0].key_start);
+        let mut text = String::new();
+        for (k, v) in new.iter() {
+            let in_source = match *k {
+                Yaml::String(ref s) => {
+                    entries.iter().any(|e| e.key.as_ref() == Some(s))
+                }
+                _ => false,
+            };
+            if !in_source && old.get(k) != Some(v) {
+                let rendered = render_yaml(&single_entry(k.clone(), v.clone()))?;
+                text.push_str(&indent(&rendered, col, true));
+            }
+        }
+        if !text.is_empty() {
+            let pos = self.region_end(end, self.line_end(last.key_start));
+            self.insert(pos, text);
+        }
+        Ok(true)
+    }
+
+    /// Update the block sequence with `items` from `old` to `new`.  We can
+    /// change existing items and append new ones.  Returns `false` if we
+    /// can't make the necessary changes.
+    fn edit_sequence(&mut self,
+                     old: &[Yaml],
+                     new: &[Yaml],
+                     items: &[SourceNode],
+                     end: usize)
+                     -> Result<bool> {
+        if items.is_empty() || items.len() != old.len() || new.len() < old.len() {
+            return Ok(false);
+        }
+        let mut dashes = vec![];
+        for item in items {
+            match self.dash_before(item.start) {
+                Some(dash) => dashes.push(dash),
+                None => return Ok(false),
+            }
+        }
+
+        for (i, item) in items.iter().enumerate() {
+            if old[i] == new[i] {
+                continue;
+            }
+            let mark = self.edits.len();
+            if !self.edit_value(item, &old[i], &new[i])? {
+                self.edits.truncate(mark);
+                let next = dashes.get(i + 1).cloned().unwrap_or(end);
+                let stop = self.region_end(next, self.line_end(dashes[i]));
+                self.replace(dashes[i], stop, &Yaml::Array(vec![new[i].clone()]))?;
+            }
+        }
+
+        if new.len() > old.len() {
+            let appended = Yaml::Array(new[old.len()..].to_vec());
+            let text = indent(&render_yaml(&appended)?, self.column(dashes[0]), true);
+            let pos = self.region_end(end, self.line_end(dashes[dashes.len() - 1]));
+            self.insert(pos, text);
+        }
+        Ok(true)
+    }
+
+    /// Update `node` in place from `old` to `new`.  Returns `false` if we
+    /// can't, in which case the caller should replace the entire entry or
+    /// item.
+    fn edit_value(&mut self,
+                  node: &SourceNode,
+                  old: &Yaml,
+                  new: &Yaml)
+                  -> Result<bool> {
+        match (&node.kind, old, new) {
+            (&SourceKind::Scalar { .. }, _, &Yaml::Hash(_)) |
+            (&SourceKind::Scalar { .. }, _, &Yaml::Array(_)) => Ok(false),
+            (&SourceKind::Scalar { .. }, _, _) => self.edit_scalar(node, new),
+            (&SourceKind::Mapping { flow: false, ref entries, end },
+             &Yaml::Hash(ref old),
+             &Yaml::Hash(ref new)) => self.edit_mapping(old, new, entries, end),
+            (&SourceKind::Sequence { flow: false, ref items, end },
+             &Yaml::Array(ref old),
+             &Yaml::Array(ref new)) => self.edit_sequence(old, new, items, end),
+            _ => Ok(false),
+        }
+    }
+
+    /// Replace the text of the scalar `node` with `new`, keeping the same
+    /// quoting style if possible.  Returns `false` if we can't find the
+    /// scalar's text, which happens with block scalars, multi-line plain
+    /// scalars and empty values.
+    fn edit_scalar(&mut self, node: &SourceNode, new: &Yaml) -> Result<bool> {
+        let (value, style) = match node.kind {
+            SourceKind::Scalar { ref value, style } => (value, style),
+            _ => return Ok(false),
+        };
+        let rest = &self.source[node.start..];
+        let len = match style {
+            TScalarStyle::DoubleQuoted => quoted_len(rest, '"'),
+            TScalarStyle::SingleQuoted => quoted_len(rest, '\''),
+            TScalarStyle::Plain => {
+                let len = plain_len(rest);
+                if &rest[..len] == value { Some(len) } else { None }
+            }
+            _ => None,
+        };
+        let len = match len {
+            Some(len) => len,
+            None => return Ok(false),
+        };
+        let text = match (style, new) {
+            (TScalarStyle::DoubleQuoted, &Yaml::String(ref s)) => double_quote(s),
+            (TScalarStyle::SingleQuoted, &Yaml::String(ref s))
+                if !s.contains('\n') => format!("'{}'", s.replace("'", "''")),
+            _ => render_yaml(new)?,
+        };
+        self.push(node.start, node.start + len, text);
+        Ok(true)
+    }
+
+    /// Apply our edits to the source.  Returns `None` if any of our edits
+    /// overlap, which should never happen.
+    fn apply(mut self) -> Option<String> {
+        self.edits.sort_by(|a, b| (a.start, a.end).cmp(&(b.start, b.end)));
+        let mut output = String::with_capacity(self.source.len());
+        let mut pos = 0;
+        for edit in &self.edits {
+            if edit.start < pos {
+                return None;
+            }
+            output.push_str(&self.source[pos..edit.start]);
+            output.push_str(&edit.text);
+            pos = edit.end;
+        }
+        output.push_str(&self.source[pos..]);
+        Some(output)
+    }
+}
+
+#[test]
+fn document_applies_minimal_edits() {
+    use super::{Image, PortMapping, RestartMode, value};
+
+    let yaml = r#"# My app
+version: "2"
+services:
+  # The web server
+  web:
+    image: 'nginx:1.10'   # pinned
+    environment:
+      - "DEBUG=1"
+    ports:
+      - "80:80"
+
+  db:
+    image: &db_image "postgres"
+"#;
+    let mut doc = Document::from_str(yaml).unwrap();
+    assert_eq!(doc.render().unwrap(), yaml);
+
+    {
+        let web = doc.file.services.get_mut("web").unwrap();
+        web.image = Some(value(Image::new("nginx:1.11").unwrap()));
+        web.environment.clear();
+        web.ports.push(value(PortMapping::new(443, 443)));
+    }
+    {
+        let db = doc.file.services.get_mut("db").unwrap();
+        db.restart = Some(value(RestartMode::Always));
+    }
+
+    assert_eq!(doc.render().unwrap(),
+               r#"# My app
+version: "2"
+services:
+  # The web server
+  web:
+    image: 'nginx:1.11'   # pinned
+    ports:
+      - "80:80"
+      - "443:443"
+
+  db:
+    image: &db_image "postgres"
+    restart: always
+"#);
+}
+
+#[test]
+fn document_regenerates_values_it_cannot_edit_in_place() {
+    use super::value;
+
+    let yaml = r#"version: "2"
+services:
+  app:
+    build: .   # shorthand
+"#;
+    let mut doc = Document::from_str(yaml).unwrap();
+    doc.file.services.get_mut("app").unwrap().build.as_mut().unwrap().dockerfile =
+        Some(value("Dockerfile.dev".to_owned()));
+    let output = doc.render().unwrap();
+    assert!(output.starts_with("version: \"2\"\nservices:\n  app:\n    build:\n"));
+    let reparsed = File::from_str(&output).unwrap();
+    assert_eq!(reparsed, doc.file);
+}
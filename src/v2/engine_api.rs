@@ -0,0 +1,839 @@
+//! Request bodies for the Docker Engine REST API, for tools which talk to
+//! the Docker daemon directly instead of running `docker-compose`.
+//!
+//! We follow `docker-compose`'s naming conventions, so containers are
+//! named `<project>_<service>_1`, and networks and volumes declared in the
+//! file are prefixed with `<project>_`.  All fields must already be
+//! interpolated, and relative bind mounts should be made absolute using
+//! `File::absolutize_paths`, or they will be reported as unsupported.
+
+use serde_json::value::Value;
+
+use errors::*;
+use super::{File, Healthcheck, HostVolume, MountType, Network, NetworkMode,
+            Ports, RestartMode, Service, ServiceOrContainer, Volume, VolumeMount,
+            VolumePermissions, parse_duration};
+use super::docker_run::command_line_args;
+use super::json_object::{JsonObject, strings};
+use super::project::{container_name, network_name, volume_name};
+use super::string_or_struct::LongSyntax;
+use super::validate::escape_pointer_segment;
+
+/// The label `docker-compose` uses to record the project name.
+const PROJECT_LABEL: &'static str = "com.docker.compose.project";
+
+/// The body of a `POST /containers/create` request, including `Config`
+/// fields at the top level, `HostConfig` and `NetworkingConfig`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerCreate {
+    /// The name of the container, which is passed as the `name` query
+    /// parameter.
+    pub name: String,
+    /// The JSON request body.
+    pub body: Value,
+    /// JSON pointers to every field of the service which can't be
+    /// expressed in a `ContainerCreate` request, such as
+    /// `/services/web/build`.
+    pub unsupported: Vec<String>,
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    pub _hidden: (),
+}
+
+impl ContainerCreate {
+    /// Build the request for the service named `name`, which is part of
+    /// `file`.  We need `file` to look up the Docker names of the networks
+    /// and volumes used by the service.
+    pub fn for_service(project: &str,
+                       name: &str,
+                       service: &Service,
+                       file: &File)
+                       -> Result<ContainerCreate> {
+        let mut builder = ContainerBuilder {
+            project: project,
+            name: name,
+            file: file,
+            config: JsonObject::new(),
+            host_config: JsonObject::new(),
+            unsupported: vec![],
+        };
+        builder.add_service(service)?;
+        let networking_config = builder.networking_config(service)?;
+
+        let mut body = builder.config;
+        body.set_object("HostConfig", builder.host_config);
+        body.set_object("NetworkingConfig", networking_config);
+        Ok(ContainerCreate {
+            name: container_name(project, name, service)?,
+            body: body.into_value(),
+            unsupported: builder.unsupported,
+            _hidden: (),
+        })
+    }
+}
+
+/// The body of a `POST /networks/create` request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkCreate {
+    /// The JSON request body.
+    pub body: Value,
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    pub _hidden: (),
+}
+
+impl NetworkCreate {
+    /// Build the request for the network `name` from the top-level
+    /// `networks` section.  Returns `None` for external networks, which
+    /// must already exist.
+    pub fn for_network(project: &str,
+                       name: &str,
+                       network: &Network)
+                       -> Result<Option<NetworkCreate>> {
+        if network.external.is_some() {
+            return Ok(None);
+        }
+        let mut body = JsonObject::new();
+        body.set("Name", format!("{}_{}", project, name));
+        body.set("CheckDuplicate", true);
+        body.set_opt("Driver", &network.driver)?;
+        body.set_map("Options", &network.driver_opts)?;
+        if network.internal {
+            body.set("Internal", true);
+        }
+        if network.enable_ipv6 {
+            body.set("EnableIPv6", true);
+        }
+        let mut labels = JsonObject::from_map(&network.labels)?;
+        labels.set(PROJECT_LABEL, project);
+        labels.set("com.docker.compose.network", name);
+        body.set_object("Labels", labels);
+        Ok(Some(NetworkCreate { body: body.into_value(), _hidden: () }))
+    }
+
+    /// Build the request for the implicit `default` network, which
+    /// `docker-compose` creates for services which don't list any
+    /// networks.
+    pub fn default_network(project: &str) -> NetworkCreate {
+        NetworkCreate::for_network(project, "default", &Network::default())
+            .expect("default network should always be valid")
+            .expect("default network should never be external")
+    }
+}
+
+/// The body of a `POST /volumes/create` request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeCreate {
+    /// The JSON request body.
+    pub body: Value,
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    pub _hidden: (),
+}
+
+impl VolumeCreate {
+    /// Build the request for the volume `name` from the top-level
+    /// `volumes` section.  Returns `None` for external volumes, which must
+    /// already exist.
+    pub fn for_volume(project: &str,
+                      name: &str,
+                      volume: &Volume)
+                      -> Result<Option<VolumeCreate>> {
+        if volume.external == Some(true) {
+            return Ok(None);
+        }
+        let mut body = JsonObject::new();
+        body.set("Name", format!("{}_{}", project, name));
+        body.set_opt("Driver", &volume.driver)?;
+        body.set_map("DriverOpts", &volume.driver_opts)?;
+        let mut labels = JsonObject::from_map(&volume.labels)?;
+        labels.set(PROJECT_LABEL, project);
+        labels.set("com.docker.compose.volume", name);
+        body.set_object("Labels", labels);
+        Ok(Some(VolumeCreate { body: body.into_value(), _hidden: () }))
+    }
+}
+
+/// Accumulates a `ContainerCreate`.
+struct ContainerBuilder<'a> {
+    /// Our project name.
+    project: &'a str,
+    /// The name of the service we're translating.
+    name: &'a str,
+    /// The file containing the service.
+    file: &'a File,
+    /// Top-level `Config` fields.
+    config: JsonObject,
+    /// `HostConfig` fields.
+    host_config: JsonObject,
+    /// JSON pointers to fields we couldn't translate.
+    unsupported: Vec<String>,
+}
+
+impl<'a> ContainerBuilder<'a> {
+    /// The JSON pointer to `field` in our service.
+    fn pointer(&self, field: &str) -> String {
+        format!("/services/{}/{}", escape_pointer_segment(self.name), field)
+    }
+
+    /// Record that `field` can't be expressed in the request.
+    fn unsupported(&mut self, field: &str) {
+        let pointer = self.pointer(field);
+        self.unsupported.push(pointer);
+    }
+
+    /// An error for a `field` which refers to an undefined `kind` named
+    /// `name`.
+    fn undefined(&self, field: &str, kind: &str, name: &str) -> Error {
+        ErrorKind::UndefinedReference(self.pointer(field),
+                                      kind.to_owned(),
+                                      name.to_owned())
+            .into()
+    }
+
+    /// The container name of another service in this file.
+    fn service_container(&self, service: &str) -> Result<String> {
+        match self.file.services.get(service) {
+            Some(other) => container_name(self.project, service, other),
+            None => Ok(format!("{}_{}_1", self.project, service)),
+        }
+    }
+
+    /// Translate every field of `service` except networks.
+    fn add_service(&mut self, service: &Service) -> Result<()> {
+        // Fields which only make sense to `docker-compose`.
+        if service.build.is_some() {
+            self.unsupported("build");
+        }
+        if !service.configs.is_empty() {
+            self.unsupported("configs");
+        }
+        if !service.secrets.is_empty() {
+            self.unsupported("secrets");
+        }
+        if !service.depends_on.is_empty() {
+            self.unsupported("depends_on");
+        }
+        if service.deploy.is_some() {
+            self.unsupported("deploy");
+        }
+        if !service.env_files.is_empty() {
+            // Call `File::inline_all` first to merge these into
+            // `environment`.
+            self.unsupported("env_file");
+        }
+        if service.extends.is_some() {
+            self.unsupported("extends");
+        }
+        if service.scone.mrenclave.is_some() {
+            self.unsupported("mrenclave");
+        }
+        if service.scone.fspf_path.is_some() {
+            self.unsupported("fspf_path");
+        }
+        if service.scone.fspf_key.is_some() {
+            self.unsupported("fspf_key");
+        }
+        if service.scone.fspf_tag.is_some() {
+            self.unsupported("fspf_tag");
+        }
+        if service.scone.not_scone.is_some() {
+            self.unsupported("not_scone");
+        }
+
+        self.add_config(service)?;
+        self.add_host_config(service)?;
+        self.add_mounts(service)?;
+        Ok(())
+    }
+
+    /// Translate fields which belong in `Config`.
+    fn add_config(&mut self, service: &Service) -> Result<()> {
+        match service.image {
+            Some(ref image) => {
+                let image = image.value()?.to_string();
+                self.config.set("Image", image);
+            }
+            None => return Err(ErrorKind::MissingImage(self.name.to_owned()).into()),
+        }
+        self.config.set_opt("Hostname", &service.hostname)?;
+        self.config.set_opt("Domainname", &service.domainname)?;
+        self.config.set_opt("User", &service.user)?;
+        self.config.set_opt("WorkingDir", &service.working_dir)?;
+        self.config.set_opt("MacAddress", &service.mac_address)?;
+        self.config.set_opt("StopSignal", &service.stop_signal)?;
+        if service.tty {
+            self.config.set("Tty", true);
+        }
+        if service.stdin_open {
+            self.config.set("OpenStdin", true);
+        }
+
+        let mut env = vec![];
+        for (key, value) in &service.environment {
+            env.push(format!("{}={}", key, value.value()?));
+        }
+        self.config.set_list("Env", env);
+
+        if let Some(ref entrypoint) = service.entrypoint {
+            let entrypoint = command_line_args(entrypoint)?;
+            self.config.set("Entrypoint", entrypoint);
+        }
+        if let Some(ref command) = service.command {
+            let command = command_line_args(command)?;
+            self.config.set("Cmd", command);
+        }
+        if let Some(ref healthcheck) = service.healthcheck {
+            self.config.set_object("Healthcheck", healthcheck_object(healthcheck)?);
+        }
+
+        let mut labels = JsonObject::from_map(&service.labels)?;
+        labels.set(PROJECT_LABEL, self.project);
+        labels.set("com.docker.compose.service", self.name);
+        labels.set("com.docker.compose.container-number", "1");
+        labels.set("com.docker.compose.oneoff", "False");
+        self.config.set_object("Labels", labels);
+
+        let mut exposed = JsonObject::new();
+        for expose in &service.expose {
+            let expose = expose.value()?;
+            let key = if expose.contains('/') {
+                expose.to_owned()
+            } else {
+                format!("{}/tcp", expose)
+            };
+            exposed.set(&key, JsonObject::new().into_value());
+        }
+        for port in &service.ports {
+            let port = port.value()?;
+            for key in port_keys(&port.container_ports, &port.protocol) {
+                exposed.set(&key, JsonObject::new().into_value());
+            }
+        }
+        self.config.set_object("ExposedPorts", exposed);
+        Ok(())
+    }
+
+    /// Translate fields which belong in `HostConfig`.
+    fn add_host_config(&mut self, service: &Service) -> Result<()> {
+        if service.privileged {
+            self.host_config.set("Privileged", true);
+        }
+        if service.init == Some(true) {
+            self.host_config.set("Init", true);
+        }
+        self.host_config.set_list("CapAdd", strings(&service.cap_add)?);
+        self.host_config.set_list("CapDrop", strings(&service.cap_drop)?);
+        self.host_config.set_list("SecurityOpt", strings(&service.security_opt)?);
+        self.host_config.set_list("Dns", strings(&service.dns)?);
+        self.host_config.set_list("DnsSearch", strings(&service.dns_search)?);
+        self.host_config.set_list("ExtraHosts", strings(&service.extra_hosts)?);
+        self.host_config.set_list("GroupAdd", service.group_add.clone());
+        self.host_config.set_opt("Isolation", &service.isolation)?;
+        self.host_config.set_opt("CgroupParent", &service.cgroup_parent)?;
+        self.host_config.set_opt("VolumeDriver", &service.volume_driver)?;
+        if let Some(shares) = service.cpu_shares {
+            self.host_config.set("CpuShares", shares);
+        }
+        if let Some(quota) = service.cpu_quota {
+            self.host_config.set("CpuQuota", quota);
+        }
+        if let Some(ref mem) = service.mem_limit {
+            self.host_config.set("Memory", mem.value()?.to_bytes());
+        }
+        if let Some(ref mem) = service.memswap_limit {
+            self.host_config.set("MemorySwap", mem.value()?.to_bytes());
+        }
+        if let Some(ref mem) = service.shm_size {
+            self.host_config.set("ShmSize", mem.value()?.to_bytes());
+        }
+        if let Some(score) = service.oom_score_adj {
+            self.host_config.set("OomScoreAdj", score);
+        }
+
+        let mut devices = vec![];
+        for device in &service.devices {
+            let device = device.value()?;
+            let mut obj = JsonObject::new();
+            obj.set("PathOnHost", &device.name);
+            obj.set("PathInContainer", device.alias.as_ref().unwrap_or(&device.name));
+            obj.set("CgroupPermissions", "rwm");
+            devices.push(obj.into_value());
+        }
+        if !devices.is_empty() {
+            self.host_config.set("Devices", devices);
+        }
+
+        // Links to services use the other service's container name, and
+        // default to an alias of the service name.
+        let mut links = vec![];
+        for link in &service.links {
+            let link = link.value()?;
+            let alias = link.alias.as_ref().unwrap_or(&link.name);
+            links.push(format!("{}:{}", self.service_container(&link.name)?, alias));
+        }
+        links.extend(strings(&service.external_links)?);
+        self.host_config.set_list("Links", links);
+
+        if let Some(ref restart) = service.restart {
+            let mut policy = JsonObject::new();
+            match *restart.value()? {
+                RestartMode::No => policy.set("Name", ""),
+                RestartMode::OnFailure(retries) => {
+                    policy.set("Name", "on-failure");
+                    if let Some(retries) = retries {
+                        policy.set("MaximumRetryCount", retries);
+                    }
+                }
+                RestartMode::Always => policy.set("Name", "always"),
+                RestartMode::UnlessStopped => policy.set("Name", "unless-stopped"),
+            }
+            self.host_config.set_object("RestartPolicy", policy);
+        }
+
+        if let Some(ref logging) = service.logging {
+            let mut log_config = JsonObject::new();
+            log_config.set_opt("Type", &logging.driver)?;
+            log_config.set_map("Config", &logging.options)?;
+            self.host_config.set_object("LogConfig", log_config);
+        }
+
+        if let Some(ref pid) = service.pid {
+            let pid = pid.value()?.to_string();
+            self.host_config.set("PidMode", pid);
+        }
+        if let Some(ref ipc) = service.ipc {
+            let ipc = ipc.value()?.to_string();
+            self.host_config.set("IpcMode", ipc);
+        }
+
+        let mut bindings = JsonObject::new();
+        for port in &service.ports {
+            let port = port.value()?;
+            let host_ports: Vec<Option<u16>> = match port.host_ports {
+                Some(ref ports) => port_numbers(ports).into_iter().map(Some).collect(),
+                None => vec![None; port_numbers(&port.container_ports).len()],
+            };
+            let keys = port_keys(&port.container_ports, &port.protocol);
+            for (key, host_port) in keys.into_iter().zip(host_ports) {
+                let mut binding = JsonObject::new();
+                let host_ip = port.host_address.map(|a| a.to_string());
+                binding.set("HostIp", host_ip.unwrap_or_default());
+                let host_port = host_port.map(|p| p.to_string());
+                binding.set("HostPort", host_port.unwrap_or_default());
+                let mut list = match bindings.remove(&key) {
+                    Some(Value::Array(list)) => list,
+                    _ => vec![],
+                };
+                list.push(binding.into_value());
+                bindings.set(&key, list);
+            }
+        }
+        self.host_config.set_object("PortBindings", bindings);
+        Ok(())
+    }
+
+    /// Translate `volumes`, `volumes_from` and `tmpfs`.
+    fn add_mounts(&mut self, service: &Service) -> Result<()> {
+        let mut binds = vec![];
+        let mut mounts = vec![];
+        let mut anonymous = JsonObject::new();
+        for (i, mount) in service.volumes.iter().enumerate() {
+            let mount = mount.value()?;
+            let source = match mount.host {
+                Some(HostVolume::Name(ref name)) => {
+                    match volume_name(self.project, name, self.file) {
+                        Some(docker_name) => Some(docker_name),
+                        None => {
+                            let field = format!("volumes/{}", i);
+                            return Err(self.undefined(&field, "volume", name));
+                        }
+                    }
+                }
+                // The daemon only accepts absolute bind sources, and
+                // won't expand `~`, so we can't pass relative paths along.
+                Some(HostVolume::Path(ref path)) if !path.is_absolute() => {
+                    self.unsupported(&format!("volumes/{}", i));
+                    continue;
+                }
+                Some(HostVolume::UserRelativePath(_)) => {
+                    self.unsupported(&format!("volumes/{}", i));
+                    continue;
+                }
+                Some(ref host) => Some(host.to_string()),
+                None => None,
+            };
+            if mount.requires_long_syntax() {
+                mounts.push(mount_object(mount, source).into_value());
+            } else if let Some(source) = source {
+                let mut bind = format!("{}:{}", source, &mount.container);
+                if mount.permissions != VolumePermissions::default() {
+                    bind.push_str(&format!(":{}", mount.permissions));
+                }
+                binds.push(bind);
+            } else {
+                anonymous.set(&mount.container, JsonObject::new().into_value());
+            }
+        }
+        self.config.set_object("Volumes", anonymous);
+        self.host_config.set_list("Binds", binds);
+        if !mounts.is_empty() {
+            self.host_config.set("Mounts", mounts);
+        }
+
+        let mut volumes_from = vec![];
+        for from in &service.volumes_from {
+            let from = from.value()?;
+            let mut source = match from.source {
+                ServiceOrContainer::Service(ref name) => self.service_container(name)?,
+                ServiceOrContainer::Container(ref name) => name.to_owned(),
+            };
+            if from.permissions != VolumePermissions::default() {
+                source.push_str(&format!(":{}", from.permissions));
+            }
+            volumes_from.push(source);
+        }
+        self.host_config.set_list("VolumesFrom", volumes_from);
+
+        let mut tmpfs = JsonObject::new();
+        for path in &service.tmpfs {
+            tmpfs.set(path.value()?, "");
+        }
+        self.host_config.set_object("Tmpfs", tmpfs);
+        Ok(())
+    }
+
+    /// Build `NetworkingConfig`, and set `HostConfig.NetworkMode`.
+    fn networking_config(&mut self, service: &Service) -> Result<JsonObject> {
+        let mut endpoints = JsonObject::new();
+        if let Some(ref mode) = service.network_mode {
+            let mode = match *mode.value()? {
+                NetworkMode::Service(ref name) => {
+                    format!("container:{}", self.service_container(name)?)
+                }
+                ref mode => mode.to_string(),
+            };
+            self.host_config.set("NetworkMode", mode);
+        } else {
+            // Services which don't list any networks join `default`.
+            let default_networks = vec!["default".to_owned()];
+            let names: Vec<String> = if service.networks.is_empty() {
+                default_networks
+            } else {
+                service.networks.keys().cloned().collect()
+            };
+            for (i, name) in names.iter().enumerate() {
+                let docker_name = match network_name(self.project, name, self.file) {
+                    Some(docker_name) => docker_name?,
+                    None => {
+                        let segment = escape_pointer_segment(name);
+                        let field = format!("networks/{}", segment);
+                        return Err(self.undefined(&field, "network", name));
+                    }
+                };
+                if i == 0 {
+                    self.host_config.set("NetworkMode", &docker_name);
+                }
+                let mut aliases = vec![self.name.to_owned()];
+                if let Some(interface) = service.networks.get(name) {
+                    aliases.extend(strings(&interface.aliases)?);
+                }
+                let mut endpoint = JsonObject::new();
+                endpoint.set("Aliases", aliases);
+                endpoints.set_object(&docker_name, endpoint);
+            }
+        }
+        let mut networking_config = JsonObject::new();
+        networking_config.set_object("EndpointsConfig", endpoints);
+        Ok(networking_config)
+    }
+}
+
+/// The individual port numbers in `ports`.
+fn port_numbers(ports: &Ports) -> Vec<u16> {
+    match *ports {
+        Ports::Port(port) => vec![port],
+        Ports::Range(first, last) => (first..last + 1).collect(),
+    }
+}
+
+/// Keys of the form `80/tcp`, as used by `ExposedPorts` and
+/// `PortBindings`.
+fn port_keys(ports: &Ports, protocol: &Option<String>) -> Vec<String> {
+    let protocol = protocol.as_ref().map(|p| &p[..]).unwrap_or("tcp");
+    port_numbers(ports).into_iter().map(|p| format!("{}/{}", p, protocol)).collect()
+}
+
+/// Build the `Healthcheck` object, which measures durations in
+/// nanoseconds.
+fn healthcheck_object(healthcheck: &Healthcheck) -> Result<JsonObject> {
+    let mut obj = JsonObject::new();
+    if let Some(test) = healthcheck.docker_test()? {
+        obj.set("Test", test);
+    }
+    let durations = [("Interval", &healthcheck.interval),
+                     ("Timeout", &healthcheck.timeout),
+                     ("StartPeriod", &healthcheck.start_period)];
+    for &(key, duration) in &durations {
+        if let Some(ref duration) = *duration {
+            let duration = parse_duration(duration.value()?)?;
+            let nanos = duration.as_secs() * 1_000_000_000 +
+                        u64::from(duration.subsec_nanos());
+            obj.set(key, nanos);
+        }
+    }
+    if let Some(retries) = healthcheck.retries {
+        obj.set("Retries", retries);
+    }
+    Ok(obj)
+}
+
+/// Build an entry in `HostConfig.Mounts`.
+fn mount_object(mount: &VolumeMount, source: Option<String>) -> JsonObject {
+    let mount_type = mount.effective_mount_type();
+    let mut obj = JsonObject::new();
+    obj.set("Type", mount_type.to_string());
+    if let Some(source) = source {
+        obj.set("Source", source);
+    }
+    obj.set("Target", &mount.container);
+    if mount.permissions == VolumePermissions::ReadOnly {
+        obj.set("ReadOnly", true);
+    }
+    if let Some(ref consistency) = mount.consistency {
+        obj.set("Consistency", consistency);
+    }
+    if let Some(ref propagation) = mount.propagation {
+        let mut bind = JsonObject::new();
+        bind.set("Propagation", propagation);
+        obj.set_object("BindOptions", bind);
+    }
+    if mount.nocopy && mount_type == MountType::Volume {
+        let mut volume = JsonObject::new();
+        volume.set("NoCopy", true);
+        obj.set_object("VolumeOptions", volume);
+    }
+    if let Some(size) = mount.tmpfs_size {
+        let mut tmpfs = JsonObject::new();
+        tmpfs.set("SizeBytes", size);
+        obj.set_object("TmpfsOptions", tmpfs);
+    }
+    obj
+}
+
+#[cfg(test)]
+fn assert_json_eq(actual: &Value, expected: &str) {
+    use serde_json;
+    let expected: Value = serde_json::from_str(expected).unwrap();
+    assert_eq!(actual, &expected);
+}
+
+#[test]
+fn container_create_matches_fixture() {
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "2"
+services:
+  db:
+    image: "postgres:11"
+  web:
+    image: "example/web:1.0"
+    command: ["rails", "server"]
+    environment:
+      RAILS_ENV: "production"
+    labels:
+      com.example.team: "web"
+    cap_add: ["NET_ADMIN"]
+    links: ["db"]
+    mem_limit: "512m"
+    restart: "on-failure:3"
+    ports: ["8080:80", "53:53/udp"]
+    volumes: ["/srv/web:/app:ro", "data:/data", "/cache"]
+    networks:
+      front:
+        aliases: ["www"]
+volumes:
+  data: {}
+networks:
+  front: {}
+"#)
+        .unwrap();
+    let web = file.services.get("web").unwrap();
+    let req = ContainerCreate::for_service("proj", "web", web, &file).unwrap();
+    assert_eq!(req.name, "proj_web_1");
+    assert!(req.unsupported.is_empty());
+    assert_json_eq(&req.body, r#"{
+  "Image": "example/web:1.0",
+  "Cmd": ["rails", "server"],
+  "Env": ["RAILS_ENV=production"],
+  "Labels": {
+    "com.docker.compose.container-number": "1",
+    "com.docker.compose.oneoff": "False",
+    "com.docker.compose.project": "proj",
+    "com.docker.compose.service": "web",
+    "com.example.team": "web"
+  },
+  "ExposedPorts": {"80/tcp": {}, "53/udp": {}},
+  "Volumes": {"/cache": {}},
+  "HostConfig": {
+    "CapAdd": ["NET_ADMIN"],
+    "Memory": 536870912,
+    "Links": ["proj_db_1:db"],
+    "RestartPolicy": {"Name": "on-failure", "MaximumRetryCount": 3},
+    "PortBindings": {
+      "80/tcp": [{"HostIp": "", "HostPort": "8080"}],
+      "53/udp": [{"HostIp": "", "HostPort": "53"}]
+    },
+    "Binds": ["/srv/web:/app:ro", "proj_data:/data"],
+    "NetworkMode": "proj_front"
+  },
+  "NetworkingConfig": {
+    "EndpointsConfig": {
+      "proj_front": {"Aliases": ["web", "www"]}
+    }
+  }
+}"#);
+
+    // Services without networks join the default network.
+    let db = file.services.get("db").unwrap();
+    let req = ContainerCreate::for_service("proj", "db", db, &file).unwrap();
+    assert_eq!(req.body.pointer("/HostConfig/NetworkMode").unwrap().as_str(),
+               Some("proj_default"));
+}
+
+#[test]
+fn container_create_reports_relative_binds() {
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "3.2"
+services:
+  web:
+    image: "example/web"
+    volumes:
+      - "./src:/app"
+      - "~/cache:/cache"
+      - type: "bind"
+        source: "./static"
+        target: "/static"
+      - "/srv/logs:/logs"
+"#)
+        .unwrap();
+    let web = file.services.get("web").unwrap();
+    let req = ContainerCreate::for_service("proj", "web", web, &file).unwrap();
+    assert_eq!(req.unsupported,
+               vec!["/services/web/volumes/0",
+                    "/services/web/volumes/1",
+                    "/services/web/volumes/2"]);
+    assert_json_eq(&req.body, r#"{
+  "Image": "example/web",
+  "Labels": {
+    "com.docker.compose.container-number": "1",
+    "com.docker.compose.oneoff": "False",
+    "com.docker.compose.project": "proj",
+    "com.docker.compose.service": "web"
+  },
+  "HostConfig": {
+    "Binds": ["/srv/logs:/logs"],
+    "NetworkMode": "proj_default"
+  },
+  "NetworkingConfig": {
+    "EndpointsConfig": {
+      "proj_default": {"Aliases": ["web"]}
+    }
+  }
+}"#);
+}
+
+#[test]
+fn container_create_includes_healthchecks() {
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "3.4"
+services:
+  web:
+    image: "example/web"
+    healthcheck:
+      test: "curl -f http://localhost/"
+      interval: 1m30s
+      timeout: 500ms
+      retries: 3
+      start_period: 10s
+"#)
+        .unwrap();
+    let web = file.services.get("web").unwrap();
+    let req = ContainerCreate::for_service("proj", "web", web, &file).unwrap();
+    assert!(req.unsupported.is_empty());
+    assert_json_eq(req.body.pointer("/Healthcheck").unwrap(), r#"{
+  "Test": ["CMD-SHELL", "curl -f http://localhost/"],
+  "Interval": 90000000000,
+  "Timeout": 500000000,
+  "StartPeriod": 10000000000,
+  "Retries": 3
+}"#);
+}
+
+#[test]
+fn network_and_volume_create_match_fixtures() {
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "2"
+services:
+  web:
+    image: "nginx"
+volumes:
+  data:
+    driver: "local"
+  shared:
+    external: true
+networks:
+  back:
+    internal: true
+  outside:
+    external:
+      name: "corp_net"
+"#)
+        .unwrap();
+
+    let back = file.networks.get("back").unwrap();
+    let req = NetworkCreate::for_network("proj", "back", back).unwrap().unwrap();
+    assert_json_eq(&req.body, r#"{
+  "Name": "proj_back",
+  "CheckDuplicate": true,
+  "Internal": true,
+  "Labels": {
+    "com.docker.compose.network": "back",
+    "com.docker.compose.project": "proj"
+  }
+}"#);
+    let outside = file.networks.get("outside").unwrap();
+    assert!(NetworkCreate::for_network("proj", "outside", outside).unwrap().is_none());
+    let default = NetworkCreate::default_network("proj");
+    assert_eq!(default.body.pointer("/Name").unwrap().as_str(),
+               Some("proj_default"));
+
+    let data = file.volumes.get("data").unwrap();
+    let req = VolumeCreate::for_volume("proj", "data", data).unwrap().unwrap();
+    assert_json_eq(&req.body, r#"{
+  "Name": "proj_data",
+  "Driver": "local",
+  "Labels": {
+    "com.docker.compose.project": "proj",
+    "com.docker.compose.volume": "data"
+  }
+}"#);
+    let shared = file.volumes.get("shared").unwrap();
+    assert!(VolumeCreate::for_volume("proj", "shared", shared).unwrap().is_none());
+}
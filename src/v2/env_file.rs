@@ -1,24 +1,24 @@
 //! Support for parsing the files pointed to by `env_file:`.
 
 use regex::Regex;
-use std::collections::BTreeMap;
 use std::fs;
 use std::io::{self, BufRead};
 use std::path::Path;
 
 use errors::*;
 use super::interpolation::{RawOr, escape};
+use super::map::Map;
 
 /// A file pointed to by an `env_file:` field.
 pub struct EnvFile {
     /// The variables found in our env file.
-    vars: BTreeMap<String, String>,
+    vars: Map<String, String>,
 }
 
 impl EnvFile {
     /// Read an `EnvFile` from a stream.
     pub fn read<R: io::Read>(input: R) -> Result<EnvFile> {
-        let mut vars: BTreeMap<String, String> = BTreeMap::new();
+        let mut vars: Map<String, String> = Map::new();
         let reader = io::BufReader::new(input);
         for line_result in reader.lines() {
             let line = line_result.chain_err(|| "I/O error")?;
@@ -52,8 +52,8 @@ impl EnvFile {
 
     /// Convert this `EnvFile` to the format we use for the `environment`
     /// member of `Service`.
-    pub fn to_environment(&self) -> Result<BTreeMap<String, RawOr<String>>> {
-        let mut env = BTreeMap::new();
+    pub fn to_environment(&self) -> Result<Map<String, RawOr<String>>> {
+        let mut env = Map::new();
         for (k, v) in &self.vars {
             env.insert(k.to_owned(), escape(v)?);
         }
@@ -64,7 +64,7 @@ impl EnvFile {
     // `Service::environment` to have values of `RawOr<String>`.
     //
     // /// Convert to a valid `Service::environment` value.
-    // pub fn to_env(&self) -> &BTreeMap<String, RawOr<String>> {
+    // pub fn to_env(&self) -> &Map<String, RawOr<String>> {
     // }
 }
 
@@ -6,28 +6,39 @@
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct File {
-    /// The version of the `docker-compose.yml` file format.  Must be 2.
-    pub version: String,
+    /// The version of the `docker-compose.yml` file format.  Files which
+    /// omit the version follow the unversioned Compose Spec.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<Version>,
+
+    /// The name of this project.  Only supported by the Compose Spec.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<RawOr<String>>,
 
     /// The individual services which make up this app.
-    pub services: BTreeMap<String, Service>,
+    pub services: Map<String, Service>,
 
     /// Named volumes used by this app.
     ///
     /// TODO MED: Can we parse just volume names followed by a colon?
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
+    #[serde(default, skip_serializing_if = "Map::is_empty",
             deserialize_with = "deserialize_map_struct_or_null")]
-    pub volumes: BTreeMap<String, Volume>,
+    pub volumes: Map<String, Volume>,
 
     /// The networks used by this app.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
+    #[serde(default, skip_serializing_if = "Map::is_empty",
             deserialize_with = "deserialize_map_struct_or_null")]
-    pub networks: BTreeMap<String, Network>,
+    pub networks: Map<String, Network>,
 
     /// secrets used by this app.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
+    #[serde(default, skip_serializing_if = "Map::is_empty",
            deserialize_with = "deserialize_map_struct_or_null")]
-    pub secrets: BTreeMap<String, Secret>,
+    pub secrets: Map<String, Secret>,
+
+    /// Configuration files used by this app.
+    #[serde(default, skip_serializing_if = "Map::is_empty",
+            deserialize_with = "deserialize_map_struct_or_null")]
+    pub configs: Map<String, Config>,
 
     /// PRIVATE.  Mark this struct as having unknown fields for future
     /// compatibility.  This prevents direct construction and exhaustive
@@ -39,20 +50,31 @@ pub struct File {
 }
 
 derive_standard_impls_for!(File, {
-    version, services, volumes, networks, secrets, _hidden
+    version, name, services, volumes, networks, secrets, configs, _hidden
 });
 
 impl File {
-    /// Read a file from an input stream containing YAML.
-    pub fn read<R>(r: R) -> Result<Self>
+    /// Read a file from an input stream containing YAML.  If the file
+    /// does not conform to the schema, the error will include the line and
+    /// column of each problem.
+    ///
+    /// We validate the file using `Validator::validate_with_source`, which
+    /// goes beyond the official schemas: it also rejects fields which need
+    /// a newer version of the format, references to undeclared configs and
+    /// secrets, and generated secrets whose length is out of range for
+    /// their kind.
+    pub fn read<R>(mut r: R) -> Result<Self>
         where R: io::Read
     {
-        let file = serde_yaml::from_reader(r)?;
-        validate_file(&file)?;
+        let mut yaml = String::new();
+        r.read_to_string(&mut yaml).chain_err(|| "I/O error")?;
+        let file = serde_yaml::from_str(&yaml)?;
+        validate_file_with_source(&file, Some(&yaml))?;
         Ok(file)
     }
 
-    /// Write a file to an output stream as YAML.
+    /// Write a file to an output stream as YAML, after validating it the
+    /// same way as `read`.
     pub fn write<W>(&self, w: &mut W) -> Result<()>
         where W: io::Write
     {
@@ -82,16 +104,47 @@ impl File {
 
     /// Inline all our external resources, such as `env_files`, looking up
     /// paths relative to `base`.
+    ///
+    /// If this file follows the Compose Spec (and therefore has no
+    /// `version`), we also embed the contents of any `configs` with a
+    /// `file`.  Numbered versions have no way to embed configs, so we
+    /// leave them alone.
     pub fn inline_all(&mut self, base: &Path) -> Result<()> {
-        for service in self.services.values_mut() {
+        for (_, service) in self.services.iter_mut() {
             service.inline_all(base)?;
         }
+        if self.version.is_none() {
+            for (_, config) in self.configs.iter_mut() {
+                config.inline_content(base)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the contents of every secret with a `file`, looking up paths
+    /// relative to `base`.  Each file must exist, must not be readable by
+    /// every user, and must be no larger than `MAX_SECRET_SIZE`.  See
+    /// `Secret::inline_content`.
+    pub fn inline_secrets(&mut self, base: &Path) -> Result<()> {
+        for (_, secret) in self.secrets.iter_mut() {
+            secret.inline_content(base)?;
+        }
+        Ok(())
+    }
+
+    /// Check that every secret with a `file` points to a file which exists
+    /// and which isn't readable by every user.  Paths are looked up
+    /// relative to `base`.
+    pub fn check_secrets(&self, base: &Path) -> Result<()> {
+        for secret in self.secrets.values() {
+            secret.check_file(base)?;
+        }
         Ok(())
     }
 
     /// Convert this file to a standalone file, with no dependencies on the
     /// current environment or any external files.  This does _not_ lock
-    /// down the image versions used in this file.
+    /// down the image versions used in this file; see `pin_images`.
     pub fn make_standalone(&mut self, base: &Path) -> Result<()> {
         // We need to interpolate first, in case there are environment
         // variables being used to construct the paths to `env_files`
@@ -99,16 +152,101 @@ impl File {
         self.interpolate_all()?;
         self.inline_all(base)
     }
+
+    /// Replace every relative path in this file with an absolute path,
+    /// resolved against `base`, which should be the directory containing
+    /// the file.  If `home` is supplied, `~/` paths in `volumes` are
+    /// expanded too.  This covers host paths in `volumes`, `build`
+    /// contexts, `env_file` entries, `extends` files, and the files used by
+    /// top-level `secrets` and `configs`.
+    pub fn absolutize_paths(&mut self,
+                            base: &Path,
+                            home: Option<&Path>)
+                            -> Result<()> {
+        absolutize_paths(self, base, home)
+    }
+
+    /// Replace the absolute paths in this file which lie under `root` with
+    /// paths relative to `base`, using `..` where needed.  `root` defaults
+    /// to `base`.  Other absolute paths, such as `/var/run/docker.sock`,
+    /// refer to the host and are left alone.  This is the inverse of
+    /// `absolutize_paths`, and is useful before moving a file to a new
+    /// directory.
+    pub fn relativize_paths(&mut self,
+                            base: &Path,
+                            root: Option<&Path>)
+                            -> Result<()> {
+        relativize_paths(self, base, root)
+    }
+
+    /// Write this file to `w` as a tarball, together with every local file
+    /// and directory it refers to, so that it can be deployed on a machine
+    /// which can't see our file system.  Relative paths are resolved
+    /// against `base`.  Like `make_standalone`, this interpolates
+    /// environment variables and inlines `env_file` entries.
+    pub fn export_bundle<W>(&self, base: &Path, w: W) -> Result<()>
+        where W: io::Write
+    {
+        export_bundle(self, base, w)
+    }
+
+    /// Unpack a tarball written by `export_bundle` into `dest`, and return
+    /// the file it contains, with every path resolved against `dest`.
+    pub fn import_bundle<R>(r: R, dest: &Path) -> Result<File>
+        where R: io::Read
+    {
+        import_bundle(r, dest)
+    }
+
+    /// Lock down the image versions used in this file, by adding a digest
+    /// to each service's `image`, giving `name:tag@digest`.  Digests are
+    /// looked up using `resolver`.  Services with a `build` section are left alone,
+    /// because their `image` names the image we build.
+    ///
+    /// If any image can't be resolved, we return an error and leave this
+    /// file unchanged.
+    pub fn pin_images<R>(&mut self, resolver: &R) -> Result<()>
+        where R: ImageResolver + ?Sized
+    {
+        pin_images(self, resolver)
+    }
+
+    /// Convert this file to the specified format `version`, translating
+    /// settings which the target version handles differently.  Returns a
+    /// report of every setting we changed.
+    ///
+    /// If a setting can't be represented exactly in the target version,
+    /// we return an error and leave the file unchanged, unless `force` is
+    /// true.  In that case, we approximate or drop the setting and list it
+    /// in `ConversionReport::lossy_changes`.
+    pub fn convert_to(&mut self, version: &Version, force: bool)
+                      -> Result<ConversionReport> {
+        let (converted, report) = convert_file(self, version, force)?;
+        *self = converted;
+        Ok(report)
+    }
+
+    /// Look for risky security settings in all our services.
+    pub fn audit(&self) -> AuditReport {
+        AuditReport::for_file(self)
+    }
+
+    /// Check that our SCONE settings are consistent.  See `SconeReport`.
+    pub fn check_scone(&self) -> SconeReport {
+        SconeReport::for_file(self)
+    }
 }
 
 impl Default for File {
     fn default() -> File {
         File {
-            version: "2".to_owned(),
+            version: Some(Version::default()),
+            name: Default::default(),
             services: Default::default(),
             volumes: Default::default(),
             networks: Default::default(),
             secrets: Default::default(),
+            configs: Default::default(),
             _hidden: (),
         }
     }
@@ -155,6 +293,63 @@ fn file_can_be_converted_from_and_to_yaml_version_2_1() {
     assert_roundtrip!(File, yaml);
 }
 
+#[test]
+fn file_can_be_converted_from_and_to_yaml_version_3_8() {
+    let yaml = r#"---
+"services":
+  "web":
+    "image": "nginx"
+    "init": true
+    "isolation": "default"
+    "ports":
+    - "80:80"
+    - "mode": "host"
+      "published": 8443
+      "target": 443
+    "volumes":
+    - "./html:/usr/share/nginx/html:ro"
+    - "consistency": "cached"
+      "source": "/var/log"
+      "target": "/logs"
+      "type": "bind"
+    - "target": "/cache"
+      "tmpfs":
+        "size": 1000000
+      "type": "tmpfs"
+"version": "3.8"
+"#;
+    assert_roundtrip!(File, yaml);
+}
+
+#[test]
+fn file_without_version_follows_the_compose_spec() {
+    let yaml = r#"---
+"name": "myapp"
+"services":
+  "db":
+    "image": "postgres"
+    "mem_limit": "1g"
+    "profiles":
+    - "debug"
+"#;
+    assert_roundtrip!(File, yaml);
+
+    let file = File::from_str(yaml).unwrap();
+    assert_eq!(file.version, None);
+    assert_eq!(file.name, Some(value("myapp".to_owned())));
+
+    // `profiles` and `name` are not supported by numbered versions.
+    let yaml = r#"---
+"services":
+  "db":
+    "image": "postgres"
+    "profiles":
+    - "debug"
+"version": "3.8"
+"#;
+    assert!(File::from_str(yaml).is_err());
+}
+
 #[test]
 fn file_allows_null_volumes_and_networks() {
     let yaml = r#"---
@@ -0,0 +1,182 @@
+// This is not a normal Rust module! It's included directly into v2.rs,
+// possibly after build-time preprocessing.  See v2.rs for an explanation
+// of how this works.
+
+/// How Docker should check whether a container is healthy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Healthcheck {
+    /// The command to run.  Parsed command lines normally begin with
+    /// `CMD` or `CMD-SHELL`, and shell code is run using `CMD-SHELL`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub test: Option<CommandLine>,
+
+    /// How long to wait between checks, such as `30s` or `1m30s`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<RawOr<String>>,
+
+    /// How long a single check may run before it is considered to have
+    /// failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<RawOr<String>>,
+
+    /// How many consecutive failures are needed before the container is
+    /// marked as unhealthy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+
+    /// How long to give the container to start before failed checks
+    /// count against `retries`.  Requires version 3.4.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_period: Option<RawOr<String>>,
+
+    /// Disable any healthcheck inherited from the image.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub disable: bool,
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    #[serde(default, skip_serializing, skip_deserializing)]
+    pub _hidden: (),
+}
+
+derive_standard_impls_for!(Healthcheck, {
+    test, interval, timeout, retries, start_period, disable, _hidden
+});
+
+impl Healthcheck {
+    /// The check to run, in the list form used by the Docker Engine API,
+    /// which begins with `CMD`, `CMD-SHELL` or `NONE`.  Returns `None` if
+    /// the image's own check should be used.  All fields must already be
+    /// interpolated.
+    pub fn docker_test(&self) -> Result<Option<Vec<String>>> {
+        if self.disable {
+            return Ok(Some(vec!["NONE".to_owned()]));
+        }
+        match self.test {
+            None => Ok(None),
+            Some(CommandLine::ShellCode(ref code)) => {
+                Ok(Some(vec!["CMD-SHELL".to_owned(), code.value()?.to_owned()]))
+            }
+            Some(CommandLine::Parsed(ref args)) => {
+                let args = args.iter()
+                    .map(|arg| arg.value().map(|a| a.to_owned()))
+                    .collect::<Result<Vec<String>>>()?;
+                match args.first().map(|a| &a[..]) {
+                    Some("CMD") | Some("CMD-SHELL") | Some("NONE") => Ok(Some(args)),
+                    _ => {
+                        let input = args.join(" ");
+                        Err(Error::invalid_value("healthcheck test", input))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Parse a duration like `1m30s` or `500ms`, using the same units as
+/// `docker-compose`.
+fn parse_duration(input: &str) -> Result<Duration> {
+    lazy_static! {
+        static ref PART: Regex =
+            Regex::new(r#"([0-9]+)(?:\.([0-9]+))?(ns|us|ms|s|m|h)"#).unwrap();
+    }
+    let invalid = || Error::invalid_value("duration", input);
+
+    let mut nanos: u64 = 0;
+    let mut end = 0;
+    for caps in PART.captures_iter(input) {
+        let (start, part_end) = caps.pos(0).unwrap();
+        if start != end {
+            return Err(invalid());
+        }
+        end = part_end;
+
+        let unit: u64 = match caps.at(3).unwrap() {
+            "ns" => 1,
+            "us" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60_000_000_000,
+            _ => 3_600_000_000_000,
+        };
+        let whole: u64 = caps.at(1).unwrap().parse().map_err(|_| invalid())?;
+        let mut part = whole.checked_mul(unit).ok_or_else(&invalid)?;
+        if let Some(fraction) = caps.at(2) {
+            // Only the first nine digits can matter, even for hours.
+            let digits = &fraction[..cmp::min(fraction.len(), 9)];
+            let scale = 10u64.pow(digits.len() as u32);
+            let digits: u64 = digits.parse().map_err(|_| invalid())?;
+            let fraction = digits * (unit / scale) + digits * (unit % scale) / scale;
+            part = part.checked_add(fraction).ok_or_else(&invalid)?;
+        }
+        nanos = nanos.checked_add(part).ok_or_else(&invalid)?;
+    }
+    if end == 0 || end != input.len() {
+        return Err(invalid());
+    }
+    Ok(Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32))
+}
+
+#[test]
+fn healthcheck_handles_shell_code_and_timings() {
+    let yaml = r#"---
+test: "curl -f http://localhost/"
+interval: 1m30s
+timeout: 10s
+retries: 3
+start_period: 40s
+"#;
+    assert_roundtrip!(Healthcheck, yaml);
+}
+
+#[test]
+fn healthcheck_handles_parsed_commands_and_disable() {
+    let yaml = r#"---
+test:
+  - CMD
+  - curl
+  - "-f"
+  - "http://localhost/"
+"#;
+    assert_roundtrip!(Healthcheck, yaml);
+
+    let yaml = r#"---
+disable: true
+"#;
+    let healthcheck: Healthcheck = serde_yaml::from_str(yaml).unwrap();
+    assert!(healthcheck.disable);
+    assert_eq!(healthcheck.test, None);
+}
+
+#[test]
+fn healthcheck_converts_test_to_docker_form() {
+    let healthcheck: Healthcheck =
+        serde_yaml::from_str("test: \"curl -f http://localhost/\"").unwrap();
+    assert_eq!(healthcheck.docker_test().unwrap(),
+               Some(vec!["CMD-SHELL".to_owned(),
+                         "curl -f http://localhost/".to_owned()]));
+
+    let healthcheck: Healthcheck =
+        serde_yaml::from_str("test: [\"CMD\", \"true\"]\ndisable: true").unwrap();
+    assert_eq!(healthcheck.docker_test().unwrap(), Some(vec!["NONE".to_owned()]));
+
+    let healthcheck: Healthcheck = serde_yaml::from_str("test: [\"true\"]").unwrap();
+    assert!(healthcheck.docker_test().is_err());
+}
+
+#[test]
+fn parse_duration_handles_docker_compose_units() {
+    assert_eq!(parse_duration("30s").unwrap(), Duration::new(30, 0));
+    assert_eq!(parse_duration("1m30s").unwrap(), Duration::new(90, 0));
+    assert_eq!(parse_duration("1h").unwrap(), Duration::new(3600, 0));
+    assert_eq!(parse_duration("1.5s").unwrap(), Duration::new(1, 500_000_000));
+    assert_eq!(parse_duration("250ms").unwrap(), Duration::new(0, 250_000_000));
+    assert_eq!(parse_duration("10us").unwrap(), Duration::new(0, 10_000));
+    for invalid in &["", "30", "s", "1m 30s", "-1s", "1d", "30s!"] {
+        assert!(parse_duration(invalid).is_err(), "{:?} should be invalid", invalid);
+    }
+}
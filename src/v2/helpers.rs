@@ -1,13 +1,23 @@
 //! Helper functions and types we use for (de)serialization.  These handle
 //! several common, annoying patterns in the `docker-compose.yml` format.
 
+#[cfg(test)]
+use rand;
 use regex::Regex;
 use serde::de;
 use serde::de::{Deserialize, Deserializer, MapVisitor, SeqVisitor, Visitor};
-use std::collections::BTreeMap;
+use serde::ser::{Serialize, Serializer};
+#[cfg(test)]
+use std::env;
+#[cfg(test)]
+use std::fs;
+use std::hash::Hash;
 use std::marker::PhantomData;
+#[cfg(test)]
+use std::path::{Path, PathBuf};
 
 use super::interpolation::{InterpolatableValue, RawOr, raw};
+use super::map::Map;
 
 /// Test whether a value is false.  Used to determine when to serialize
 /// things.
@@ -32,6 +42,34 @@ pub fn normalize_yaml(yaml: &str) -> String {
     NL_EOS.replace_all(&WS_NL.replace_all(yaml, "\n"), "")
 }
 
+/// A temporary directory with a unique name, which is deleted along with
+/// its contents when dropped.  Used by unit tests.
+#[cfg(test)]
+pub struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    /// Create a new, empty directory whose name starts with `prefix`.
+    pub fn new(prefix: &str) -> TempDir {
+        let name = format!("{}_{:016x}", prefix, rand::random::<u64>());
+        let path = env::temp_dir().join(name);
+        fs::create_dir(&path).unwrap();
+        TempDir(path)
+    }
+
+    /// The path to this directory.
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
 /// We use this when the format wants a `String`, but has support for
 /// converting several other types.  Mostly this is so that users can
 /// write `ENV_VAR: 1`, and not get an error about using `1` instead of
@@ -68,7 +106,7 @@ impl Visitor for ToStringVisitor {
 
 /// A wrapper type which uses `ToStringVisitor` to deserialize a value,
 /// converting many scalar types to a string.
-struct ConvertToString(String);
+pub struct ConvertToString(pub String);
 
 impl Deserialize for ConvertToString {
     fn deserialize<D>(deserializer: &mut D) -> Result<Self, D::Error>
@@ -100,25 +138,25 @@ impl Deserialize for ConvertToString {
 /// ```text
 /// struct Example {
 ///     #[serde(deserialize_with = "deserialize_hash_or_key_value_list")]
-///     pub args: BTreeMap<String, RawOr<String>>,
+///     pub args: Map<String, RawOr<String>>,
 /// }
 /// ```
 pub fn deserialize_map_or_key_value_list<D>
     (deserializer: &mut D)
-     -> Result<BTreeMap<String, RawOr<String>>, D::Error>
+     -> Result<Map<String, RawOr<String>>, D::Error>
     where D: Deserializer
 {
     /// Declare an internal visitor type to handle our input.
     struct MapOrKeyValueListVisitor;
 
     impl Visitor for MapOrKeyValueListVisitor {
-        type Value = BTreeMap<String, RawOr<String>>;
+        type Value = Map<String, RawOr<String>>;
 
         // We have a real map.
         fn visit_map<V>(&mut self, mut visitor: V) -> Result<Self::Value, V::Error>
             where V: MapVisitor
         {
-            let mut map: BTreeMap<String, RawOr<String>> = BTreeMap::new();
+            let mut map: Map<String, RawOr<String>> = Map::new();
             while let Some(key) = visitor.visit_key::<String>()? {
                 if map.contains_key(&key) {
                     let msg = format!("duplicate map key: {}", &key);
@@ -144,7 +182,7 @@ pub fn deserialize_map_or_key_value_list<D>
                     Regex::new("^([^=]+)=(.*)$").unwrap();
             }
 
-            let mut map: BTreeMap<String, RawOr<String>> = BTreeMap::new();
+            let mut map: Map<String, RawOr<String>> = Map::new();
             while let Some(key_value) = visitor.visit::<String>()? {
                 let caps = KEY_VALUE.captures(&key_value)
                     .ok_or_else(|| {
@@ -170,20 +208,26 @@ pub fn deserialize_map_or_key_value_list<D>
     deserializer.deserialize_map(MapOrKeyValueListVisitor)
 }
 
-/// Given a map, deserialize it normally.  But if we have a list of string
-/// values, deserialize it as a map keyed with those strings, and with
+/// Given a map, deserialize it normally.  But if we have a list of
+/// keys, deserialize it as a map with those keys, and with
 /// `Default::default()` used as the value.
-pub fn deserialize_map_or_default_list<T, D>
+pub fn deserialize_map_or_default_list<K, T, D>
     (deserializer: &mut D)
-     -> Result<BTreeMap<String, T>, D::Error>
-    where T: Default + Deserialize,
+     -> Result<Map<K, T>, D::Error>
+    where K: Ord + Hash + Deserialize,
+          T: Default + Deserialize,
           D: Deserializer
 {
     /// Declare an internal visitor type to handle our input.
-    struct MapOrDefaultListVisitor<T>(PhantomData<T>) where T: Default + Deserialize;
+    struct MapOrDefaultListVisitor<K, T>(PhantomData<(K, T)>)
+        where K: Ord + Hash + Deserialize,
+              T: Default + Deserialize;
 
-    impl<T: Default + Deserialize> Visitor for MapOrDefaultListVisitor<T> {
-        type Value = BTreeMap<String, T>;
+    impl<K, T> Visitor for MapOrDefaultListVisitor<K, T>
+        where K: Ord + Hash + Deserialize,
+              T: Default + Deserialize
+    {
+        type Value = Map<K, T>;
 
         fn visit_map<M>(&mut self, visitor: M) -> Result<Self::Value, M::Error>
             where M: MapVisitor
@@ -195,16 +239,35 @@ pub fn deserialize_map_or_default_list<T, D>
         fn visit_seq<V>(&mut self, mut visitor: V) -> Result<Self::Value, V::Error>
             where V: SeqVisitor
         {
-            let mut map: Self::Value = BTreeMap::new();
-            // TODO LOW: Fail with error if values are interpolated.
-            while let Some(key) = visitor.visit::<String>()? {
+            let mut map: Self::Value = Map::new();
+            // TODO LOW: Fail with error if `String` keys are interpolated.
+            while let Some(key) = visitor.visit::<K>()? {
                 map.insert(key, Default::default());
             }
             Ok(map)
         }
     }
 
-    deserializer.deserialize(MapOrDefaultListVisitor(PhantomData::<T>))
+    deserializer.deserialize(MapOrDefaultListVisitor(PhantomData::<(K, T)>))
+}
+
+/// The inverse of `deserialize_map_or_default_list`: if every value in
+/// the map is `Default::default()`, serialize just the keys as a list.
+/// Otherwise, serialize the map normally.
+pub fn serialize_map_or_default_list<K, T, S>(map: &Map<K, T>,
+                                              serializer: &mut S)
+                                              -> Result<(), S::Error>
+    where K: Ord + Hash + Serialize,
+          T: Default + PartialEq + Serialize,
+          S: Serializer
+{
+    let default = T::default();
+    if map.values().all(|v| *v == default) {
+        let keys: Vec<&K> = map.keys().collect();
+        keys.serialize(serializer)
+    } else {
+        map.serialize(serializer)
+    }
 }
 
 /// Deserialize either list or a single bare string as a list.
@@ -251,13 +314,13 @@ pub fn deserialize_item_or_list<T, D>(deserializer: &mut D)
 
 /// Deserialize either list or a single bare string as a list.
 pub fn deserialize_map_struct_or_null<T, D>(deserializer: &mut D)
-                                            -> Result<BTreeMap<String, T>, D::Error>
+                                            -> Result<Map<String, T>, D::Error>
     where T: Deserialize + Default,
           D: Deserializer
 {
-    let with_nulls: BTreeMap<String, Option<T>> =
+    let with_nulls: Map<String, Option<T>> =
         Deserialize::deserialize(deserializer)?;
-    let mut result = BTreeMap::new();
+    let mut result = Map::new();
     for (k, v) in with_nulls {
         result.insert(k, v.unwrap_or_default());
     }
@@ -22,22 +22,147 @@ impl fmt::Display for RegistryHost {
     }
 }
 
-/// The name of an external resource, and an optional local alias to which
-/// it is mapped inside a container.  Our fields names are based on the
-/// `docker` documentation.
+impl FromStr for RegistryHost {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        lazy_static! {
+            static ref REGISTRY_HOST: Regex =
+                Regex::new(r#"^((?:[a-zA-Z0-9](?:[a-zA-Z0-9-]*[a-zA-Z0-9])?(?:\.[a-zA-Z0-9](?:[a-zA-Z0-9-]*[a-zA-Z0-9])?)*)|\[[a-fA-F0-9:]+\])(?::([0-9]+))?$"#).unwrap();
+        }
+        let caps = REGISTRY_HOST.captures(s).ok_or_else(|| {
+            Error::invalid_value("registry host", s)
+        })?;
+        let port = match caps.at(2) {
+            Some(port) => {
+                Some(FromStr::from_str(port).map_err(|_| {
+                    Error::invalid_value("registry host", s)
+                })?)
+            }
+            None => None,
+        };
+        Ok(RegistryHost {
+            host: caps.at(1).unwrap().to_owned(),
+            port: port,
+        })
+    }
+}
+
+/// The registry used for images which don't specify one.
+const DEFAULT_REGISTRY_HOST: &'static str = "docker.io";
+
+/// The repository path used for official images on the default registry.
+const OFFICIAL_REPOSITORY_PATH: &'static str = "library";
+
+/// A content-addressable digest identifying an exact image, such as
+/// `sha256:` followed by 64 hexadecimal digits.
+///
+/// ```
+/// use compose_yml::v2 as dc;
+/// let digest: dc::Digest = "sha256:0123456789abcdef0123456789abcdef\
+///                           0123456789abcdef0123456789abcdef"
+///     .parse().unwrap();
+/// assert_eq!(digest.algorithm, "sha256");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Digest {
+    /// The hash algorithm, such as `sha256`.
+    pub algorithm: String,
+    /// The encoded hash value.
+    pub hex: String,
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", &self.algorithm, &self.hex)
+    }
+}
+
+impl FromStr for Digest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        lazy_static! {
+            static ref DIGEST: Regex =
+                Regex::new(r#"^([A-Za-z][A-Za-z0-9]*(?:[-_+.][A-Za-z][A-Za-z0-9]*)*):([0-9a-fA-F]{32,})$"#).unwrap();
+        }
+        let caps = DIGEST.captures(s).ok_or_else(|| {
+            Error::invalid_value("digest", s)
+        })?;
+        let algorithm = caps.at(1).unwrap();
+        let hex = caps.at(2).unwrap();
+        // Algorithms we know have a fixed length.  Others just need to be
+        // long enough to be plausible.
+        let expected_len = match algorithm {
+            "sha256" => Some(64),
+            "sha384" => Some(96),
+            "sha512" => Some(128),
+            _ => None,
+        };
+        if expected_len.map_or(false, |len| hex.len() != len) {
+            return Err(Error::invalid_value("digest", s));
+        }
+        Ok(Digest {
+            algorithm: algorithm.to_owned(),
+            hex: hex.to_owned(),
+        })
+    }
+}
+
+impl Serialize for Digest {
+    fn serialize<S>(&self, serializer: &mut S) -> result::Result<(), S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl Deserialize for Digest {
+    fn deserialize<D>(deserializer: &mut D) -> result::Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let s = String::deserialize(deserializer)?;
+        Digest::from_str(&s).map_err(|err| de::Error::custom(format!("{}", err)))
+    }
+}
+
+/// A reference to a Docker image, following the grammar used by the
+/// Docker distribution tools:
+///
+/// ```text
+/// [registry_host[:port]/][path/...]name[:tag][@digest]
+/// ```
+///
+/// The first component is only treated as a registry host if it contains
+/// a `.` or a `:`, or if it is `localhost`.
+///
+/// ```
+/// use compose_yml::v2 as dc;
+/// let image = dc::Image::new("localhost:5000/team/app:1.0").unwrap();
+/// assert_eq!(image.registry_host.as_ref().unwrap().host, "localhost");
+/// assert_eq!(image.path, vec!["team".to_owned()]);
+/// assert_eq!(image.name, "app");
+/// assert_eq!(image.tag, Some("1.0".to_owned()));
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Image {
     /// The server running our Docker registry.
     pub registry_host: Option<RegistryHost>,
 
-    /// The name of the user account on the Docker registry.
-    pub user_name: Option<String>,
+    /// The repository path components before `name`, such as the user or
+    /// organization name on Docker Hub, or the project and team on other
+    /// registries.
+    pub path: Vec<String>,
 
     /// The name of this image.
     pub name: String,
 
     /// A tag identifying a specific version in our image registry.
     pub tag: Option<String>,
+
+    /// A digest identifying the exact contents of this image.  If both a
+    /// `tag` and a `digest` are present, Docker uses the digest.
+    pub digest: Option<Digest>,
 }
 
 impl Image {
@@ -53,6 +178,79 @@ impl Image {
             ..self.to_owned()
         }
     }
+
+    /// Return the `Image` with the digest removed.
+    pub fn without_digest(&self) -> Image {
+        Image {
+            digest: None,
+            ..self.to_owned()
+        }
+    }
+
+    /// The repository name, including the registry host and path, but
+    /// without any tag or digest.
+    pub fn repository(&self) -> String {
+        Image {
+                tag: None,
+                digest: None,
+                ..self.to_owned()
+            }
+            .to_string()
+    }
+
+    /// Is this image stored on Docker Hub, the default registry?
+    pub fn is_docker_hub(&self) -> bool {
+        match self.registry_host {
+            None => true,
+            Some(ref registry_host) => {
+                registry_host.port.is_none() &&
+                (registry_host.host == DEFAULT_REGISTRY_HOST ||
+                 registry_host.host == "index.docker.io")
+            }
+        }
+    }
+
+    /// Return the fully-qualified form of this image, as used internally
+    /// by Docker: images without a registry host are placed on
+    /// `docker.io`, and official images are placed under `library/`.
+    ///
+    /// ```
+    /// use compose_yml::v2 as dc;
+    /// let image = dc::Image::new("redis:5").unwrap();
+    /// assert_eq!(image.normalized().to_string(), "docker.io/library/redis:5");
+    /// ```
+    pub fn normalized(&self) -> Image {
+        let mut image = self.to_owned();
+        if image.is_docker_hub() {
+            image.registry_host = Some(RegistryHost {
+                host: DEFAULT_REGISTRY_HOST.to_owned(),
+                port: None,
+            });
+            if image.path.is_empty() {
+                image.path.push(OFFICIAL_REPOSITORY_PATH.to_owned());
+            }
+        }
+        image
+    }
+
+    /// Return the shortest form of this image which Docker will treat as
+    /// equivalent, undoing `normalized`.
+    ///
+    /// ```
+    /// use compose_yml::v2 as dc;
+    /// let image = dc::Image::new("docker.io/library/redis:5").unwrap();
+    /// assert_eq!(image.familiar().to_string(), "redis:5");
+    /// ```
+    pub fn familiar(&self) -> Image {
+        let mut image = self.to_owned();
+        if image.is_docker_hub() {
+            image.registry_host = None;
+            if image.path.len() == 1 && image.path[0] == OFFICIAL_REPOSITORY_PATH {
+                image.path.clear();
+            }
+        }
+        image
+    }
 }
 
 impl fmt::Display for Image {
@@ -60,13 +258,16 @@ impl fmt::Display for Image {
         if let Some(ref registry_host) = self.registry_host {
             write!(f, "{}/", registry_host)?;
         }
-        if let Some(ref user_name) = self.user_name {
-            write!(f, "{}/", user_name)?;
+        for component in &self.path {
+            write!(f, "{}/", component)?;
         }
         write!(f, "{}", &self.name)?;
         if let Some(ref tag) = self.tag {
             write!(f, ":{}", tag)?;
         }
+        if let Some(ref digest) = self.digest {
+            write!(f, "@{}", digest)?;
+        }
         Ok(())
     }
 }
@@ -76,37 +277,59 @@ impl FromStr for Image {
 
     fn from_str(s: &str) -> Result<Self> {
         lazy_static! {
-            static ref IMAGE: Regex =
-                Regex::new(r#"^(?:([^/:.]+\.[^/:]+)(?::([0-9]+))?/)?(?:([^/:.]+)/)?([^/:]+)(?::([^/:]+))?$"#).unwrap();
+            static ref PATH_COMPONENT: Regex =
+                Regex::new(r#"^[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*$"#).unwrap();
+            static ref TAG: Regex =
+                Regex::new(r#"^[\w][\w.-]{0,127}$"#).unwrap();
         }
-        let caps = IMAGE.captures(s).ok_or_else(|| {
-            Error::invalid_value("image", s)
-        })?;
-        // This could use a good refactoring.
-        let registry_host =
-            if caps.at(1).is_some() {
-                // TODO LOW: Is there a special map function for things
-                // which might fail?
-                let port =
-                    if caps.at(2).is_some() {
-                        Some(FromStr::from_str(caps.at(2).unwrap()).map_err(|_| {
-                            Error::invalid_value("image", s)
-                        })?)
-                    } else {
-                        None
-                    };
-                Some(RegistryHost {
-                    host: caps.at(1).unwrap().to_owned(),
-                    port: port,
-                })
-            } else {
-                None
-            };
+        let mkerr = || Error::invalid_value("image", s);
+
+        // Split off the digest, and then the tag.  A colon before the last
+        // slash belongs to the registry host's port number.
+        let (rest, digest) = match s.find('@') {
+            Some(idx) => (&s[..idx], Some(Digest::from_str(&s[idx + 1..])?)),
+            None => (s, None),
+        };
+        let tag_start = match (rest.rfind(':'), rest.rfind('/')) {
+            (Some(colon), Some(slash)) if colon > slash => Some(colon),
+            (Some(colon), None) => Some(colon),
+            _ => None,
+        };
+        let (repository, tag) = match tag_start {
+            Some(idx) => (&rest[..idx], Some(&rest[idx + 1..])),
+            None => (rest, None),
+        };
+        if let Some(tag) = tag {
+            if !TAG.is_match(tag) {
+                return Err(mkerr());
+            }
+        }
+        if repository.len() > 255 {
+            return Err(mkerr());
+        }
+
+        let mut components: Vec<&str> = repository.split('/').collect();
+        let registry_host = if components.len() > 1 &&
+                               (components[0].contains('.') ||
+                                components[0].contains(':') ||
+                                components[0] == "localhost") {
+            Some(RegistryHost::from_str(components.remove(0)).map_err(|_| mkerr())?)
+        } else {
+            None
+        };
+        for component in &components {
+            if !PATH_COMPONENT.is_match(component) {
+                return Err(mkerr());
+            }
+        }
+        let name = components.pop().expect("split always returns a component");
+
         Ok(Image {
             registry_host: registry_host,
-            user_name: caps.at(3).map(|s| s.to_owned()),
-            name: caps.at(4).unwrap().to_owned(),
-            tag: caps.at(5).map(|s| s.to_owned()),
+            path: components.into_iter().map(|c| c.to_owned()).collect(),
+            name: name.to_owned(),
+            tag: tag.map(|t| t.to_owned()),
+            digest: digest,
         })
     }
 }
@@ -117,33 +340,37 @@ impl_interpolatable_value!(Image);
 fn parses_stand_image_formats() {
     let img1 = Image {
         registry_host: None,
-        user_name: None,
+        path: vec![],
         name: "hello".to_owned(),
         tag: None,
+        digest: None,
     };
     let img2 = Image {
         registry_host: None,
-        user_name: Some("example".to_owned()),
+        path: vec!["example".to_owned()],
         name: "hello".to_owned(),
         tag: Some("4.4-alpine".to_owned()),
+        digest: None,
     };
     let img3 = Image {
         registry_host: Some(RegistryHost {
             host: "example.com".to_owned(),
             port: Some(123),
         }),
-        user_name: None,
+        path: vec![],
         name: "hello".to_owned(),
         tag: Some("latest".to_owned()),
+        digest: None,
     };
     let img4 = Image {
         registry_host: Some(RegistryHost {
             host: "example.com".to_owned(),
             port: None,
         }),
-        user_name: Some("staff".to_owned()),
+        path: vec!["staff".to_owned()],
         name: "hello".to_owned(),
         tag: None,
+        digest: None,
     };
     let pairs = vec!(
         (img1, "hello"),
@@ -156,3 +383,80 @@ fn parses_stand_image_formats() {
         assert_eq!(img, Image::from_str(s).unwrap());
     }
 }
+
+#[test]
+fn parses_full_distribution_reference_grammar() {
+    let digest = "sha256:0123456789abcdef0123456789abcdef\
+                  0123456789abcdef0123456789abcdef";
+
+    let localhost = Image::from_str("localhost:5000/app").unwrap();
+    assert_eq!(localhost.registry_host,
+               Some(RegistryHost {
+                   host: "localhost".to_owned(),
+                   port: Some(5000),
+               }));
+    assert_eq!(localhost.path, Vec::<String>::new());
+    assert_eq!(localhost.name, "app");
+    assert_eq!(localhost.tag, None);
+
+    let nested = Image::from_str("gcr.io/proj/team/app:v1.2").unwrap();
+    assert_eq!(nested.registry_host.as_ref().unwrap().host, "gcr.io");
+    assert_eq!(nested.path, vec!["proj".to_owned(), "team".to_owned()]);
+    assert_eq!(nested.name, "app");
+    assert_eq!(nested.tag, Some("v1.2".to_owned()));
+
+    let pinned = Image::from_str(&format!("redis:5@{}", digest)).unwrap();
+    assert_eq!(pinned.tag, Some("5".to_owned()));
+    assert_eq!(pinned.digest.as_ref().unwrap().to_string(), digest);
+    assert_eq!(pinned.repository(), "redis");
+
+    for s in &["localhost:5000/app",
+               "gcr.io/proj/team/app:v1.2",
+               "registry.local:8443/a/b/c@sha256:0123456789abcdef0123456789abcdef\
+                0123456789abcdef0123456789abcdef"] {
+        assert_eq!(Image::from_str(s).unwrap().to_string(), *s);
+    }
+
+    for s in &["Hello", "foo//bar", "foo:bad/tag", "redis@sha256:abc", "-foo"] {
+        assert!(Image::from_str(s).is_err(), "should reject {}", s);
+    }
+}
+
+#[test]
+fn images_can_be_normalized_and_shortened() {
+    let pairs = [("redis", "docker.io/library/redis"),
+                 ("user/app:1", "docker.io/user/app:1"),
+                 ("index.docker.io/library/redis", "docker.io/library/redis"),
+                 ("gcr.io/proj/app", "gcr.io/proj/app"),
+                 ("localhost/app", "localhost/app")];
+    for &(short, normalized) in &pairs {
+        let image = Image::from_str(short).unwrap();
+        assert_eq!(image.normalized().to_string(), normalized);
+        assert_eq!(Image::from_str(normalized).unwrap().familiar().to_string(),
+                   image.familiar().to_string());
+    }
+    assert_eq!(Image::from_str("docker.io/library/redis:5").unwrap().familiar(),
+               Image::from_str("redis:5").unwrap());
+}
+
+#[test]
+fn digests_must_have_the_right_length_for_their_algorithm() {
+    let hex32 = "0123456789abcdef0123456789abcdef";
+    let hex64 = format!("{}{}", hex32, hex32);
+    let hex128 = format!("{}{}", hex64, hex64);
+
+    assert!(Digest::from_str(&format!("sha256:{}", hex64)).is_ok());
+    assert!(Digest::from_str(&format!("sha512:{}", hex128)).is_ok());
+    assert!(Digest::from_str(&format!("sha384:{}{}", hex64, hex32)).is_ok());
+    // We don't know how long other algorithms' digests should be.
+    assert!(Digest::from_str(&format!("multihash+base58:{}", hex32)).is_ok());
+
+    for s in &[format!("sha256:{}", hex32),
+               format!("sha256:{}0", hex64),
+               format!("sha512:{}", hex64),
+               format!("sha384:{}", hex64),
+               format!("other:{}", &hex32[1..])] {
+        assert!(Digest::from_str(s).is_err(), "should reject {}", s);
+    }
+    assert!(Image::from_str(&format!("redis@sha256:{}", hex32)).is_err());
+}
@@ -0,0 +1,331 @@
+// This is not a normal Rust module! It's included directly into v2.rs,
+// possibly after build-time preprocessing.  See v2.rs for an explanation
+// of how this works.
+
+/// The only version of the `compose.lock` format we currently support.
+const IMAGE_LOCK_VERSION: u32 = 1;
+
+/// The header written at the top of every `compose.lock` file.
+const IMAGE_LOCK_HEADER: &'static str =
+    "# Generated by compose_yml.  Do not edit this file by hand.\n";
+
+/// A `compose.lock` file, recording the digest of every image referred to
+/// by a `File`, including images which are only named as the output of a
+/// `build` section.
+///
+/// Images are keyed by their normalized reference with an explicit tag,
+/// and are always written in sorted order with one image per line, so
+/// that changes to a lock file produce small, readable diffs.  Keys which
+/// are written by hand are normalized when the lock is read.
+///
+/// ```yaml
+/// # Generated by compose_yml.  Do not edit this file by hand.
+/// ---
+/// "images":
+///   "docker.io/library/redis:5": "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+/// "version": 1
+/// ```
+///
+/// An `ImageLock` is also an `ImageResolver` which never touches the
+/// network, so it can be passed to `File::pin_images`, and is useful for
+/// testing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageLock {
+    /// The digest of each image, indexed by normalized reference.  We use
+    /// a `BTreeMap` instead of a `Map` so that the output is sorted even
+    /// when the `preserve_order` feature is enabled.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub images: BTreeMap<String, Digest>,
+
+    /// The version of the lock file format.
+    pub version: u32,
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    #[serde(default, skip_serializing, skip_deserializing)]
+    pub _hidden: (),
+}
+
+derive_standard_impls_for!(ImageLock, {
+    images, version, _hidden
+});
+
+impl Default for ImageLock {
+    fn default() -> ImageLock {
+        ImageLock {
+            images: Default::default(),
+            version: IMAGE_LOCK_VERSION,
+            _hidden: (),
+        }
+    }
+}
+
+impl ImageLock {
+    /// Create an empty lock.
+    pub fn new() -> ImageLock {
+        Default::default()
+    }
+
+    /// Build a lock listing every image in `file`, using `resolver` to
+    /// look up any image which isn't already pinned to a digest.
+    pub fn generate<R>(file: &File, resolver: &R) -> Result<ImageLock>
+        where R: ImageResolver + ?Sized
+    {
+        let mut lock = ImageLock::new();
+        for image in image_lock_images(file)? {
+            let digest = match image.digest {
+                Some(ref digest) => digest.clone(),
+                None => resolver.resolve(image)?,
+            };
+            lock.insert(image, digest);
+        }
+        Ok(lock)
+    }
+
+    /// Record the digest for `image`.
+    pub fn insert(&mut self, image: &Image, digest: Digest) {
+        self.images.insert(lock_key(image), digest);
+    }
+
+    /// Look up the digest recorded for `image`, if any.
+    pub fn get(&self, image: &Image) -> Option<&Digest> {
+        self.images.get(&lock_key(image))
+    }
+
+    /// Compare `file` against this lock, and report any images which
+    /// are missing from the lock, any lock entries which `file` no longer
+    /// uses, and any images which `file` pins to a different digest.  An
+    /// empty list means that the lock is up to date.
+    pub fn verify(&self, file: &File) -> Result<Vec<ImageLockDrift>> {
+        let mut used = BTreeMap::new();
+        for image in image_lock_images(file)? {
+            used.insert(lock_key(image), image.digest.clone());
+        }
+
+        let mut drift = vec![];
+        for (key, pinned) in &used {
+            match (self.images.get(key), pinned.as_ref()) {
+                (None, _) => drift.push(ImageLockDrift::Missing(key.clone())),
+                (Some(locked), Some(pinned)) if locked != pinned => {
+                    drift.push(ImageLockDrift::Changed(key.clone(),
+                                                       locked.clone(),
+                                                       pinned.clone()))
+                }
+                _ => {}
+            }
+        }
+        for key in self.images.keys() {
+            if !used.contains_key(key) {
+                drift.push(ImageLockDrift::Unused(key.clone()));
+            }
+        }
+        Ok(drift)
+    }
+
+    /// Ask `resolver` for the current digest of every image in this lock,
+    /// and report any images whose digests have changed since the lock
+    /// was generated.
+    pub fn check_for_updates<R>(&self, resolver: &R) -> Result<Vec<ImageLockDrift>>
+        where R: ImageResolver + ?Sized
+    {
+        let mut drift = vec![];
+        for (key, locked) in &self.images {
+            let current = resolver.resolve(&Image::from_str(key)?)?;
+            if &current != locked {
+                drift.push(ImageLockDrift::Changed(key.clone(),
+                                                   locked.clone(),
+                                                   current));
+            }
+        }
+        Ok(drift)
+    }
+
+    /// Read a lock from an input stream containing YAML.
+    pub fn read<R>(mut r: R) -> Result<Self>
+        where R: io::Read
+    {
+        let mut yaml = String::new();
+        r.read_to_string(&mut yaml).chain_err(|| "I/O error")?;
+        let mut lock: ImageLock = serde_yaml::from_str(&yaml)?;
+        if lock.version != IMAGE_LOCK_VERSION {
+            let version = lock.version.to_string();
+            return Err(Error::invalid_value("lock file version", version));
+        }
+        let mut images = BTreeMap::new();
+        for (image, digest) in lock.images {
+            images.insert(lock_key(&Image::from_str(&image)?), digest);
+        }
+        lock.images = images;
+        Ok(lock)
+    }
+
+    /// Write a lock to an output stream as YAML.
+    pub fn write<W>(&self, w: &mut W) -> Result<()>
+        where W: io::Write
+    {
+        w.write_all(IMAGE_LOCK_HEADER.as_bytes()).chain_err(|| "I/O error")?;
+        serde_yaml::to_writer(w, self)?;
+        // Always end with a newline, to keep diffs and editors happy.
+        w.write_all(b"\n").chain_err(|| "I/O error")?;
+        Ok(())
+    }
+
+    /// Read a lock from the specified path.
+    pub fn read_from_path<P>(path: P) -> Result<Self>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mkerr = || ErrorKind::ReadFile(path.to_owned());
+        let f = fs::File::open(path).chain_err(&mkerr)?;
+        Self::read(io::BufReader::new(f)).chain_err(&mkerr)
+    }
+
+    /// Write a lock to the specified path.
+    pub fn write_to_path<P>(&self, path: P) -> Result<()>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mkerr = || ErrorKind::WriteFile(path.to_owned());
+        let f = fs::File::create(path).chain_err(&mkerr)?;
+        self.write(&mut io::BufWriter::new(f)).chain_err(&mkerr)
+    }
+}
+
+impl FromStr for ImageLock {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ImageLock> {
+        Self::read(io::Cursor::new(s))
+    }
+}
+
+impl ImageResolver for ImageLock {
+    fn resolve(&self, image: &Image) -> Result<Digest> {
+        self.get(image)
+            .cloned()
+            .ok_or_else(|| ErrorKind::UnresolvedImage(image.to_string()).into())
+    }
+}
+
+/// Every image referred to by `file`, including the names of images
+/// produced by `build` sections.  We skip images like `redis@sha256:...`,
+/// which are pinned to a digest without a tag, because there's nothing to
+/// lock.
+fn image_lock_images(file: &File) -> Result<Vec<&Image>> {
+    let mut images = vec![];
+    for service in file.services.values() {
+        if let Some(ref image) = service.image {
+            let image = image.value()?;
+            if image.tag.is_some() || image.digest.is_none() {
+                images.push(image);
+            }
+        }
+    }
+    Ok(images)
+}
+
+/// A difference between an `ImageLock` and the images actually in use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageLockDrift {
+    /// An image is used by the file, but isn't listed in the lock.
+    Missing(String),
+    /// An image is listed in the lock, but no longer used by the file.
+    Unused(String),
+    /// An image has a different digest than the one in the lock.  Holds
+    /// the image, the locked digest, and the new digest.
+    Changed(String, Digest, Digest),
+}
+
+impl fmt::Display for ImageLockDrift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ImageLockDrift::Missing(ref image) => {
+                write!(f, "{} is not in the lock", image)
+            }
+            ImageLockDrift::Unused(ref image) => {
+                write!(f, "{} is locked but no longer used", image)
+            }
+            ImageLockDrift::Changed(ref image, ref locked, ref current) => {
+                write!(f, "{} is locked to {} but is now {}", image, locked, current)
+            }
+        }
+    }
+}
+
+#[test]
+fn image_lock_lists_every_image_including_build_targets() {
+    let resolver = ImageLock::from_str(r#"---
+images:
+  "redis:5": "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+  "example/app:dev": "sha256:fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210"
+version: 1
+"#)
+        .unwrap();
+    let file = File::from_str(r#"---
+version: "2"
+services:
+  cache:
+    image: "redis:5"
+  app:
+    build: "."
+    image: "example/app:dev"
+  proxy:
+    image: "nginx@sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+"#)
+        .unwrap();
+
+    let lock = ImageLock::generate(&file, &resolver).unwrap();
+    let mut out = vec![];
+    lock.write(&mut out).unwrap();
+    let expected = r#"# Generated by compose_yml.  Do not edit this file by hand.
+---
+"images":
+  "docker.io/example/app:dev": "sha256:fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210"
+  "docker.io/library/redis:5": "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+"version": 1
+"#;
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+    assert_eq!(ImageLock::from_str(expected).unwrap(), lock);
+    assert_eq!(lock.verify(&file).unwrap(), vec![]);
+    assert_eq!(lock.check_for_updates(&resolver).unwrap(), vec![]);
+}
+
+#[test]
+fn image_lock_reports_drift() {
+    let lock = ImageLock::from_str(r#"---
+images:
+  "docker.io/library/nginx:latest": "sha256:fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210"
+  "docker.io/library/redis:5": "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+version: 1
+"#)
+        .unwrap();
+    let file = File::from_str(r#"---
+version: "2"
+services:
+  cache:
+    image: "redis:5@sha256:0000000000000000000000000000000000000000000000000000000000000000"
+  db:
+    image: "postgres:11"
+"#)
+        .unwrap();
+    let drift: Vec<String> = lock.verify(&file)
+        .unwrap()
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+    assert_eq!(drift,
+               vec!["docker.io/library/postgres:11 is not in the lock",
+                    "docker.io/library/redis:5 is locked to \
+                     sha256:0123456789abcdef0123456789abcdef\
+                     0123456789abcdef0123456789abcdef but is now \
+                     sha256:00000000000000000000000000000000\
+                     00000000000000000000000000000000",
+                    "docker.io/library/nginx:latest is locked but no longer used"]);
+
+    let err = ImageLock::from_str("---\nversion: 2\n").unwrap_err();
+    assert!(err.to_string().contains("lock file version"));
+}
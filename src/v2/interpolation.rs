@@ -1,5 +1,7 @@
 //! Interpolation of shell-style variables into strings.
 
+#[cfg(feature = "preserve_order")]
+use linked_hash_map::LinkedHashMap;
 use regex::{Captures, Regex};
 use serde::de::{self, Deserialize, Deserializer};
 use serde::ser::{Serialize, Serializer};
@@ -7,9 +9,13 @@ use std::collections::BTreeMap;
 use std::env;
 use std::error;
 use std::fmt::{self, Display};
+#[cfg(feature = "preserve_order")]
+use std::hash::Hash;
+use std::mem;
+use std::num;
 use std::path::{Path, PathBuf};
 use std::result;
-use std::str::FromStr;
+use std::str::{self, FromStr};
 use std::string;
 use void::Void;
 
@@ -267,6 +273,14 @@ impl IntoInvalidValueError for string::ParseError {
     // Just use the default method in this case.
 }
 
+impl IntoInvalidValueError for num::ParseIntError {
+    // Just use the default method in this case.
+}
+
+impl IntoInvalidValueError for str::ParseBoolError {
+    // Just use the default method in this case.
+}
+
 impl IntoInvalidValueError for Void {
     fn into_invalid_value_error(self, _: &str, _: &str) -> Error {
         unreachable!()
@@ -332,6 +346,8 @@ macro_rules! impl_interpolatable_value {
 }
 
 impl_interpolatable_value!(String);
+impl_interpolatable_value!(u32);
+impl_interpolatable_value!(bool);
 
 /// This can be parsed and formatted, but not using the usual APIs.
 impl InterpolatableValue for PathBuf {
@@ -359,7 +375,7 @@ impl<'a, T> Display for DisplayInterpolatableValue<'a, T>
 
 /// Either a raw, unparsed string, or a value of the specified type.  This
 /// is the internal, private implementation of `RawOr`.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum RawOrValue<T>
     where T: InterpolatableValue
 {
@@ -418,7 +434,7 @@ enum RawOrValue<T>
 /// assert!(dc::escape::<dc::NetworkMode, _>("invalid").is_err());
 /// assert!(dc::raw::<dc::NetworkMode, _>("invalid").is_err());
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RawOr<T>(RawOrValue<T>) where T: InterpolatableValue;
 
 /// `InterpolatableValue` is basically just a string that we parse for
@@ -648,11 +664,64 @@ impl<T: InterpolateAll> InterpolateAll for Vec<T> {
     }
 }
 
-impl<K: Ord + Clone, T: InterpolateAll> InterpolateAll for BTreeMap<K, T> {
+/// Interpolate each of `keys`, and fail if two of them become the same.
+fn interpolate_keys<'a, K, I>(keys: I) -> Result<Vec<K>>
+    where K: 'a + Eq + Clone + Display + InterpolateAll,
+          I: Iterator<Item = &'a K>
+{
+    let mut interpolated: Vec<K> = vec![];
+    for key in keys {
+        let mut new_key = key.clone();
+        new_key.interpolate_all()?;
+        if interpolated.contains(&new_key) {
+            return Err(ErrorKind::DuplicateInterpolatedKey(new_key.to_string())
+                .into());
+        }
+        interpolated.push(new_key);
+    }
+    Ok(interpolated)
+}
+
+/// Interpolate both the keys and the values of a map.  Most maps have
+/// `String` keys, which are left alone, but a few, like
+/// `Service::depends_on`, have keys which may be interpolated.
+impl<K, T> InterpolateAll for BTreeMap<K, T>
+    where K: Ord + Clone + Display + InterpolateAll,
+          T: InterpolateAll
+{
     fn interpolate_all(&mut self) -> Result<()> {
         for (_k, v) in self.iter_mut() {
             v.interpolate_all()?;
         }
+        let keys = interpolate_keys(self.keys())?;
+        if keys.iter().zip(self.keys()).any(|(new, old)| new != old) {
+            let values = mem::replace(self, BTreeMap::new())
+                .into_iter()
+                .map(|(_, v)| v);
+            self.extend(keys.into_iter().zip(values));
+        }
+        Ok(())
+    }
+}
+
+/// Interpolate both the keys and the values of a map, keeping the keys in
+/// their original order.
+#[cfg(feature = "preserve_order")]
+impl<K, T> InterpolateAll for LinkedHashMap<K, T>
+    where K: Hash + Eq + Clone + Display + InterpolateAll,
+          T: InterpolateAll
+{
+    fn interpolate_all(&mut self) -> Result<()> {
+        for (_k, v) in self.iter_mut() {
+            v.interpolate_all()?;
+        }
+        let keys = interpolate_keys(self.keys())?;
+        if keys.iter().zip(self.keys()).any(|(new, old)| new != old) {
+            let values = mem::replace(self, LinkedHashMap::new())
+                .into_iter()
+                .map(|(_, v)| v);
+            self.extend(keys.into_iter().zip(values));
+        }
         Ok(())
     }
 }
@@ -0,0 +1,97 @@
+//! A helper for building JSON request bodies and manifests by hand, for
+//! formats which have too many optional fields to be worth describing
+//! with `#[derive(Serialize)]`.
+
+use serde::ser::Serialize;
+use serde_json::value::{Map as JsonMap, Value, to_value};
+use std::fmt;
+
+use errors::*;
+use super::{Map, RawOr};
+use super::interpolation::InterpolatableValue;
+
+/// A JSON object under construction.  We leave out empty values, which
+/// consumers treat the same as missing ones, to keep the output readable.
+#[derive(Debug, Default)]
+pub struct JsonObject(JsonMap<String, Value>);
+
+impl JsonObject {
+    /// Create an empty object.
+    pub fn new() -> JsonObject {
+        JsonObject(JsonMap::new())
+    }
+
+    /// Create an object from a map of strings, such as labels.
+    pub fn from_map(values: &Map<String, RawOr<String>>) -> Result<JsonObject> {
+        let mut obj = JsonObject::new();
+        for (key, value) in values {
+            obj.set(key, value.value()?);
+        }
+        Ok(obj)
+    }
+
+    /// Is this object empty?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Set `key` to `value`.
+    pub fn set<T: Serialize>(&mut self, key: &str, value: T) {
+        self.0.insert(key.to_owned(), to_value(value));
+    }
+
+    /// Set `key` to `value`, if it has one.
+    pub fn set_opt<T>(&mut self, key: &str, value: &Option<RawOr<T>>) -> Result<()>
+        where T: InterpolatableValue + Serialize
+    {
+        if let Some(ref value) = *value {
+            self.set(key, value.value()?);
+        }
+        Ok(())
+    }
+
+    /// Set `key` to a list, unless it's empty.
+    pub fn set_list<T: Serialize>(&mut self, key: &str, values: Vec<T>) {
+        if !values.is_empty() {
+            self.set(key, values);
+        }
+    }
+
+    /// Set `key` to a map of strings, unless it's empty.
+    pub fn set_map(&mut self,
+                   key: &str,
+                   values: &Map<String, RawOr<String>>)
+                   -> Result<()> {
+        let obj = JsonObject::from_map(values)?;
+        self.set_object(key, obj);
+        Ok(())
+    }
+
+    /// Set `key` to another object, unless it's empty.
+    pub fn set_object(&mut self, key: &str, obj: JsonObject) {
+        if !obj.is_empty() {
+            self.0.insert(key.to_owned(), obj.into_value());
+        }
+    }
+
+    /// Remove `key`, returning its old value.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.0.remove(key)
+    }
+
+    /// Convert to a JSON value.
+    pub fn into_value(self) -> Value {
+        Value::Object(self.0)
+    }
+}
+
+/// Format each value in `values` as a string.
+pub fn strings<T>(values: &[RawOr<T>]) -> Result<Vec<String>>
+    where T: InterpolatableValue + fmt::Display
+{
+    let mut result = vec![];
+    for value in values {
+        result.push(value.value()?.to_string());
+    }
+    Ok(result)
+}
@@ -0,0 +1,929 @@
+//! Converting a `File` to Kubernetes manifests, in the style of
+//! [kompose](http://kompose.io/).
+//!
+//! Each service becomes a `Deployment`, plus a `Service` if it publishes
+//! any ports.  Named volumes become `PersistentVolumeClaim`s, `env_file`
+//! entries become a `ConfigMap`, and secrets with a `file` or inlined
+//! contents become `Secret`s, which are mounted into the pods that use them.
+//! Many `docker-compose` features have no Kubernetes equivalent, so we
+//! report them instead of silently dropping them.
+//!
+//! A `healthcheck` becomes a `livenessProbe`, with `start_period` used as
+//! the initial delay.  Kubernetes never runs the image's own check, so we
+//! can only translate a healthcheck with a `test`.
+
+use serde_json::value::Value;
+use serde_yaml;
+use std::cmp;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use errors::*;
+use super::{File, Healthcheck, HostVolume, MemorySize, MountType, Ports, RawOr,
+            RestartMode, Secret, Service, VolumePermissions, parse_duration};
+use super::docker_run::command_line_args;
+use super::env_file::EnvFile;
+use super::json_object::{JsonObject, strings};
+use super::validate::escape_pointer_segment;
+
+/// The label we use to connect each `Deployment` to its pods and
+/// `Service`, matching kompose.
+const SERVICE_LABEL: &'static str = "io.kompose.service";
+
+/// How much storage to request for each `PersistentVolumeClaim`.
+const DEFAULT_STORAGE_REQUEST: &'static str = "100Mi";
+
+/// Kubernetes manifests generated from a `File`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KubernetesManifests {
+    /// The generated Kubernetes objects, in a stable order: the objects
+    /// for each service, followed by volume claims and secrets.
+    pub objects: Vec<Value>,
+    /// JSON pointers to every field in the file which couldn't be
+    /// converted, such as `/services/web/privileged`.
+    pub unsupported: Vec<String>,
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    pub _hidden: (),
+}
+
+impl KubernetesManifests {
+    /// Convert `file`, looking up `env_file` and secret paths relative to
+    /// `base`.  All fields must already be interpolated.
+    pub fn for_file(file: &File, base: &Path) -> Result<KubernetesManifests> {
+        let mut manifests = KubernetesManifests {
+            objects: vec![],
+            unsupported: vec![],
+            _hidden: (),
+        };
+        for (name, service) in &file.services {
+            manifests.add_service(name, service, base)?;
+        }
+        for (name, volume) in &file.volumes {
+            // External volumes are expected to have existing claims.
+            if volume.external != Some(true) {
+                manifests.add_volume_claim(name);
+            }
+        }
+        for (name, secret) in &file.secrets {
+            manifests.add_secret(name, secret, base)?;
+        }
+        Ok(manifests)
+    }
+
+    /// Was everything in the file converted?
+    pub fn is_complete(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+
+    /// Write all our manifests as a multi-document YAML stream, suitable
+    /// for `kubectl apply -f`.
+    pub fn write<W>(&self, w: &mut W) -> Result<()>
+        where W: io::Write
+    {
+        for object in &self.objects {
+            serde_yaml::to_writer(w, object)?;
+            w.write_all(b"\n").chain_err(|| "I/O error")?;
+        }
+        Ok(())
+    }
+
+    /// Record that the field at `pointer` can't be converted.
+    fn unsupported(&mut self, pointer: String) {
+        self.unsupported.push(pointer);
+    }
+
+    /// Record that `field` of the service `name` can't be converted.
+    fn unsupported_field(&mut self, name: &str, field: &str) {
+        let pointer = format!("/services/{}/{}", escape_pointer_segment(name), field);
+        self.unsupported(pointer);
+    }
+
+    /// Convert a single service.
+    fn add_service(&mut self,
+                   name: &str,
+                   service: &Service,
+                   base: &Path)
+                   -> Result<()> {
+        let unsupported_fields = [
+            ("build", service.build.is_some()),
+            ("cgroup_parent", service.cgroup_parent.is_some()),
+            ("configs", !service.configs.is_empty()),
+            ("container_name", service.container_name.is_some()),
+            ("cpu_quota", service.cpu_quota.is_some()),
+            ("cpu_shares", service.cpu_shares.is_some()),
+            ("depends_on", !service.depends_on.is_empty()),
+            ("devices", !service.devices.is_empty()),
+            ("dns", !service.dns.is_empty()),
+            ("dns_search", !service.dns_search.is_empty()),
+            ("domainname", service.domainname.is_some()),
+            ("extends", service.extends.is_some()),
+            ("external_links", !service.external_links.is_empty()),
+            ("group_add", !service.group_add.is_empty()),
+            ("init", service.init == Some(true)),
+            ("ipc", service.ipc.is_some()),
+            ("isolation", service.isolation.is_some()),
+            ("links", !service.links.is_empty()),
+            ("logging", service.logging.is_some()),
+            ("mac_address", service.mac_address.is_some()),
+            ("memswap_limit", service.memswap_limit.is_some()),
+            ("network_mode", service.network_mode.is_some()),
+            ("networks", !service.networks.is_empty()),
+            ("oom_score_adj", service.oom_score_adj.is_some()),
+            ("pid", service.pid.is_some()),
+            ("privileged", service.privileged),
+            ("security_opt", !service.security_opt.is_empty()),
+            ("shm_size", service.shm_size.is_some()),
+            ("stop_signal", service.stop_signal.is_some()),
+            ("volume_driver", service.volume_driver.is_some()),
+            ("volumes_from", !service.volumes_from.is_empty()),
+            ("mrenclave", service.scone.mrenclave.is_some()),
+            ("fspf_path", service.scone.fspf_path.is_some()),
+            ("fspf_key", service.scone.fspf_key.is_some()),
+            ("fspf_tag", service.scone.fspf_tag.is_some()),
+            ("not_scone", service.scone.not_scone.is_some()),
+        ];
+        for &(field, present) in &unsupported_fields {
+            if present {
+                self.unsupported_field(name, field);
+            }
+        }
+
+        let k8s_name = kubernetes_name(name);
+        let mut container = JsonObject::new();
+        container.set("name", &k8s_name);
+        match service.image {
+            Some(ref image) => container.set("image", image.value()?.to_string()),
+            None => return Err(ErrorKind::MissingImage(name.to_owned()).into()),
+        }
+        if let Some(ref entrypoint) = service.entrypoint {
+            container.set("command", command_line_args(entrypoint)?);
+        }
+        if let Some(ref command) = service.command {
+            container.set("args", command_line_args(command)?);
+        }
+        container.set_opt("workingDir", &service.working_dir)?;
+        if service.stdin_open {
+            container.set("stdin", true);
+        }
+        if service.tty {
+            container.set("tty", true);
+        }
+
+        let mut env = vec![];
+        for (key, value) in &service.environment {
+            let mut var = JsonObject::new();
+            var.set("name", key);
+            var.set("value", value.value()?);
+            env.push(var.into_value());
+        }
+        container.set_list("env", env);
+        if !service.env_files.is_empty() {
+            let config_map_name = format!("{}-env", k8s_name);
+            self.add_env_config_map(&config_map_name, name, service, base)?;
+            let mut config_map_ref = JsonObject::new();
+            config_map_ref.set("name", config_map_name);
+            let mut env_from = JsonObject::new();
+            env_from.set_object("configMapRef", config_map_ref);
+            container.set("envFrom", vec![env_from.into_value()]);
+        }
+
+        let mut ports = vec![];
+        for port in &service.ports {
+            let port = port.value()?;
+            for container_port in port_numbers(&port.container_ports) {
+                ports.push(container_port_object(container_port, &port.protocol));
+            }
+        }
+        for expose in &service.expose {
+            let expose = expose.value()?;
+            let mut parts = expose.splitn(2, '/');
+            let number = parts.next().unwrap_or("");
+            let protocol = parts.next().map(|p| p.to_owned());
+            match number.parse::<u16>() {
+                Ok(number) => ports.push(container_port_object(number, &protocol)),
+                Err(_) => return Err(Error::invalid_value("port", expose.to_owned())),
+            }
+        }
+        container.set_list("ports", ports);
+
+        let mut security_context = JsonObject::new();
+        let mut capabilities = JsonObject::new();
+        capabilities.set_list("add", strings(&service.cap_add)?);
+        capabilities.set_list("drop", strings(&service.cap_drop)?);
+        security_context.set_object("capabilities", capabilities);
+        if let Some(ref user) = service.user {
+            let user = user.value()?;
+            let mut ids = user.splitn(2, ':').map(|id| id.parse::<u32>());
+            match (ids.next(), ids.next()) {
+                (Some(Ok(uid)), None) => security_context.set("runAsUser", uid),
+                (Some(Ok(uid)), Some(Ok(gid))) => {
+                    security_context.set("runAsUser", uid);
+                    security_context.set("runAsGroup", gid);
+                }
+                // Kubernetes only supports numeric IDs.
+                _ => self.unsupported_field(name, "user"),
+            }
+        }
+        container.set_object("securityContext", security_context);
+
+        container.set_object("resources", self.resources(service)?);
+
+        if let Some(ref healthcheck) = service.healthcheck {
+            match healthcheck.docker_test()? {
+                // Kubernetes ignores the image's check, so we're done.
+                Some(ref test) if test[0] == "NONE" => {}
+                Some(test) => {
+                    let probe = liveness_probe(&test, healthcheck)?;
+                    container.set_object("livenessProbe", probe);
+                }
+                None => self.unsupported_field(name, "healthcheck"),
+            }
+        }
+
+        let (volume_mounts, volumes) = self.volumes(name, service)?;
+        container.set_list("volumeMounts", volume_mounts);
+
+        let mut pod_spec = JsonObject::new();
+        pod_spec.set("containers", vec![container.into_value()]);
+        pod_spec.set_list("volumes", volumes);
+        pod_spec.set_opt("hostname", &service.hostname)?;
+        let mut host_aliases = vec![];
+        for host in &service.extra_hosts {
+            let host = host.value()?;
+            let mut alias = JsonObject::new();
+            alias.set("ip", host.address.to_string());
+            alias.set("hostnames", vec![&host.hostname]);
+            host_aliases.push(alias.into_value());
+        }
+        pod_spec.set_list("hostAliases", host_aliases);
+        if let Some(ref restart) = service.restart {
+            // Deployments always restart their pods.
+            match *restart.value()? {
+                RestartMode::Always | RestartMode::UnlessStopped => {}
+                _ => self.unsupported_field(name, "restart"),
+            }
+        }
+
+        let mut template = JsonObject::new();
+        template.set_object("metadata", selector_labels(name));
+        template.set_object("spec", pod_spec);
+
+        let mut selector = JsonObject::new();
+        selector.set_object("matchLabels", selector_labels(name));
+
+        let mut spec = JsonObject::new();
+        let mut replicas = 1;
+        let mut metadata = metadata(&k8s_name, name);
+        let mut annotations = JsonObject::from_map(&service.labels)?;
+        if let Some(ref deploy) = service.deploy {
+            if let Some(ref mode) = deploy.mode {
+                if mode.value()? != "replicated" {
+                    self.unsupported_field(name, "deploy/mode");
+                }
+            }
+            if let Some(count) = deploy.replicas {
+                replicas = count;
+            }
+            for (key, value) in &deploy.labels {
+                annotations.set(key, value.value()?);
+            }
+        }
+        metadata.set_object("annotations", annotations);
+        spec.set("replicas", replicas);
+        spec.set_object("selector", selector);
+        spec.set_object("template", template);
+        self.objects.push(manifest("apps/v1", "Deployment", metadata, "spec", spec));
+
+        self.add_kubernetes_service(name, service)
+    }
+
+    /// Build the `resources` for a container.
+    fn resources(&mut self, service: &Service) -> Result<JsonObject> {
+        let mut limits = JsonObject::new();
+        let mut requests = JsonObject::new();
+        if let Some(ref mem) = service.mem_limit {
+            limits.set("memory", memory_quantity(*mem.value()?));
+        }
+        let resources = service.deploy.as_ref().and_then(|d| d.resources.as_ref());
+        if let Some(resources) = resources {
+            if let Some(ref spec) = resources.limits {
+                limits.set_opt("cpu", &spec.cpus)?;
+                if let Some(ref mem) = spec.memory {
+                    limits.set("memory", memory_quantity(*mem.value()?));
+                }
+            }
+            if let Some(ref spec) = resources.reservations {
+                requests.set_opt("cpu", &spec.cpus)?;
+                if let Some(ref mem) = spec.memory {
+                    requests.set("memory", memory_quantity(*mem.value()?));
+                }
+            }
+        }
+        let mut obj = JsonObject::new();
+        obj.set_object("limits", limits);
+        obj.set_object("requests", requests);
+        Ok(obj)
+    }
+
+    /// Build the `volumeMounts` for a container and the `volumes` for its
+    /// pod.
+    fn volumes(&mut self,
+               name: &str,
+               service: &Service)
+               -> Result<(Vec<Value>, Vec<Value>)> {
+        let k8s_name = kubernetes_name(name);
+        let mut mounts = vec![];
+        let mut volumes = vec![];
+        let mut volume_names: Vec<String> = vec![];
+        for (i, mount) in service.volumes.iter().enumerate() {
+            let mount = mount.value()?;
+            let mut source = JsonObject::new();
+            let volume_name = match mount.host {
+                Some(HostVolume::Name(ref volume)) => {
+                    let mut claim = JsonObject::new();
+                    claim.set("claimName", kubernetes_name(volume));
+                    source.set_object("persistentVolumeClaim", claim);
+                    kubernetes_name(volume)
+                }
+                Some(HostVolume::Path(ref path)) if path.is_absolute() => {
+                    let mut host_path = JsonObject::new();
+                    host_path.set("path", path.display().to_string());
+                    source.set_object("hostPath", host_path);
+                    format!("{}-hostpath{}", k8s_name, i)
+                }
+                Some(_) => {
+                    // Relative paths mean nothing inside a cluster.
+                    self.unsupported_field(name, &format!("volumes/{}", i));
+                    continue;
+                }
+                None => {
+                    let mut empty_dir = JsonObject::new();
+                    if mount.effective_mount_type() == MountType::Tmpfs {
+                        empty_dir.set("medium", "Memory");
+                    }
+                    source.set("emptyDir", empty_dir.into_value());
+                    format!("{}-volume{}", k8s_name, i)
+                }
+            };
+
+            let mut volume_mount = JsonObject::new();
+            volume_mount.set("name", &volume_name);
+            volume_mount.set("mountPath", &mount.container);
+            if mount.permissions == VolumePermissions::ReadOnly {
+                volume_mount.set("readOnly", true);
+            }
+            mounts.push(volume_mount.into_value());
+
+            // A volume may be mounted more than once, but only declared
+            // once.
+            if !volume_names.contains(&volume_name) {
+                let mut volume = source;
+                volume.set("name", &volume_name);
+                volumes.push(volume.into_value());
+                volume_names.push(volume_name);
+            }
+        }
+
+        for (i, path) in service.tmpfs.iter().enumerate() {
+            let volume_name = format!("{}-tmpfs{}", k8s_name, i);
+            let mut volume_mount = JsonObject::new();
+            volume_mount.set("name", &volume_name);
+            volume_mount.set("mountPath", path.value()?);
+            mounts.push(volume_mount.into_value());
+
+            let mut empty_dir = JsonObject::new();
+            empty_dir.set("medium", "Memory");
+            let mut volume = JsonObject::new();
+            volume.set("name", &volume_name);
+            volume.set("emptyDir", empty_dir.into_value());
+            volumes.push(volume.into_value());
+        }
+
+        // Each `Secret` holds a single key named after the secret, which we
+        // mount where `docker-compose` would put it.
+        for (i, secret) in service.secrets.iter().enumerate() {
+            let secret = secret.value()?;
+            if secret.uid.is_some() || secret.gid.is_some() {
+                self.unsupported_field(name, &format!("secrets/{}", i));
+            }
+            let target = secret.target.as_ref().unwrap_or(&secret.source);
+            let mount_path = if target.starts_with('/') {
+                target.to_owned()
+            } else {
+                format!("/run/secrets/{}", target)
+            };
+            let volume_name = format!("{}-secret{}", k8s_name, i);
+            let mut volume_mount = JsonObject::new();
+            volume_mount.set("name", &volume_name);
+            volume_mount.set("mountPath", mount_path);
+            volume_mount.set("subPath", &secret.source);
+            volume_mount.set("readOnly", true);
+            mounts.push(volume_mount.into_value());
+
+            let mut source = JsonObject::new();
+            source.set("secretName", kubernetes_name(&secret.source));
+            if let Some(mode) = secret.mode {
+                source.set("defaultMode", mode);
+            }
+            let mut volume = JsonObject::new();
+            volume.set("name", &volume_name);
+            volume.set_object("secret", source);
+            volumes.push(volume.into_value());
+        }
+        Ok((mounts, volumes))
+    }
+
+    /// Add a Kubernetes `Service` exposing the published ports of
+    /// `service`, if it has any.
+    fn add_kubernetes_service(&mut self, name: &str, service: &Service) -> Result<()> {
+        let mut ports = vec![];
+        for (i, port) in service.ports.iter().enumerate() {
+            let port = port.value()?;
+            if port.host_address.is_some() {
+                self.unsupported_field(name, &format!("ports/{}", i));
+            }
+            let container_ports = port_numbers(&port.container_ports);
+            let host_ports = match port.host_ports {
+                Some(ref ports) => port_numbers(ports),
+                None => container_ports.clone(),
+            };
+            let protocol = kubernetes_protocol(&port.protocol);
+            let pairs = host_ports.into_iter().zip(container_ports);
+            for (host_port, container_port) in pairs {
+                // Port names must be unique, even across protocols.
+                let port_name = if protocol == "TCP" {
+                    host_port.to_string()
+                } else {
+                    format!("{}-{}", host_port, protocol.to_lowercase())
+                };
+                let mut obj = JsonObject::new();
+                obj.set("name", port_name);
+                obj.set("port", host_port);
+                obj.set("protocol", &protocol);
+                obj.set("targetPort", container_port);
+                ports.push(obj.into_value());
+            }
+        }
+        if ports.is_empty() {
+            return Ok(());
+        }
+
+        let mut spec = JsonObject::new();
+        spec.set("ports", ports);
+        spec.set_object("selector", selector_labels(name));
+        let metadata = metadata(&kubernetes_name(name), name);
+        self.objects.push(manifest("v1", "Service", metadata, "spec", spec));
+        Ok(())
+    }
+
+    /// Add a `ConfigMap` containing the variables from the `env_file`
+    /// entries of `service`.
+    fn add_env_config_map(&mut self,
+                          config_map_name: &str,
+                          name: &str,
+                          service: &Service,
+                          base: &Path)
+                          -> Result<()> {
+        let mut data = JsonObject::new();
+        for path in &service.env_files {
+            let env_file = EnvFile::load(&base.join(path.value()?))?;
+            for (key, value) in &env_file.to_environment()? {
+                data.set(key, value.value()?);
+            }
+        }
+        let metadata = metadata(config_map_name, name);
+        self.objects.push(manifest("v1", "ConfigMap", metadata, "data", data));
+        Ok(())
+    }
+
+    /// Add a `PersistentVolumeClaim` for the named volume `name`.
+    fn add_volume_claim(&mut self, name: &str) {
+        let mut requests = JsonObject::new();
+        requests.set("storage", DEFAULT_STORAGE_REQUEST);
+        let mut resources = JsonObject::new();
+        resources.set_object("requests", requests);
+        let mut spec = JsonObject::new();
+        spec.set("accessModes", vec!["ReadWriteOnce"]);
+        spec.set_object("resources", resources);
+
+        let mut metadata = JsonObject::new();
+        metadata.set("name", kubernetes_name(name));
+        let claim = manifest("v1", "PersistentVolumeClaim", metadata, "spec", spec);
+        self.objects.push(claim);
+    }
+
+    /// Add a `Secret` containing the contents of `secret`, which are either
+    /// inlined or loaded from its `file`.
+    fn add_secret(&mut self, name: &str, secret: &Secret, base: &Path) -> Result<()> {
+        if secret.external == Some(true) {
+            return Ok(());
+        }
+        let pointer = format!("/secrets/{}", escape_pointer_segment(name));
+        let content = match (&secret.content, &secret.file) {
+            (&Some(ref content), _) => content.as_bytes().to_owned(),
+            (&None, &Some(ref file)) => {
+                let path = base.join(file);
+                let mkerr = || ErrorKind::ReadFile(path.clone());
+                let mut content = vec![];
+                let mut f = fs::File::open(&path).chain_err(&mkerr)?;
+                io::Read::read_to_end(&mut f, &mut content).chain_err(&mkerr)?;
+                content
+            }
+            (&None, &None) => {
+                // Secrets generated from a `kind` and `length` only make
+                // sense to SCONE.
+                self.unsupported(pointer);
+                return Ok(());
+            }
+        };
+        // `stringData` can only hold text, and we don't base64-encode
+        // binary secrets for `data` yet.
+        let content = match String::from_utf8(content) {
+            Ok(content) => content,
+            Err(_) => {
+                self.unsupported(pointer);
+                return Ok(());
+            }
+        };
+
+        let mut data = JsonObject::new();
+        data.set(name, content);
+        let mut metadata = JsonObject::new();
+        metadata.set("name", kubernetes_name(name));
+        let mut obj = manifest_object("v1", "Secret", metadata);
+        obj.set("type", "Opaque");
+        obj.set_object("stringData", data);
+        self.objects.push(obj.into_value());
+        Ok(())
+    }
+}
+
+/// Convert a `docker-compose` name into a valid Kubernetes object name,
+/// which may only contain lowercase letters, digits and `-`.
+fn kubernetes_name(name: &str) -> String {
+    name.to_lowercase().replace("_", "-").replace(".", "-")
+}
+
+/// The labels used to select the pods for the service `name`.
+fn selector_labels(name: &str) -> JsonObject {
+    let mut labels = JsonObject::new();
+    labels.set(SERVICE_LABEL, kubernetes_name(name));
+    let mut obj = JsonObject::new();
+    obj.set_object("labels", labels);
+    obj
+}
+
+/// Object metadata for something belonging to the service `service`.
+fn metadata(name: &str, service: &str) -> JsonObject {
+    let mut obj = selector_labels(service);
+    obj.set("name", name);
+    obj
+}
+
+/// The common fields of every Kubernetes object.
+fn manifest_object(api_version: &str, kind: &str, metadata: JsonObject) -> JsonObject {
+    let mut obj = JsonObject::new();
+    obj.set("apiVersion", api_version);
+    obj.set("kind", kind);
+    obj.set_object("metadata", metadata);
+    obj
+}
+
+/// A Kubernetes object with a single body field, such as `spec` or
+/// `data`.
+fn manifest(api_version: &str,
+            kind: &str,
+            metadata: JsonObject,
+            key: &str,
+            body: JsonObject)
+            -> Value {
+    let mut obj = manifest_object(api_version, kind, metadata);
+    obj.set_object(key, body);
+    obj.into_value()
+}
+
+/// The individual port numbers in `ports`.
+fn port_numbers(ports: &Ports) -> Vec<u16> {
+    match *ports {
+        Ports::Port(port) => vec![port],
+        Ports::Range(first, last) => (first..last + 1).collect(),
+    }
+}
+
+/// Kubernetes spells protocols in uppercase.
+fn kubernetes_protocol(protocol: &Option<String>) -> String {
+    protocol.as_ref().map(|p| p.to_uppercase()).unwrap_or_else(|| "TCP".to_owned())
+}
+
+/// An entry in a container's `ports` list.
+fn container_port_object(port: u16, protocol: &Option<String>) -> Value {
+    let mut obj = JsonObject::new();
+    obj.set("containerPort", port);
+    obj.set("protocol", kubernetes_protocol(protocol));
+    obj.into_value()
+}
+
+/// Build a `livenessProbe` which runs `test`, a healthcheck in the form
+/// returned by `Healthcheck::docker_test`.
+fn liveness_probe(test: &[String], healthcheck: &Healthcheck) -> Result<JsonObject> {
+    let command = if test[0] == "CMD" {
+        test[1..].to_vec()
+    } else {
+        vec!["/bin/sh".to_owned(), "-c".to_owned(), test[1..].join(" ")]
+    };
+    let mut exec = JsonObject::new();
+    exec.set("command", command);
+    let mut probe = JsonObject::new();
+    probe.set_object("exec", exec);
+    if let Some(seconds) = probe_seconds(&healthcheck.interval)? {
+        probe.set("periodSeconds", seconds);
+    }
+    if let Some(seconds) = probe_seconds(&healthcheck.timeout)? {
+        probe.set("timeoutSeconds", seconds);
+    }
+    if let Some(seconds) = probe_seconds(&healthcheck.start_period)? {
+        probe.set("initialDelaySeconds", seconds);
+    }
+    if let Some(retries) = healthcheck.retries {
+        probe.set("failureThreshold", retries);
+    }
+    Ok(probe)
+}
+
+/// Probes measure time in whole seconds, so round `duration` up, and
+/// never return less than one second.
+fn probe_seconds(duration: &Option<RawOr<String>>) -> Result<Option<u64>> {
+    match *duration {
+        Some(ref duration) => {
+            let duration = parse_duration(duration.value()?)?;
+            let round_up = if duration.subsec_nanos() > 0 { 1 } else { 0 };
+            Ok(Some(cmp::max(duration.as_secs() + round_up, 1)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Kubernetes uses `Mi` where Docker uses `m`, which means "milli" to
+/// Kubernetes.
+fn memory_quantity(size: MemorySize) -> String {
+    let bytes = size.to_bytes();
+    if bytes != 0 && bytes % (1024 * 1024 * 1024) == 0 {
+        format!("{}Gi", bytes / (1024 * 1024 * 1024))
+    } else if bytes != 0 && bytes % (1024 * 1024) == 0 {
+        format!("{}Mi", bytes / (1024 * 1024))
+    } else if bytes != 0 && bytes % 1024 == 0 {
+        format!("{}Ki", bytes / 1024)
+    } else {
+        bytes.to_string()
+    }
+}
+
+#[cfg(test)]
+fn assert_yaml_eq(actual: &Value, expected: &str) {
+    let expected: Value = serde_yaml::from_str(expected).unwrap();
+    assert_eq!(actual, &expected);
+}
+
+#[test]
+fn kubernetes_manifests_match_golden_files() {
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "2"
+services:
+  web_app:
+    image: "example/web:1.0"
+    command: ["rails", "server"]
+    environment:
+      RAILS_ENV: "production"
+    mem_limit: "512m"
+    ports: ["8080:80"]
+    volumes: ["data:/data:ro"]
+    privileged: true
+    network_mode: "host"
+volumes:
+  data: {}
+"#)
+        .unwrap();
+    let manifests = KubernetesManifests::for_file(&file, Path::new(".")).unwrap();
+    assert_eq!(manifests.unsupported,
+               vec!["/services/web_app/network_mode", "/services/web_app/privileged"]);
+    assert_eq!(manifests.objects.len(), 3);
+
+    assert_yaml_eq(&manifests.objects[0], r#"---
+apiVersion: "apps/v1"
+kind: "Deployment"
+metadata:
+  labels:
+    io.kompose.service: "web-app"
+  name: "web-app"
+spec:
+  replicas: 1
+  selector:
+    matchLabels:
+      io.kompose.service: "web-app"
+  template:
+    metadata:
+      labels:
+        io.kompose.service: "web-app"
+    spec:
+      containers:
+        - args: ["rails", "server"]
+          env:
+            - name: "RAILS_ENV"
+              value: "production"
+          image: "example/web:1.0"
+          name: "web-app"
+          ports:
+            - containerPort: 80
+              protocol: "TCP"
+          resources:
+            limits:
+              memory: "512Mi"
+          volumeMounts:
+            - mountPath: "/data"
+              name: "data"
+              readOnly: true
+      volumes:
+        - name: "data"
+          persistentVolumeClaim:
+            claimName: "data"
+"#);
+
+    assert_yaml_eq(&manifests.objects[1], r#"---
+apiVersion: "v1"
+kind: "Service"
+metadata:
+  labels:
+    io.kompose.service: "web-app"
+  name: "web-app"
+spec:
+  ports:
+    - name: "8080"
+      port: 8080
+      protocol: "TCP"
+      targetPort: 80
+  selector:
+    io.kompose.service: "web-app"
+"#);
+
+    assert_yaml_eq(&manifests.objects[2], r#"---
+apiVersion: "v1"
+kind: "PersistentVolumeClaim"
+metadata:
+  name: "data"
+spec:
+  accessModes: ["ReadWriteOnce"]
+  resources:
+    requests:
+      storage: "100Mi"
+"#);
+
+    let mut out = vec![];
+    manifests.write(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out.matches("---").count(), 3);
+}
+
+#[test]
+fn kubernetes_manifests_include_env_files_and_secrets() {
+    use std::str::FromStr;
+    use super::{SecretContent, TempDir};
+
+    let tmp = TempDir::new("compose_yml_k8s_test");
+    let dir = tmp.path();
+    let mut f = fs::File::create(dir.join("worker.env")).unwrap();
+    io::Write::write_all(&mut f, b"GREETING=hello\n").unwrap();
+    let mut f = fs::File::create(dir.join("api_key.txt")).unwrap();
+    io::Write::write_all(&mut f, b"s3cret").unwrap();
+
+    let file = File::from_str(r#"---
+version: "3.1"
+services:
+  worker:
+    image: "example/worker"
+    env_file: ["worker.env"]
+    secrets:
+      - source: "api_key"
+        mode: 256
+secrets:
+  api_key:
+    file: "api_key.txt"
+"#)
+        .unwrap();
+    let manifests = KubernetesManifests::for_file(&file, dir).unwrap();
+    assert!(manifests.is_complete());
+    assert_yaml_eq(&manifests.objects[0], r#"---
+apiVersion: "v1"
+kind: "ConfigMap"
+metadata:
+  labels:
+    io.kompose.service: "worker"
+  name: "worker-env"
+data:
+  GREETING: "hello"
+"#);
+    let env_from = manifests.objects[1]
+        .pointer("/spec/template/spec/containers/0/envFrom/0/configMapRef/name");
+    assert_eq!(env_from.and_then(|v| v.as_str()), Some("worker-env"));
+    let pod_spec = manifests.objects[1].pointer("/spec/template/spec").unwrap();
+    assert_yaml_eq(pod_spec.pointer("/containers/0/volumeMounts").unwrap(),
+                   r#"---
+- name: "worker-secret0"
+  mountPath: "/run/secrets/api_key"
+  subPath: "api_key"
+  readOnly: true
+"#);
+    assert_yaml_eq(pod_spec.pointer("/volumes").unwrap(),
+                   r#"---
+- name: "worker-secret0"
+  secret:
+    secretName: "api-key"
+    defaultMode: 256
+"#);
+    assert_yaml_eq(&manifests.objects[2], r#"---
+apiVersion: "v1"
+kind: "Secret"
+metadata:
+  name: "api-key"
+type: "Opaque"
+stringData:
+  api_key: "s3cret"
+"#);
+
+    // Inlined contents are used as-is, and don't need a file.
+    let mut file = file;
+    {
+        let secret = file.secrets.get_mut("api_key").unwrap();
+        secret.file = None;
+        secret.content = Some(SecretContent::new(b"inlined".to_vec()));
+    }
+    let manifests = KubernetesManifests::for_file(&file, dir).unwrap();
+    assert!(manifests.is_complete());
+    let data = manifests.objects[2].pointer("/stringData/api_key");
+    assert_eq!(data.and_then(|v| v.as_str()), Some("inlined"));
+}
+
+#[test]
+fn kubernetes_manifests_turn_healthchecks_into_liveness_probes() {
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "3.4"
+services:
+  web:
+    image: "example/web"
+    healthcheck:
+      test: "curl -f http://localhost/"
+      interval: 30s
+      timeout: 1500ms
+      retries: 3
+      start_period: 1m
+  worker:
+    image: "example/worker"
+    healthcheck:
+      test: ["CMD", "/bin/check", "--quick"]
+  cache:
+    image: "redis"
+    healthcheck:
+      disable: true
+  db:
+    image: "postgres"
+    healthcheck:
+      interval: 10s
+"#)
+        .unwrap();
+    let manifests = KubernetesManifests::for_file(&file, Path::new(".")).unwrap();
+    assert_eq!(manifests.unsupported, vec!["/services/db/healthcheck"]);
+
+    let probe = |name: &str| {
+        let pointer = "/spec/template/spec/containers/0/livenessProbe";
+        manifests.objects
+            .iter()
+            .find(|obj| {
+                obj.pointer("/kind").and_then(|v| v.as_str()) == Some("Deployment") &&
+                obj.pointer("/metadata/name").and_then(|v| v.as_str()) == Some(name)
+            })
+            .and_then(|obj| obj.pointer(pointer))
+            .cloned()
+    };
+    assert_yaml_eq(&probe("web").unwrap(), r#"---
+exec:
+  command: ["/bin/sh", "-c", "curl -f http://localhost/"]
+periodSeconds: 30
+timeoutSeconds: 2
+initialDelaySeconds: 60
+failureThreshold: 3
+"#);
+    assert_yaml_eq(&probe("worker").unwrap(), r#"---
+exec:
+  command: ["/bin/check", "--quick"]
+"#);
+    assert_eq!(probe("cache"), None);
+    assert_eq!(probe("db"), None);
+}
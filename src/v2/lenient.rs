@@ -0,0 +1,752 @@
+//! Lenient parsing for `docker-compose.yml` files which use fields we
+//! don't support yet.
+//!
+//! Normally, we refuse to parse any file containing a key we don't
+//! recognize, because we can't round-trip it.  But newer versions of
+//! `docker-compose` add fields all the time, and it's often useful to
+//! inspect and edit the parts of a file which we _do_ understand.  A
+//! `LenientFile` strips out any unknown keys before parsing, remembers
+//! them, and puts them back when writing the file out again.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
+
+use errors::*;
+use serde_yaml;
+use super::File;
+use super::validate::{escape_pointer_segment, validate_file,
+                      validate_file_with_source};
+
+/// The keys we know how to parse at a particular place in a file.
+enum Shape {
+    /// We don't look inside this value.
+    Any,
+    /// A struct with the specified fields, each with its own shape.
+    Struct(&'static [(&'static str, &'static Shape)]),
+    /// A map with arbitrary keys, whose values all have the same shape.
+    MapOf(&'static Shape),
+    /// A list whose items all have the same shape.
+    SeqOf(&'static Shape),
+}
+
+// These tables need to be kept in sync with the serialized field names of
+// the corresponding structs, which `shape_tables_match_serialized_fields`
+// checks.  Values which may be written as either a string or a struct are
+// only checked when they're actually structs.
+
+static ANY: Shape = Shape::Any;
+
+static FILE: Shape = Shape::Struct(&[("version", &ANY),
+                                     ("name", &ANY),
+                                     ("services", &SERVICES),
+                                     ("volumes", &VOLUMES),
+                                     ("networks", &NETWORKS),
+                                     ("secrets", &SECRETS),
+                                     ("configs", &CONFIGS)]);
+
+static SERVICES: Shape = Shape::MapOf(&SERVICE);
+
+static SERVICE: Shape =
+    Shape::Struct(&[("build", &BUILD),
+                    ("cap_add", &ANY),
+                    ("cap_drop", &ANY),
+                    ("command", &ANY),
+                    ("cgroup_parent", &ANY),
+                    ("configs", &SERVICE_CONFIGS),
+                    ("container_name", &ANY),
+                    ("devices", &ANY),
+                    ("depends_on", &DEPENDENCIES),
+                    ("deploy", &DEPLOY),
+                    ("dns", &ANY),
+                    ("dns_search", &ANY),
+                    ("tmpfs", &ANY),
+                    ("entrypoint", &ANY),
+                    ("env_file", &ANY),
+                    ("environment", &ANY),
+                    ("expose", &ANY),
+                    ("extends", &EXTENDS),
+                    ("external_links", &ANY),
+                    ("extra_hosts", &ANY),
+                    ("healthcheck", &HEALTHCHECK),
+                    ("image", &ANY),
+                    ("init", &ANY),
+                    ("isolation", &ANY),
+                    ("labels", &ANY),
+                    ("links", &ANY),
+                    ("logging", &LOGGING),
+                    ("network_mode", &ANY),
+                    ("networks", &NETWORK_INTERFACES),
+                    ("pid", &ANY),
+                    ("ports", &PORTS),
+                    ("profiles", &ANY),
+                    ("secrets", &SERVICE_SECRETS),
+                    ("security_opt", &ANY),
+                    ("stop_signal", &ANY),
+                    ("volumes", &VOLUME_MOUNTS),
+                    ("volumes_from", &ANY),
+                    ("volume_driver", &ANY),
+                    ("cpu_shares", &ANY),
+                    ("cpu_quota", &ANY),
+                    ("domainname", &ANY),
+                    ("hostname", &ANY),
+                    ("ipc", &ANY),
+                    ("mac_address", &ANY),
+                    ("mem_limit", &ANY),
+                    ("memswap_limit", &ANY),
+                    ("privileged", &ANY),
+                    ("restart", &ANY),
+                    ("shm_size", &ANY),
+                    ("stdin_open", &ANY),
+                    ("tty", &ANY),
+                    ("user", &ANY),
+                    ("working_dir", &ANY),
+                    ("oom_score_adj", &ANY),
+                    ("group_add", &ANY),
+                    ("mrenclave", &ANY),
+                    ("fspf_path", &ANY),
+                    ("fspf_key", &ANY),
+                    ("fspf_tag", &ANY),
+                    ("not_scone", &ANY)]);
+
+static BUILD: Shape = Shape::Struct(&[("context", &ANY),
+                                      ("dockerfile", &ANY),
+                                      ("args", &ANY)]);
+
+static DEPENDENCIES: Shape = Shape::MapOf(&DEPENDENCY);
+
+static DEPENDENCY: Shape = Shape::Struct(&[("condition", &ANY)]);
+
+static DEPLOY: Shape = Shape::Struct(&[("mode", &ANY),
+                                       ("replicas", &ANY),
+                                       ("labels", &ANY),
+                                       ("resources", &RESOURCES)]);
+
+static RESOURCES: Shape = Shape::Struct(&[("limits", &RESOURCE_SPEC),
+                                          ("reservations", &RESOURCE_SPEC)]);
+
+static RESOURCE_SPEC: Shape = Shape::Struct(&[("cpus", &ANY), ("memory", &ANY)]);
+
+static EXTENDS: Shape = Shape::Struct(&[("service", &ANY), ("file", &ANY)]);
+
+static HEALTHCHECK: Shape = Shape::Struct(&[("test", &ANY),
+                                            ("interval", &ANY),
+                                            ("timeout", &ANY),
+                                            ("retries", &ANY),
+                                            ("start_period", &ANY),
+                                            ("disable", &ANY)]);
+
+static LOGGING: Shape = Shape::Struct(&[("driver", &ANY), ("options", &ANY)]);
+
+static PORTS: Shape = Shape::SeqOf(&PORT);
+
+static PORT: Shape = Shape::Struct(&[("host_ip", &ANY),
+                                     ("mode", &ANY),
+                                     ("protocol", &ANY),
+                                     ("published", &ANY),
+                                     ("target", &ANY)]);
+
+static SERVICE_CONFIGS: Shape = Shape::SeqOf(&SERVICE_CONFIG);
+
+static SERVICE_CONFIG: Shape = Shape::Struct(&[("gid", &ANY),
+                                               ("mode", &ANY),
+                                               ("source", &ANY),
+                                               ("target", &ANY),
+                                               ("uid", &ANY)]);
+
+static SERVICE_SECRETS: Shape = Shape::SeqOf(&SERVICE_SECRET);
+
+static SERVICE_SECRET: Shape = Shape::Struct(&[("gid", &ANY),
+                                               ("mode", &ANY),
+                                               ("source", &ANY),
+                                               ("target", &ANY),
+                                               ("uid", &ANY)]);
+
+static VOLUME_MOUNTS: Shape = Shape::SeqOf(&VOLUME_MOUNT);
+
+static VOLUME_MOUNT: Shape = Shape::Struct(&[("bind", &BIND_OPTIONS),
+                                             ("consistency", &ANY),
+                                             ("read_only", &ANY),
+                                             ("source", &ANY),
+                                             ("target", &ANY),
+                                             ("tmpfs", &TMPFS_OPTIONS),
+                                             ("type", &ANY),
+                                             ("volume", &VOLUME_OPTIONS)]);
+
+static BIND_OPTIONS: Shape = Shape::Struct(&[("propagation", &ANY)]);
+
+static TMPFS_OPTIONS: Shape = Shape::Struct(&[("size", &ANY)]);
+
+static VOLUME_OPTIONS: Shape = Shape::Struct(&[("nocopy", &ANY)]);
+
+static NETWORK_INTERFACES: Shape = Shape::MapOf(&NETWORK_INTERFACE);
+
+static NETWORK_INTERFACE: Shape = Shape::Struct(&[("aliases", &ANY)]);
+
+static VOLUMES: Shape = Shape::MapOf(&VOLUME);
+
+static VOLUME: Shape = Shape::Struct(&[("driver", &ANY),
+                                       ("driver_opts", &ANY),
+                                       ("external", &ANY),
+                                       ("labels", &ANY)]);
+
+static NETWORKS: Shape = Shape::MapOf(&NETWORK);
+
+static NETWORK: Shape = Shape::Struct(&[("driver", &ANY),
+                                        ("driver_opts", &ANY),
+                                        ("external", &EXTERNAL_NETWORK),
+                                        ("internal", &ANY),
+                                        ("enable_ipv6", &ANY),
+                                        ("labels", &ANY)]);
+
+static EXTERNAL_NETWORK: Shape = Shape::Struct(&[("name", &ANY)]);
+
+static SECRETS: Shape = Shape::MapOf(&SECRET);
+
+static SECRET: Shape = Shape::Struct(&[("file", &ANY),
+                                       ("external", &ANY),
+                                       ("kind", &ANY),
+                                       ("length", &ANY),
+                                       ("labels", &ANY)]);
+
+static CONFIGS: Shape = Shape::MapOf(&CONFIG);
+
+static CONFIG: Shape = Shape::Struct(&[("content", &ANY),
+                                       ("external", &ANY),
+                                       ("file", &ANY),
+                                       ("labels", &ANY),
+                                       ("name", &ANY)]);
+
+/// A key which we didn't recognize while reading a file leniently.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownField {
+    /// A JSON pointer to the unknown key, such as `/x-custom`.
+    pub path: String,
+    /// The raw YAML value of the unknown key.
+    pub value: Yaml,
+}
+
+/// A `File` which was parsed leniently, plus any fields which we didn't
+/// recognize.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LenientFile {
+    /// Everything in the file which we understood.
+    pub file: File,
+
+    /// Every unknown key we found, in the order we found it.  When
+    /// writing, we'll try to put these back where we found them.
+    pub unknown_fields: Vec<UnknownField>,
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    pub _hidden: (),
+}
+
+impl LenientFile {
+    /// Read a file from an input stream containing YAML, setting aside any
+    /// keys which we don't recognize.  The remaining fields must still be
+    /// valid.
+    pub fn read<R>(mut r: R) -> Result<Self>
+        where R: io::Read
+    {
+        let mut yaml = String::new();
+        r.read_to_string(&mut yaml).chain_err(|| "I/O error")?;
+        let mut doc = load_yaml(&yaml)?;
+        let mut unknown_fields = vec![];
+        strip_unknown_fields(&mut doc, &FILE, "", &mut unknown_fields);
+        let file = serde_yaml::from_str(&dump_yaml(&doc)?)?;
+        // Removing keys doesn't change the JSON pointers of the remaining
+        // values, so we can still use the original source to find errors.
+        validate_file_with_source(&file, Some(&yaml))?;
+        Ok(LenientFile {
+            file: file,
+            unknown_fields: unknown_fields,
+            _hidden: (),
+        })
+    }
+
+    /// Write a file to an output stream as YAML, including any unknown
+    /// fields whose parent still exists.  Unknown fields belonging to
+    /// removed services or other removed structures are discarded.
+    pub fn write<W>(&self, w: &mut W) -> Result<()>
+        where W: io::Write
+    {
+        validate_file(&self.file)?;
+        let mut doc = load_yaml(&serde_yaml::to_string(&self.file)?)?;
+        for field in &self.unknown_fields {
+            insert_at_path(&mut doc, &field.path, field.value.clone());
+        }
+        w.write_all(dump_yaml(&doc)?.as_bytes()).chain_err(|| "I/O error")
+    }
+
+    /// Read a file from the specified path.
+    pub fn read_from_path<P>(path: P) -> Result<Self>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mkerr = || ErrorKind::ReadFile(path.to_owned());
+        let f = fs::File::open(path).chain_err(&mkerr)?;
+        Self::read(io::BufReader::new(f)).chain_err(&mkerr)
+    }
+
+    /// Write a file to the specified path.
+    pub fn write_to_path<P>(&self, path: P) -> Result<()>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mkerr = || ErrorKind::WriteFile(path.to_owned());
+        let f = fs::File::create(path).chain_err(&mkerr)?;
+        self.write(&mut io::BufWriter::new(f)).chain_err(&mkerr)
+    }
+}
+
+impl FromStr for LenientFile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<LenientFile> {
+        Self::read(io::Cursor::new(s))
+    }
+}
+
+/// Parse the first document in `yaml`.
+pub fn load_yaml(yaml: &str) -> Result<Yaml> {
+    let mut docs = YamlLoader::load_from_str(yaml)
+        .map_err(|err| Error::invalid_value("YAML", format!("{}", err)))?;
+    if docs.is_empty() {
+        Ok(Yaml::Null)
+    } else {
+        Ok(docs.swap_remove(0))
+    }
+}
+
+/// Convert `doc` back into YAML source.
+pub fn dump_yaml(doc: &Yaml) -> Result<String> {
+    let mut out = String::new();
+    YamlEmitter::new(&mut out).dump(doc)
+        .map_err(|err| Error::invalid_value("YAML", format!("{}", err)))?;
+    out.push('\n');
+    Ok(out)
+}
+
+/// Remove any keys from `node` which aren't allowed by `shape`, recording
+/// them in `unknown`.  `pointer` is the JSON pointer of `node`.
+fn strip_unknown_fields(node: &mut Yaml,
+                        shape: &Shape,
+                        pointer: &str,
+                        unknown: &mut Vec<UnknownField>) {
+    if let Shape::SeqOf(item_shape) = *shape {
+        if let Yaml::Array(ref mut items) = *node {
+            for (i, item) in items.iter_mut().enumerate() {
+                let child = format!("{}/{}", pointer, i);
+                strip_unknown_fields(item, item_shape, &child, unknown);
+            }
+        }
+        return;
+    }
+    let hash = match *node {
+        Yaml::Hash(ref mut hash) => hash,
+        // Anything else is either an error, which we'll report when we
+        // parse the file, or a value in some shorthand format.
+        _ => return,
+    };
+    match *shape {
+        Shape::Any | Shape::SeqOf(_) => {}
+        Shape::MapOf(value_shape) => {
+            for (key, value) in hash.iter_mut() {
+                if let Yaml::String(ref key) = *key {
+                    let child = format!("{}/{}", pointer, escape_pointer_segment(key));
+                    strip_unknown_fields(value, value_shape, &child, unknown);
+                }
+            }
+        }
+        Shape::Struct(fields) => {
+            let unknown_keys: Vec<String> = hash.keys()
+                .filter_map(|k| k.as_str())
+                .filter(|k| !fields.iter().any(|&(name, _)| name == *k))
+                .map(|k| k.to_owned())
+                .collect();
+            for key in unknown_keys {
+                let value = hash.remove(&Yaml::String(key.clone()))
+                    .expect("unknown key should be present");
+                unknown.push(UnknownField {
+                    path: format!("{}/{}", pointer, escape_pointer_segment(&key)),
+                    value: value,
+                });
+            }
+            for &(name, field_shape) in fields {
+                if let Some(value) = hash.get_mut(&Yaml::String(name.to_owned())) {
+                    let child = format!("{}/{}", pointer, name);
+                    strip_unknown_fields(value, field_shape, &child, unknown);
+                }
+            }
+        }
+    }
+}
+
+/// Insert `value` into `doc` at the JSON pointer `path`.  Returns `false`
+/// if the parent of `path` doesn't exist, or isn't a mapping.  This
+/// happens when a list item we stripped a key from is now written using
+/// its short syntax.
+fn insert_at_path(doc: &mut Yaml, path: &str, value: Yaml) -> bool {
+    let mut segments: Vec<String> = path.split('/')
+        .skip(1)
+        .map(|s| s.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    let last = match segments.pop() {
+        Some(last) => last,
+        None => return false,
+    };
+    let mut node = doc;
+    for segment in segments {
+        let current = node;
+        node = match *current {
+            Yaml::Hash(ref mut hash) => {
+                match hash.get_mut(&Yaml::String(segment)) {
+                    Some(child) => child,
+                    None => return false,
+                }
+            }
+            Yaml::Array(ref mut items) => {
+                match segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                    Some(child) => child,
+                    None => return false,
+                }
+            }
+            _ => return false,
+        };
+    }
+    match *node {
+        Yaml::Hash(ref mut hash) => {
+            hash.insert(Yaml::String(last), value);
+            true
+        }
+        _ => false,
+    }
+}
+
+#[test]
+fn lenient_file_records_and_restores_unknown_fields() {
+    let yaml = r#"---
+version: "2"
+x-custom: "hello"
+services:
+  web:
+    image: "nginx"
+    future_field: true
+    logging:
+      driver: "json-file"
+      future_option: 1
+"#;
+    assert!(File::from_str(yaml).is_err());
+
+    let lenient = LenientFile::from_str(yaml).unwrap();
+    let web = &lenient.file.services["web"];
+    assert_eq!(web.image.as_ref().unwrap().value().unwrap().to_string(), "nginx");
+    let paths: Vec<&str> = lenient.unknown_fields
+        .iter()
+        .map(|f| &f.path[..])
+        .collect();
+    assert_eq!(paths,
+               vec!["/x-custom",
+                    "/services/web/future_field",
+                    "/services/web/logging/future_option"]);
+    assert_eq!(lenient.unknown_fields[1].value, Yaml::Boolean(true));
+
+    let mut out = vec![];
+    lenient.write(&mut out).unwrap();
+    let reread = LenientFile::read(io::Cursor::new(out)).unwrap();
+    assert_eq!(reread, lenient);
+}
+
+#[test]
+fn lenient_file_strips_unknown_fields_from_list_items() {
+    let yaml = r#"---
+version: "3.8"
+services:
+  web:
+    image: "nginx"
+    ports:
+      - "443:443"
+      - target: 80
+        published: 8080
+        mode: "host"
+        app_protocol: "http"
+"#;
+    assert!(File::from_str(yaml).is_err());
+
+    let lenient = LenientFile::from_str(yaml).unwrap();
+    assert_eq!(lenient.unknown_fields.len(), 1);
+    assert_eq!(lenient.unknown_fields[0].path,
+               "/services/web/ports/1/app_protocol");
+    assert_eq!(lenient.unknown_fields[0].value,
+               Yaml::String("http".to_owned()));
+
+    let mut out = vec![];
+    lenient.write(&mut out).unwrap();
+    let reread = LenientFile::read(io::Cursor::new(out)).unwrap();
+    assert_eq!(reread, lenient);
+}
+
+#[test]
+fn shape_tables_match_serialized_fields() {
+    use std::hash::Hash;
+    use super::{Build, CommandLine, Config, Context, Dependency, Deploy, Extends,
+                ExternalNetwork, Healthcheck, HostVolume, Logging, Map, MountType,
+                Network, NetworkInterface, PortMapping, Ports, RawOr, ResourceSpec,
+                Resources, SconeConfig, Secret, Service, ServiceConfig, ServiceSecret,
+                Volume, VolumeMount, VolumePermissions, raw, value};
+    use super::interpolation::InterpolatableValue;
+
+    /// Check that every struct in `node` has exactly the fields listed in
+    /// `shape`.
+    fn check_shape(shape: &Shape, node: &Yaml, pointer: &str) {
+        if let Shape::SeqOf(item_shape) = *shape {
+            if let Yaml::Array(ref items) = *node {
+                for (i, item) in items.iter().enumerate() {
+                    check_shape(item_shape, item, &format!("{}/{}", pointer, i));
+                }
+            }
+            return;
+        }
+        let hash = match *node {
+            Yaml::Hash(ref hash) => hash,
+            _ => return,
+        };
+        match *shape {
+            Shape::Any | Shape::SeqOf(_) => {}
+            Shape::MapOf(value_shape) => {
+                for (key, value) in hash {
+                    let child = format!("{}/{}", pointer, key.as_str().unwrap());
+                    check_shape(value_shape, value, &child);
+                }
+
+            }
+            Shape::Struct(fields) => {
+                let mut expected: Vec<&str> = fields.iter().map(|&(n, _)| n).collect();
+                let mut actual: Vec<&str> =
+                    hash.keys().filter_map(|k| k.as_str()).collect();
+                expected.sort();
+                actual.sort();
+                assert_eq!(actual, expected, "fields of {}", pointer);
+                for &(name, field_shape) in fields {
+                    let value = &hash[&Yaml::String(name.to_owned())];
+                    check_shape(field_shape, value, &format!("{}/{}", pointer, name));
+                }
+            }
+        }
+    }
+
+    fn v<T: InterpolatableValue>(s: &str) -> RawOr<T> {
+        raw(s).unwrap()
+    }
+
+    fn map<K: Ord + Hash, T>(key: K, value: T) -> Map<K, T> {
+        let mut map = Map::new();
+        map.insert(key, value);
+        map
+    }
+
+    // We use struct literals without `..Default::default()`, so that
+    // adding a field to any of these structs breaks this test until we
+    // look at the corresponding table.
+    let labels = map("com.example.label".to_owned(), v("foo"));
+    let spec = ResourceSpec {
+        cpus: Some(v("0.5")),
+        memory: Some(v("512m")),
+        _hidden: (),
+    };
+    let service = Service {
+        build: Some(Build {
+            context: value(Context::new(".")),
+            dockerfile: Some(v("Dockerfile")),
+            args: map("ARG".to_owned(), v("1")),
+            _hidden: (),
+        }),
+        cap_add: vec![v("NET_ADMIN")],
+        cap_drop: vec![v("CHOWN")],
+        command: Some(CommandLine::ShellCode(v("true"))),
+        cgroup_parent: Some(v("parent")),
+        configs: vec![value(ServiceConfig {
+                               source: "config".to_owned(),
+                               target: Some("/etc/nginx.conf".to_owned()),
+                               uid: Some("0".to_owned()),
+                               gid: Some("0".to_owned()),
+                               mode: Some(0o440),
+                               _hidden: (),
+                           })],
+        container_name: Some(v("web")),
+        devices: vec![v("/dev/null")],
+        depends_on: map(v("db"),
+                        Dependency {
+                            condition: Some(v("service_healthy")),
+                            _hidden: (),
+                        }),
+        deploy: Some(Deploy {
+            mode: Some(v("replicated")),
+            replicas: Some(2),
+            labels: labels.clone(),
+            resources: Some(Resources {
+                limits: Some(spec.clone()),
+                reservations: Some(spec),
+                _hidden: (),
+            }),
+            _hidden: (),
+        }),
+        dns: vec![v("8.8.8.8")],
+        dns_search: vec![v("example.com")],
+        tmpfs: vec![v("/tmp")],
+        entrypoint: Some(CommandLine::ShellCode(v("/init"))),
+        env_files: vec![v("web.env")],
+        environment: map("FOO".to_owned(), v("bar")),
+        expose: vec![v("80")],
+        extends: Some(Extends {
+            service: v("base"),
+            file: Some(v("base.yml")),
+            _hidden: (),
+        }),
+        external_links: vec![v("redis")],
+        extra_hosts: vec![v("host:127.0.0.1")],
+        healthcheck: Some(Healthcheck {
+            test: Some(CommandLine::ShellCode(v("true"))),
+            interval: Some(v("30s")),
+            timeout: Some(v("5s")),
+            retries: Some(3),
+            start_period: Some(v("10s")),
+            disable: true,
+            _hidden: (),
+        }),
+        image: Some(v("nginx")),
+        init: Some(true),
+        isolation: Some(v("default")),
+        labels: labels.clone(),
+        links: vec![v("db")],
+        logging: Some(Logging {
+            driver: Some(v("json-file")),
+            options: map("max-size".to_owned(), v("1m")),
+            _hidden: (),
+        }),
+        network_mode: Some(v("bridge")),
+        networks: map("front".to_owned(),
+                      NetworkInterface {
+                          aliases: vec![v("www")],
+                          _hidden: (),
+                      }),
+        pid: Some(v("host")),
+        ports: vec![value(PortMapping {
+                             host_address: Some("127.0.0.1".parse().unwrap()),
+                             host_ports: Some(Ports::Port(8080)),
+                             container_ports: Ports::Port(80),
+                             protocol: Some("tcp".to_owned()),
+                             mode: Some("host".to_owned()),
+                             _hidden: (),
+                         })],
+        profiles: vec![v("debug")],
+        secrets: vec![value(ServiceSecret {
+                               source: "secret".to_owned(),
+                               target: Some("password".to_owned()),
+                               uid: Some("0".to_owned()),
+                               gid: Some("0".to_owned()),
+                               mode: Some(0o400),
+                               _hidden: (),
+                           })],
+        security_opt: vec![v("no-new-privileges")],
+        stop_signal: Some(v("SIGTERM")),
+        // Real mounts only use one of `bind`, `volume` and `tmpfs`, but
+        // we set them all here so that they all get checked.
+        volumes: vec![value(VolumeMount {
+                               host: Some(HostVolume::Path("./data".into())),
+                               container: "/data".to_owned(),
+                               permissions: VolumePermissions::ReadOnly,
+                               mount_type: Some(MountType::Bind),
+                               consistency: Some("cached".to_owned()),
+                               nocopy: true,
+                               propagation: Some("rprivate".to_owned()),
+                               tmpfs_size: Some(1024),
+                               _hidden: (),
+                           })],
+        volumes_from: vec![v("data")],
+        volume_driver: Some(v("local")),
+        cpu_shares: Some(512),
+        cpu_quota: Some(50000),
+        domainname: Some(v("example.com")),
+        hostname: Some(v("web")),
+        ipc: Some(v("host")),
+        mac_address: Some(v("02:42:ac:11:65:43")),
+        mem_limit: Some(v("512m")),
+        memswap_limit: Some(v("1g")),
+        privileged: true,
+        restart: Some(v("always")),
+        shm_size: Some(v("64m")),
+        stdin_open: true,
+        tty: true,
+        user: Some(v("nobody")),
+        working_dir: Some(v("/app")),
+        oom_score_adj: Some(100),
+        group_add: vec!["staff".to_owned()],
+        scone: SconeConfig {
+            mrenclave: Some(v("0123456789abcdef0123456789abcdef\
+                               0123456789abcdef0123456789abcdef")),
+            fspf_path: Some(v("/fspf.pb")),
+            fspf_key: Some(v("0123456789abcdef0123456789abcdef\
+                              0123456789abcdef0123456789abcdef")),
+            fspf_tag: Some(v("0123456789abcdef0123456789abcdef")),
+            not_scone: Some(v("false")),
+            _hidden: (),
+        },
+        _hidden: (),
+    };
+    let file = File {
+        version: Some("3.8".parse().unwrap()),
+        name: Some(v("app")),
+        services: map("web".to_owned(), service),
+        volumes: map("data".to_owned(),
+                     Volume {
+                         driver: Some(v("local")),
+                         driver_opts: map("type".to_owned(), v("tmpfs")),
+                         external: Some(false),
+                         labels: labels.clone(),
+                         _hidden: (),
+                     }),
+        networks: map("front".to_owned(),
+                      Network {
+                          driver: Some(v("bridge")),
+                          driver_opts: map("opt".to_owned(), v("1")),
+                          external: Some(ExternalNetwork {
+                              name: Some(v("front_net")),
+                              _hidden: (),
+                          }),
+                          internal: true,
+                          enable_ipv6: true,
+                          labels: labels.clone(),
+                          _hidden: (),
+                      }),
+        secrets: map("secret".to_owned(),
+                     Secret {
+                         file: Some("secret.txt".to_owned()),
+                         external: Some(false),
+                         kind: Some(v("hex")),
+                         length: Some(v("16")),
+                         labels: labels.clone(),
+                         // Never serialized.
+                         content: None,
+                         _hidden: (),
+                     }),
+        configs: map("config".to_owned(),
+                     Config {
+                         content: Some("listen 80;".to_owned()),
+                         external: Some(false),
+                         file: Some(v("nginx.conf")),
+                         labels: labels,
+                         name: Some(v("config")),
+                         _hidden: (),
+                     }),
+        _hidden: (),
+    };
+    let doc = load_yaml(&serde_yaml::to_string(&file).unwrap()).unwrap();
+    check_shape(&FILE, &doc, "");
+}
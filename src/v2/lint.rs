@@ -0,0 +1,460 @@
+//! A pluggable engine for checking `docker-compose.yml` files against
+//! local conventions.
+//!
+//! Unlike schema validation, lint rules don't check whether a file is
+//! _legal_, but whether it follows the house style of a particular team:
+//! whether images are pinned, whether services restart automatically, and
+//! so on.  Each rule is a `LintRule` trait object with a unique ID, and
+//! individual services can opt out of rules using a label:
+//!
+//! ```yaml
+//! services:
+//!   debug:
+//!     image: "busybox"
+//!     labels:
+//!       compose_yml.lint.ignore: "no-latest-tag,require-restart"
+//! ```
+
+use std::fmt;
+
+use super::{File, HostVolume, Service};
+use super::paths::normalize;
+use super::validate::escape_pointer_segment;
+
+/// The label used to suppress lint rules for a single service.  Its value
+/// should be a comma-separated list of rule IDs, or `all`.
+pub const LINT_IGNORE_LABEL: &'static str = "compose_yml.lint.ignore";
+
+/// How serious is a lint finding?
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Worth knowing about, but probably fine.
+    Info,
+    /// Probably a mistake.
+    Warning,
+    /// Definitely a mistake.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found by a `LintRule`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    /// The ID of the rule which found this problem.
+    pub rule_id: String,
+    /// How serious is this problem?
+    pub severity: Severity,
+    /// The service in which we found this problem, if any.
+    pub service: Option<String>,
+    /// A JSON pointer to the offending field, such as
+    /// `/services/web/image`.
+    pub path: String,
+    /// A human-readable description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}] {}: {}",
+               self.severity, &self.rule_id, &self.path, &self.message)
+    }
+}
+
+/// Collects findings on behalf of a `LintRule`.
+#[derive(Debug)]
+pub struct LintContext<'a> {
+    /// The rule we're currently running.
+    rule: &'a LintRule,
+    /// The service we're currently checking, if any.
+    service: Option<&'a str>,
+    /// Where to put our findings.
+    findings: &'a mut Vec<LintFinding>,
+}
+
+impl<'a> LintContext<'a> {
+    /// The JSON pointer to the current service, or to the root of the file
+    /// if we're not checking a service.
+    pub fn base_path(&self) -> String {
+        match self.service {
+            Some(name) => format!("/services/{}", escape_pointer_segment(name)),
+            None => "".to_owned(),
+        }
+    }
+
+    /// Build a JSON pointer to `field` (which may itself contain several
+    /// `/`-separated segments) inside the current service.
+    pub fn path(&self, field: &str) -> String {
+        format!("{}/{}", self.base_path(), field)
+    }
+
+    /// Report a problem at the JSON pointer `path`.
+    pub fn report<P, M>(&mut self, path: P, message: M)
+        where P: Into<String>,
+              M: Into<String>
+    {
+        self.findings.push(LintFinding {
+            rule_id: self.rule.id().to_owned(),
+            severity: self.rule.severity(),
+            service: self.service.map(|s| s.to_owned()),
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+}
+
+/// A single lint rule.  Rules may check the file as a whole, individual
+/// services, or both.
+pub trait LintRule: fmt::Debug + Send + Sync {
+    /// A unique, stable, kebab-case identifier for this rule, which can be
+    /// used to suppress it.
+    fn id(&self) -> &str;
+
+    /// How serious are the problems reported by this rule?
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Check the file as a whole.  The default implementation does nothing.
+    fn check_file(&self, _file: &File, _ctx: &mut LintContext) {}
+
+    /// Check a single service.  This will not be called for services which
+    /// suppress this rule using `LINT_IGNORE_LABEL`.  The default
+    /// implementation does nothing.
+    fn check_service(&self, _name: &str, _service: &Service, _ctx: &mut LintContext) {}
+}
+
+/// Does `service` ask us to suppress the rule `id`?
+fn is_suppressed(service: &Service, id: &str) -> bool {
+    match service.labels.get(LINT_IGNORE_LABEL).and_then(|v| v.value().ok()) {
+        Some(ignored) => {
+            ignored.split(',').map(|s| s.trim()).any(|s| s == id || s == "all")
+        }
+        None => false,
+    }
+}
+
+/// A collection of lint rules which can be run against a `File`.  The
+/// default `Linter` has no rules; see `Linter::with_builtin_rules`.
+#[derive(Debug, Default)]
+pub struct Linter {
+    /// The rules we'll run.
+    rules: Vec<Box<LintRule>>,
+}
+
+impl Linter {
+    /// Create a new `Linter` with no rules.
+    pub fn new() -> Linter {
+        Default::default()
+    }
+
+    /// Create a new `Linter` with all of our built-in rules.
+    pub fn with_builtin_rules() -> Linter {
+        let mut linter = Linter::new();
+        linter.add_rule(NoLatestTag);
+        linter.add_rule(RequireRestart);
+        linter.add_rule(NoPrivileged);
+        linter.add_rule(RequireMemLimit);
+        linter.add_rule(NoHostPathsOutsideProject);
+        linter
+    }
+
+    /// Add a rule to this linter.
+    pub fn add_rule<R: LintRule + 'static>(&mut self, rule: R) {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// Remove the rule with the specified ID, if present.
+    pub fn remove_rule(&mut self, id: &str) {
+        self.rules.retain(|r| r.id() != id);
+    }
+
+    /// Run all our rules against `file`, and return any problems found.
+    pub fn lint(&self, file: &File) -> Vec<LintFinding> {
+        let mut findings = vec![];
+        for rule in &self.rules {
+            {
+                let mut ctx = LintContext {
+                    rule: &**rule,
+                    service: None,
+                    findings: &mut findings,
+                };
+                rule.check_file(file, &mut ctx);
+            }
+            for (name, service) in &file.services {
+                if is_suppressed(service, rule.id()) {
+                    continue;
+                }
+                let mut ctx = LintContext {
+                    rule: &**rule,
+                    service: Some(&name[..]),
+                    findings: &mut findings,
+                };
+                rule.check_service(name, service, &mut ctx);
+            }
+        }
+        findings
+    }
+}
+
+/// Images should be pinned to a specific tag, not `latest`, or to a
+/// digest.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoLatestTag;
+
+impl LintRule for NoLatestTag {
+    fn id(&self) -> &str {
+        "no-latest-tag"
+    }
+
+    fn check_service(&self, _name: &str, service: &Service, ctx: &mut LintContext) {
+        // We can't say anything about images which require interpolation.
+        if let Some(image) = service.image.as_ref().and_then(|i| i.value().ok()) {
+            // A digest pins the image more precisely than any tag.
+            if image.digest.is_some() {
+                return;
+            }
+            match image.tag.as_ref().map(|t| &t[..]) {
+                None => {
+                    let path = ctx.path("image");
+                    ctx.report(path, format!("image {} has no tag", image));
+                }
+                Some("latest") => {
+                    let path = ctx.path("image");
+                    ctx.report(path, format!("image {} uses the latest tag", image));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+/// Images should be pinned to an exact digest, as `File::pin_images`
+/// does.  This is stricter than most teams want during development, so
+/// it isn't one of the built-in rules.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequirePinnedImages;
+
+impl LintRule for RequirePinnedImages {
+    fn id(&self) -> &str {
+        "require-pinned-images"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check_service(&self, _name: &str, service: &Service, ctx: &mut LintContext) {
+        // Services which we build are named by `image`, not pulled.
+        if service.build.is_some() {
+            return;
+        }
+        if let Some(ref image) = service.image {
+            let pinned = image.value().map(|i| i.digest.is_some()).unwrap_or(false);
+            if !pinned {
+                let path = ctx.path("image");
+                ctx.report(path, format!("image {} is not pinned to a digest", image));
+            }
+        }
+    }
+}
+
+/// Every service should specify a `restart` policy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequireRestart;
+
+impl LintRule for RequireRestart {
+    fn id(&self) -> &str {
+        "require-restart"
+    }
+
+    fn check_service(&self, _name: &str, service: &Service, ctx: &mut LintContext) {
+        if service.restart.is_none() {
+            let path = ctx.path("restart");
+            ctx.report(path, "service has no restart policy");
+        }
+    }
+}
+
+/// Services should not run in privileged mode.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoPrivileged;
+
+impl LintRule for NoPrivileged {
+    fn id(&self) -> &str {
+        "no-privileged"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check_service(&self, _name: &str, service: &Service, ctx: &mut LintContext) {
+        if service.privileged {
+            let path = ctx.path("privileged");
+            ctx.report(path, "service runs in privileged mode");
+        }
+    }
+}
+
+/// Every service should have a memory limit, either as `mem_limit` or, in
+/// version 3 files, as `deploy.resources.limits.memory`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequireMemLimit;
+
+impl LintRule for RequireMemLimit {
+    fn id(&self) -> &str {
+        "require-mem-limit"
+    }
+
+    fn check_service(&self, _name: &str, service: &Service, ctx: &mut LintContext) {
+        let deploy_limit = service.deploy
+            .as_ref()
+            .and_then(|d| d.resources.as_ref())
+            .and_then(|r| r.limits.as_ref())
+            .and_then(|l| l.memory.as_ref());
+        if service.mem_limit.is_none() && deploy_limit.is_none() {
+            let path = ctx.path("mem_limit");
+            ctx.report(path, "service has no memory limit");
+        }
+    }
+}
+
+/// Services should only mount host paths inside the project directory,
+/// which means relative paths that don't climb out of it using `..`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoHostPathsOutsideProject;
+
+impl LintRule for NoHostPathsOutsideProject {
+    fn id(&self) -> &str {
+        "no-host-paths-outside-project"
+    }
+
+    fn check_service(&self, _name: &str, service: &Service, ctx: &mut LintContext) {
+        for (i, volume) in service.volumes.iter().enumerate() {
+            let mount = match volume.value() {
+                Ok(mount) => mount,
+                Err(_) => continue,
+            };
+            let outside = match mount.host {
+                Some(HostVolume::Path(ref path)) => {
+                    path.is_absolute() || normalize(path).starts_with("..")
+                }
+                Some(HostVolume::UserRelativePath(_)) => true,
+                Some(HostVolume::Name(_)) | None => false,
+            };
+            if outside {
+                let path = ctx.path(&format!("volumes/{}", i));
+                ctx.report(path,
+                           format!("volume {} mounts a host path outside the project",
+                                   mount));
+            }
+        }
+    }
+}
+
+#[test]
+fn builtin_rules_report_problems_with_paths() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "2"
+services:
+  web:
+    image: "nginx:latest"
+    privileged: true
+    restart: "always"
+    mem_limit: "512m"
+    volumes:
+      - "/etc:/host-etc"
+      - "./src:/app"
+"#;
+    let file = File::from_str(yaml).unwrap();
+    let findings = Linter::with_builtin_rules().lint(&file);
+    let mut found: Vec<(&str, &str)> = findings.iter()
+        .map(|f| (&f.rule_id[..], &f.path[..]))
+        .collect();
+    found.sort();
+    assert_eq!(found,
+               vec![("no-host-paths-outside-project", "/services/web/volumes/0"),
+                    ("no-latest-tag", "/services/web/image"),
+                    ("no-privileged", "/services/web/privileged")]);
+    assert!(findings.iter().all(|f| f.service == Some("web".to_owned())));
+}
+
+#[test]
+fn no_host_paths_outside_project_normalizes_paths() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "3.2"
+services:
+  web:
+    image: "nginx"
+    volumes:
+      - "./../shared:/shared"
+      - type: "bind"
+        source: "src/../../etc"
+        target: "/host-etc"
+      - "./src/../lib:/lib"
+      - "../project/src:/src"
+"#;
+    let file = File::from_str(yaml).unwrap();
+    let mut linter = Linter::new();
+    linter.add_rule(NoHostPathsOutsideProject);
+    let paths: Vec<String> = linter.lint(&file).into_iter().map(|f| f.path).collect();
+    assert_eq!(paths,
+               vec!["/services/web/volumes/0",
+                    "/services/web/volumes/1",
+                    "/services/web/volumes/3"]);
+}
+
+#[test]
+fn rules_can_be_suppressed_with_a_label() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "2"
+services:
+  debug:
+    image: "busybox"
+    labels:
+      compose_yml.lint.ignore: "no-latest-tag, require-restart"
+"#;
+    let file = File::from_str(yaml).unwrap();
+    let findings = Linter::with_builtin_rules().lint(&file);
+    let ids: Vec<&str> = findings.iter().map(|f| &f.rule_id[..]).collect();
+    assert_eq!(ids, vec!["require-mem-limit"]);
+}
+
+#[test]
+fn require_pinned_images_reports_unpinned_images() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "2"
+services:
+  app:
+    build: "."
+    image: "example/app"
+  cache:
+    image: "redis@sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+  web:
+    image: "nginx:1.15"
+"#;
+    let file = File::from_str(yaml).unwrap();
+    let mut linter = Linter::new();
+    linter.add_rule(RequirePinnedImages);
+    let findings = linter.lint(&file);
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].path, "/services/web/image");
+    assert_eq!(findings[0].severity, Severity::Error);
+}
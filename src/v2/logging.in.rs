@@ -11,8 +11,8 @@ pub struct Logging {
     pub driver: Option<RawOr<String>>,
 
     /// Options to pass to the log driver.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
-    pub options: BTreeMap<String, RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub options: Map<String, RawOr<String>>,
 
     /// PRIVATE.  Mark this struct as having unknown fields for future
     /// compatibility.  This prevents direct construction and exhaustive
@@ -0,0 +1,69 @@
+//! The map type used for services, labels, environment variables and other
+//! keyed collections.
+//!
+//! By default, we use a `BTreeMap`, which means that keys are always
+//! written back out in alphabetical order.  If the `preserve_order` cargo
+//! feature is enabled, we use a `LinkedHashMap` instead, which remembers
+//! the order in which keys were declared.
+
+#[cfg(feature = "preserve_order")]
+use linked_hash_map::LinkedHashMap as MapImpl;
+#[cfg(not(feature = "preserve_order"))]
+use std::collections::BTreeMap as MapImpl;
+
+/// An ordered map.  This is a `BTreeMap` sorted by key, unless the
+/// `preserve_order` feature is enabled, in which case it's a
+/// `LinkedHashMap` which keeps keys in insertion order.
+///
+/// Code which needs to work with either representation should stick to
+/// the methods the two types have in common, such as `get`, `get_mut`,
+/// `insert`, `remove`, `iter` and `iter_mut`.
+pub type Map<K, V> = MapImpl<K, V>;
+
+#[test]
+#[cfg(feature = "preserve_order")]
+fn file_preserves_declaration_order() {
+    use std::str::FromStr;
+    use super::{File, normalize_yaml};
+
+    let yaml = r#"---
+"version": "2"
+"services":
+  "web":
+    "image": "nginx"
+    "environment":
+      "ZEBRA": "1"
+      "APPLE": "2"
+  "db":
+    "image": "postgres"
+"#;
+    let file = File::from_str(yaml).unwrap();
+    let names: Vec<&str> = file.services.keys().map(|k| &k[..]).collect();
+    assert_eq!(names, vec!["web", "db"]);
+
+    let mut output = vec![];
+    file.write(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(normalize_yaml(&output), normalize_yaml(yaml));
+    assert!(output.find("ZEBRA").unwrap() < output.find("APPLE").unwrap());
+}
+
+#[test]
+#[cfg(feature = "preserve_order")]
+fn interpolated_keys_keep_their_order() {
+    use serde_yaml;
+    use std::env;
+    use super::{InterpolateAll, Service, value};
+
+    env::set_var("COMPOSE_YML_FIRST_DEPENDENCY", "db");
+    let yaml = r#"---
+"depends_on":
+  - "$COMPOSE_YML_FIRST_DEPENDENCY"
+  - "cache"
+"image": "example/web"
+"#;
+    let mut service: Service = serde_yaml::from_str(yaml).unwrap();
+    service.interpolate_all().unwrap();
+    assert_eq!(service.depends_on.keys().collect::<Vec<_>>(),
+               vec![&value("db".to_owned()), &value("cache".to_owned())]);
+}
@@ -1,7 +1,11 @@
 //! Merging two `docker-compose.yml` files together.
 
+#[cfg(feature = "preserve_order")]
+use linked_hash_map::LinkedHashMap;
 use std::collections::BTreeMap;
 use std::collections::btree_map;
+#[cfg(feature = "preserve_order")]
+use std::hash::Hash;
 use std::marker::Sized;
 
 /// This trait is implemented by types that can be merged
@@ -115,6 +119,46 @@ fn btree_map_merges_by_key() {
     assert_merge!(BTreeMap<&'static str, Vec<bool>>, map1, map2, expected);
 }
 
+#[cfg(feature = "preserve_order")]
+impl<K: Hash + Eq + Clone, T: MergeOverride> MergeOverride for LinkedHashMap<K, T> {
+    /// Merges `ovr` into `self`, recursively merging values if present in
+    /// both, and returns a new `LinkedHashMap`.  Keys which are already
+    /// present keep their original position, and new keys are added at the
+    /// end.
+    fn merge_override(&self, ovr: &Self) -> Self {
+        let mut result = self.clone();
+        for (ovr_key, ovr_val) in ovr {
+            let merged = result.get(ovr_key).map(|val| val.merge_override(ovr_val));
+            match merged {
+                Some(merged) => {
+                    if let Some(val) = result.get_mut(ovr_key) {
+                        *val = merged;
+                    }
+                }
+                None => {
+                    result.insert(ovr_key.to_owned(), ovr_val.clone());
+                }
+            }
+        }
+        result
+    }
+}
+
+#[test]
+#[cfg(feature = "preserve_order")]
+fn linked_hash_map_merges_by_key_and_keeps_order() {
+    let mut map1 = LinkedHashMap::new();
+    map1.insert("c", vec!(false));
+    map1.insert("a", vec!(false));
+    let mut map2 = LinkedHashMap::new();
+    map2.insert("b", vec!(true));
+    map2.insert("c", vec!(true));
+    let merged = map1.merge_override(&map2);
+    let keys: Vec<&str> = merged.keys().cloned().collect();
+    assert_eq!(keys, vec!["c", "a", "b"]);
+    assert_eq!(merged[&"c"], vec!(false, true));
+}
+
 /// Derive `MergeOverride` for a custom struct type, by recursively merging
 /// all fields.
 macro_rules! derive_merge_override_for {
@@ -8,6 +8,7 @@ use serde::de::{self, Deserialize, Deserializer, SeqVisitor, Visitor};
 use serde::ser::{Serialize, Serializer};
 use serde_yaml;
 use std::borrow::ToOwned;
+use std::cmp;
 use std::collections::BTreeMap;
 use std::convert::Into;
 use std::default::Default;
@@ -20,36 +21,80 @@ use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 use std::result;
 use std::str::FromStr;
+use std::time::Duration;
 use void::Void;
 
+pub use self::audit::{AuditFinding, AuditReport, RiskLevel, RiskySetting};
+use self::bundle::{export_bundle, import_bundle};
+use self::convert::convert_file;
+pub use self::convert::{ConversionChange, ConversionChangeKind, ConversionReport};
+pub use self::docker_run::DockerRunCommand;
+pub use self::document::Document;
+pub use self::engine_api::{ContainerCreate, NetworkCreate, VolumeCreate};
 use self::helpers::*;
 use self::env_file::EnvFile;
 pub use self::git_url::GitUrl;
+pub use self::lenient::{LenientFile, UnknownField};
 pub use self::interpolation::{RawOr, raw, escape, value, InterpolateAll, Environment,
                               OsEnvironment};
+pub use self::kubernetes::KubernetesManifests;
+pub use self::lint::{LINT_IGNORE_LABEL, LintContext, LintFinding, LintRule, Linter,
+                     NoHostPathsOutsideProject, NoLatestTag, NoPrivileged,
+                     RequireMemLimit, RequirePinnedImages, RequireRestart,
+                     Severity};
+pub use self::map::Map;
 pub use self::merge_override::MergeOverride;
 pub use self::mode_enum::*;
+use self::paths::{absolutize_paths, relativize_paths};
+use self::pin::{lock_key, pin_images};
+pub use self::pin::ImageResolver;
+pub use self::project::Project;
+pub use self::scone::{FspfKey, FspfTag, MrEnclave, SconeConfig};
+pub use self::scone_check::{SconeFinding, SconeProblem, SconeReport};
+pub use self::secret_store::LocalSecretStore;
 use self::string_or_struct::*;
 use self::true_or_struct::*;
-use self::validate::validate_file;
+use self::validate::{validate_file, validate_file_with_source};
+pub use self::validate::{SchemaViolation, SourceLocation, Validator};
+pub use self::systemd::SystemdUnit;
+pub use self::version::{Version, VersionRequirement};
 
 // Re-export errors here so that people can use them by including `use
 // compose_yml::v2`.
 pub use errors::*;
 
+mod audit;
+mod bundle;
+mod convert;
+mod docker_run;
+mod document;
+mod engine_api;
 mod helpers;
 mod env_file;
 mod git_url;
 #[macro_use]
 mod interpolation;
+mod json_object;
+mod kubernetes;
+mod lenient;
+mod lint;
+mod map;
 mod string_or_struct;
+mod systemd;
 mod true_or_struct;
 #[macro_use]
 mod merge_override;
 mod mode_enum;
+mod paths;
+mod pin;
+mod project;
+mod scone;
+mod scone_check;
+mod secret_store;
 #[macro_use]
 mod derive;
 mod validate;
+mod version;
 
 macro_rules! assert_roundtrip {
     ( $ty:ty, $yaml:expr ) => {
@@ -98,11 +143,15 @@ serde_include!("service");
 serde_include!("volume");
 serde_include!("network");
 serde_include!("secret");
+serde_include!("config");
 
 // Service-related types.
 serde_include!("build");
 serde_include!("context");
+serde_include!("dependency");
+serde_include!("deploy");
 serde_include!("extends");
+serde_include!("healthcheck");
 serde_include!("logging");
 serde_include!("network_interface");
 serde_include!("port_mapping");
@@ -111,3 +160,6 @@ serde_include!("volumes_from");
 
 // Network-related types.
 serde_include!("external_network");
+
+// Lock files.
+serde_include!("image_lock");
@@ -190,6 +190,72 @@ mode_enum! {
     }
 }
 
+mode_enum! {
+    /// What state should a service listed in `depends_on` be in before we
+    /// start the service which depends on it?
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum DependencyCondition {
+        /// Wait until the container has been started.
+        ("service_started") => ServiceStarted,
+        /// Wait until the container's health check passes.
+        ("service_healthy") => ServiceHealthy,
+        /// Wait until the container has run to completion and exited
+        /// successfully.  Only supported by the Compose Spec.
+        ("service_completed_successfully") => ServiceCompletedSuccessfully
+    ;
+    }
+}
+
+mode_enum! {
+    /// What kind of storage should be mounted into a container?  This is
+    /// only specified when using the long volume syntax.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum MountType {
+        /// A named or anonymous volume managed by Docker.
+        ("volume") => Volume,
+        /// A file or directory on the host.
+        ("bind") => Bind,
+        /// A temporary in-memory filesystem.
+        ("tmpfs") => Tmpfs,
+        /// A Windows named pipe.
+        ("npipe") => NamedPipe
+    ;
+    }
+}
+
+mode_enum! {
+    /// What sort of value should SCONE generate for a secret?
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum SecretKind {
+        /// Printable ASCII characters, excluding spaces.
+        ("ascii") => Ascii,
+        /// Arbitrary bytes.
+        ("binary") => Binary,
+        /// Lowercase hexadecimal digits.
+        ("hex") => Hex
+    ;
+    }
+}
+
+impl SecretKind {
+    /// The smallest and largest `length` allowed for this kind of secret,
+    /// inclusive.  Lengths count characters for `ascii` and `hex`
+    /// secrets, and bytes for `binary` secrets.
+    pub fn length_bounds(&self) -> (u32, u32) {
+        match *self {
+            SecretKind::Ascii | SecretKind::Hex => (1, 4096),
+            SecretKind::Binary => (1, 65536),
+        }
+    }
+}
+
+#[test]
+fn secret_kind_rejects_typos() {
+    assert_eq!(SecretKind::from_str("ascii").unwrap(), SecretKind::Ascii);
+    assert_eq!(SecretKind::Hex.to_string(), "hex");
+    assert!(SecretKind::from_str("asci").is_err());
+}
+
 /// What should Docker do when the container stops running?
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[allow(missing_copy_implementations)]
@@ -11,8 +11,8 @@ pub struct Network {
     pub driver: Option<RawOr<String>>,
 
     /// Options to pass to the network driver.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
-    pub driver_opts: BTreeMap<String, RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub driver_opts: Map<String, RawOr<String>>,
 
     /// Mutually-exclusive with all other options.
     ///
@@ -38,9 +38,9 @@ pub struct Network {
 
     /// Docker labels for this volume, specifying various sorts of
     /// custom metadata.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
+    #[serde(default, skip_serializing_if = "Map::is_empty",
             deserialize_with = "deserialize_map_or_key_value_list")]
-    pub labels: BTreeMap<String, RawOr<String>>,
+    pub labels: Map<String, RawOr<String>>,
 
     // TODO LOW: ipam
 
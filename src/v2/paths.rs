@@ -0,0 +1,262 @@
+//! Resolving the relative paths in a file against the file's directory,
+//! and turning them back into relative paths, so that files can be moved
+//! between directories without breaking.
+//!
+//! `docker-compose` resolves relative paths against the directory
+//! containing the file.  The path-bearing fields are:
+//!
+//! - host paths in `volumes`, including `~/` paths
+//! - `build` contexts which are local directories
+//! - `env_file` entries
+//! - `extends` files
+//! - top-level `secrets` and `configs` files
+//!
+//! A `build` section's `dockerfile` is relative to its context, not to the
+//! file, so we leave it alone.
+
+use std::path::{Component, Path, PathBuf};
+
+use errors::*;
+use super::{Context, File, HostVolume};
+
+/// Replace every relative path in `file` with an absolute path, resolved
+/// against `base`.  If `home` is supplied, `~/` paths in `volumes` are
+/// expanded to absolute paths under `home`.  All path-bearing fields must
+/// already be interpolated.
+pub fn absolutize_paths(file: &mut File,
+                        base: &Path,
+                        home: Option<&Path>)
+                        -> Result<()> {
+    let absolutize = |path: &Path| normalize(&base.join(path));
+    rewrite_paths(file, &absolutize, home)
+}
+
+/// Replace every absolute path in `file` which lies under `root` with a
+/// path relative to `base`, using `..` if needed.  `root` defaults to
+/// `base`.  Absolute paths outside `root`, such as `/etc/ssl`, refer to
+/// the host and are left alone, as are `~/` paths.  All path-bearing
+/// fields must already be interpolated.
+pub fn relativize_paths(file: &mut File,
+                        base: &Path,
+                        root: Option<&Path>)
+                        -> Result<()> {
+    let base = normalize(base);
+    let root = root.map_or_else(|| base.clone(), normalize);
+    let relativize = |path: &Path| {
+        if path.is_absolute() {
+            let normalized = normalize(path);
+            if normalized.starts_with(&root) {
+                if let Some(relative) = relative_to(&normalized, &base) {
+                    return relative;
+                }
+            }
+        }
+        path.to_owned()
+    };
+    rewrite_paths(file, &relativize, None)
+}
+
+/// Apply `f` to every path in `file`, expanding `~/` paths in `volumes`
+/// under `home` if it's supplied.
+pub fn rewrite_paths<F>(file: &mut File, f: &F, home: Option<&Path>) -> Result<()>
+    where F: Fn(&Path) -> PathBuf
+{
+    for (_, service) in file.services.iter_mut() {
+        if let Some(ref mut build) = service.build {
+            if let Context::Dir(ref mut dir) = *build.context.value_mut()? {
+                let new_dir = f(dir);
+                *dir = new_dir;
+            }
+        }
+        for env_file in &mut service.env_files {
+            let path = env_file.value_mut()?;
+            let new_path = f(path);
+            *path = new_path;
+        }
+        if let Some(ref mut extends) = service.extends {
+            if let Some(ref mut extends_file) = extends.file {
+                let path = extends_file.value_mut()?;
+                let new_path = f(path);
+                *path = new_path;
+            }
+        }
+        for mount in &mut service.volumes {
+            let mount = mount.value_mut()?;
+            let new_host = match mount.host {
+                Some(HostVolume::Path(ref path)) => Some(HostVolume::Path(f(path))),
+                Some(HostVolume::UserRelativePath(ref path)) => {
+                    home.map(|home| HostVolume::Path(normalize(&home.join(path))))
+                }
+                _ => None,
+            };
+            if new_host.is_some() {
+                mount.host = new_host;
+            }
+        }
+    }
+    for (_, secret) in file.secrets.iter_mut() {
+        if let Some(ref mut secret_file) = secret.file {
+            let new_path = f(Path::new(&secret_file[..]));
+            *secret_file = new_path.to_string_lossy().into_owned();
+        }
+    }
+    for (_, config) in file.configs.iter_mut() {
+        if let Some(ref mut config_file) = config.file {
+            let path = config_file.value_mut()?;
+            let new_path = f(path);
+            *path = new_path;
+        }
+    }
+    Ok(())
+}
+
+/// Remove `.` components from `path`, and resolve `..` components where
+/// we can do so without looking at the file system.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut components: Vec<Component> = vec![];
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                match components.last().cloned() {
+                    Some(Component::Normal(_)) => {
+                        components.pop();
+                    }
+                    // `/..` is the same as `/`.
+                    Some(Component::RootDir) |
+                    Some(Component::Prefix(_)) => {}
+                    _ => components.push(component),
+                }
+            }
+            _ => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        return PathBuf::from(".");
+    }
+    components.iter().map(|c| c.as_os_str()).collect()
+}
+
+/// The path which refers to `path` from inside `base`.  Both paths must be
+/// absolute and normalized.  Returns `None` if they don't have the same
+/// root.
+fn relative_to(path: &Path, base: &Path) -> Option<PathBuf> {
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = path.iter().zip(base.iter()).take_while(|&(p, b)| p == b).count();
+    // The root (and drive prefix, on Windows) must match.
+    let root_len = path.iter()
+        .take_while(|c| match **c {
+            Component::Prefix(_) | Component::RootDir => true,
+            _ => false,
+        })
+        .count();
+    if common < root_len {
+        return None;
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    // Start with `./` if we don't start with `..`, so that host paths in
+    // `volumes` aren't mistaken for volume names.
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    for component in &path[common..] {
+        relative.push(component.as_os_str());
+    }
+    Some(relative)
+}
+
+#[cfg(not(windows))]
+#[test]
+fn absolutize_and_relativize_paths() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+services:
+  web:
+    build: "."
+    env_file: ["./web.env", "../shared/common.env"]
+    extends:
+      file: "base.yml"
+      service: "web"
+    volumes:
+      - "./src:/app/src"
+      - "~/.cache:/root/.cache"
+      - "/etc/ssl:/etc/ssl:ro"
+      - "data:/data"
+configs:
+  nginx:
+    file: "./nginx.conf"
+secrets:
+  token:
+    file: "secrets/token.txt"
+volumes:
+  data: {}
+"#;
+    let mut file = File::from_str(yaml).unwrap();
+    file.absolutize_paths(Path::new("/home/me/app"), Some(Path::new("/home/me")))
+        .unwrap();
+    let web = file.services.get("web").unwrap();
+    assert_eq!(web.build.as_ref().unwrap().context.value().unwrap(),
+               &Context::new("/home/me/app"));
+    assert_eq!(web.env_files
+                   .iter()
+                   .map(|p| p.value().unwrap().to_owned())
+                   .collect::<Vec<_>>(),
+               vec![PathBuf::from("/home/me/app/web.env"),
+                    PathBuf::from("/home/me/shared/common.env")]);
+    assert_eq!(web.extends.as_ref().unwrap().file.as_ref().unwrap().value().unwrap(),
+               Path::new("/home/me/app/base.yml"));
+    let hosts: Vec<String> = web.volumes
+        .iter()
+        .map(|v| v.value().unwrap().host.as_ref().unwrap().to_string())
+        .collect();
+    assert_eq!(hosts,
+               vec!["/home/me/app/src", "/home/me/.cache", "/etc/ssl", "data"]);
+    let nginx = file.configs.get("nginx").unwrap();
+    assert_eq!(nginx.file.as_ref().unwrap().value().unwrap(),
+               Path::new("/home/me/app/nginx.conf"));
+    assert_eq!(file.secrets.get("token").unwrap().file,
+               Some("/home/me/app/secrets/token.txt".to_owned()));
+
+    // Move the file to a sibling directory.
+    file.relativize_paths(Path::new("/home/me/deploy"), Some(Path::new("/home/me")))
+        .unwrap();
+    let web = file.services.get("web").unwrap();
+    assert_eq!(web.build.as_ref().unwrap().context.value().unwrap(),
+               &Context::new("../app"));
+    assert_eq!(web.env_files[1].value().unwrap(),
+               Path::new("../shared/common.env"));
+    let hosts: Vec<String> = web.volumes
+        .iter()
+        .map(|v| v.value().unwrap().host.as_ref().unwrap().to_string())
+        .collect();
+    assert_eq!(hosts, vec!["../app/src", "../.cache", "/etc/ssl", "data"]);
+    assert_eq!(file.secrets.get("token").unwrap().file,
+               Some("../app/secrets/token.txt".to_owned()));
+
+    // By default, we only touch paths inside `base`.
+    let mut file = File::from_str(yaml).unwrap();
+    file.absolutize_paths(Path::new("/home/me/app"), None).unwrap();
+    file.relativize_paths(Path::new("/home/me/app"), None).unwrap();
+    let web = file.services.get("web").unwrap();
+    assert_eq!(web.env_files[1].value().unwrap(),
+               Path::new("/home/me/shared/common.env"));
+    let hosts: Vec<String> = web.volumes
+        .iter()
+        .map(|v| v.value().unwrap().host.as_ref().unwrap().to_string())
+        .collect();
+    assert_eq!(hosts, vec!["./src", "~/.cache", "/etc/ssl", "data"]);
+}
+
+#[test]
+fn normalize_resolves_dots_lexically() {
+    assert_eq!(normalize(Path::new("/a/./b/../c")), PathBuf::from("/a/c"));
+    assert_eq!(normalize(Path::new("/..")), PathBuf::from("/"));
+    assert_eq!(normalize(Path::new("../a/..")), PathBuf::from(".."));
+    assert_eq!(normalize(Path::new("./")), PathBuf::from("."));
+}
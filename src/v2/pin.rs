@@ -0,0 +1,151 @@
+//! Pinning images to exact digests, so that a file always runs the same
+//! code no matter when it's deployed.
+//!
+//! We don't talk to Docker registries ourselves.  Instead, digests are
+//! looked up using an `ImageResolver`, which may be backed by a registry
+//! client, the local Docker daemon, or an `ImageLock` listing known
+//! digests.
+
+use errors::*;
+use super::{Digest, File, Image, value};
+
+/// Looks up the digest of an image.
+pub trait ImageResolver {
+    /// Return the digest which `image` currently refers to.  `image` will
+    /// not have a digest of its own.
+    fn resolve(&self, image: &Image) -> Result<Digest>;
+}
+
+/// The key used to look up `image` in an `ImageLock`.  We normalize images
+/// and supply the default `latest` tag, so that `redis` and
+/// `docker.io/library/redis:latest` are treated as the same image.  But
+/// `redis@sha256:...` doesn't refer to `latest`, so images which only
+/// have a digest are left untagged.
+pub fn lock_key(image: &Image) -> String {
+    let untagged_digest = image.tag.is_none() && image.digest.is_some();
+    let mut image = image.without_digest().normalized();
+    if image.tag.is_none() && !untagged_digest {
+        image.tag = Some("latest".to_owned());
+    }
+    image.to_string()
+}
+
+/// Pin the image of every service in `file` to a digest, using `resolver`
+/// to look up digests.  We keep any tag, giving `name:tag@digest`, so that
+/// pinned images still match their entries in a lock file.  Services which
+/// are already pinned are left alone, as are services with a `build`
+/// section, because their `image` names the image we build.  If any image
+/// can't be resolved, we return an error and leave `file` unchanged.
+pub fn pin_images<R>(file: &mut File, resolver: &R) -> Result<()>
+    where R: ImageResolver + ?Sized
+{
+    let mut pinned = vec![];
+    for (name, service) in &file.services {
+        if service.build.is_some() {
+            continue;
+        }
+        if let Some(ref image) = service.image {
+            let image = image.value()?;
+            if image.digest.is_none() {
+                let digest = resolver.resolve(image)?;
+                pinned.push((name.to_owned(),
+                             Image { digest: Some(digest), ..image.clone() }));
+            }
+        }
+    }
+    for (name, image) in pinned {
+        if let Some(service) = file.services.get_mut(&name) {
+            service.image = Some(value(image));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn pin_images_adds_digests() {
+    use std::str::FromStr;
+    use super::ImageLock;
+
+    let resolver = ImageLock::from_str(r#"---
+images:
+  "redis:5": "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+  "docker.io/library/nginx": "sha256:fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210"
+version: 1
+"#)
+        .unwrap();
+
+    let yaml = r#"---
+version: "2"
+services:
+  cache:
+    image: "redis:5"
+  web:
+    image: "nginx"
+  app:
+    build: "."
+    image: "example/app:dev"
+"#;
+    let mut file = File::from_str(yaml).unwrap();
+    file.pin_images(&resolver).unwrap();
+    let image = |name: &str| {
+        let service = file.services.get(name).unwrap();
+        service.image.as_ref().unwrap().value().unwrap().to_string()
+    };
+    assert_eq!(image("cache"),
+               "redis:5@sha256:0123456789abcdef0123456789abcdef\
+                0123456789abcdef0123456789abcdef");
+    assert_eq!(image("web"),
+               "nginx@sha256:fedcba9876543210fedcba9876543210\
+                fedcba9876543210fedcba9876543210");
+    assert_eq!(image("app"), "example/app:dev");
+
+    let yaml = r#"---
+version: "2"
+services:
+  db:
+    image: "postgres:11"
+"#;
+    let mut file = File::from_str(yaml).unwrap();
+    let err = file.pin_images(&resolver).unwrap_err();
+    assert_eq!(err.to_string(), "no digest known for image 'postgres:11'");
+}
+
+#[test]
+fn lock_key_does_not_tag_digest_only_images() {
+    use std::str::FromStr;
+
+    let digest = "sha256:0123456789abcdef0123456789abcdef\
+                  0123456789abcdef0123456789abcdef";
+    let key = |image: &str| lock_key(&Image::from_str(image).unwrap());
+    assert_eq!(key("redis"), "docker.io/library/redis:latest");
+    assert_eq!(key(&format!("redis:5@{}", digest)), "docker.io/library/redis:5");
+    assert_eq!(key(&format!("redis@{}", digest)), "docker.io/library/redis");
+}
+
+#[test]
+fn pinned_files_match_their_lock() {
+    use std::str::FromStr;
+    use super::ImageLock;
+
+    let mut resolver = ImageLock::new();
+    resolver.insert(&Image::from_str("redis:5").unwrap(),
+                    Digest::from_str("sha256:0123456789abcdef0123456789abcdef\
+                                      0123456789abcdef0123456789abcdef")
+                        .unwrap());
+    resolver.insert(&Image::from_str("nginx").unwrap(),
+                    Digest::from_str("sha256:fedcba9876543210fedcba9876543210\
+                                      fedcba9876543210fedcba9876543210")
+                        .unwrap());
+    let mut file = File::from_str(r#"---
+version: "2"
+services:
+  cache:
+    image: "redis:5"
+  web:
+    image: "nginx"
+"#)
+        .unwrap();
+    let lock = ImageLock::generate(&file, &resolver).unwrap();
+    file.pin_images(&lock).unwrap();
+    assert_eq!(lock.verify(&file).unwrap(), vec![]);
+}
@@ -72,6 +72,14 @@ pub struct PortMapping {
     pub host_ports: Option<Ports>,
     /// The container port(s) to export.
     pub container_ports: Ports,
+    /// The network protocol to use, such as `tcp` or `udp`.  Defaults to
+    /// `tcp`.
+    pub protocol: Option<String>,
+    /// How to publish the port in swarm mode: `ingress` to load balance
+    /// across the swarm, or `host` to publish on the node itself.  This
+    /// can only be written using the long syntax, which requires version
+    /// 3.2 or later.
+    pub mode: Option<String>,
 
     /// PRIVATE.  Mark this struct as having unknown fields for future
     /// compatibility.  This prevents direct construction and exhaustive
@@ -103,6 +111,8 @@ impl PortMapping {
             host_address: Default::default(),
             host_ports: Some(host_ports.into()),
             container_ports: container_ports.into(),
+            protocol: None,
+            mode: None,
             _hidden: (),
         }
     }
@@ -125,6 +135,8 @@ impl PortMapping {
             host_address: Default::default(),
             host_ports: None,
             container_ports: container_ports.into(),
+            protocol: None,
+            mode: None,
             _hidden: (),
         }
     }
@@ -132,6 +144,109 @@ impl PortMapping {
 
 impl_interpolatable_value!(PortMapping);
 
+/// The long syntax for a `PortMapping`, as a map.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PortMappingLongSyntax {
+    /// The host address to bind to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    host_ip: Option<String>,
+    /// The swarm publishing mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<String>,
+    /// The network protocol.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    protocol: Option<String>,
+    /// The host port or range of ports, which may be written as either a
+    /// number or a string.
+    #[serde(default, skip_serializing_if = "Option::is_none",
+            serialize_with = "serialize_published_ports",
+            deserialize_with = "deserialize_published_ports")]
+    published: Option<Ports>,
+    /// The container port.
+    target: u16,
+}
+
+impl LongSyntax for PortMapping {
+    fn requires_long_syntax(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    fn serialize_long<S>(&self, serializer: &mut S) -> result::Result<(), S::Error>
+        where S: Serializer
+    {
+        // The long syntax only supports port ranges for `published`.
+        let target = match self.container_ports {
+            Ports::Port(port) => port,
+            Ports::Range(..) => {
+                return Err(serde::ser::Error::custom("cannot use port ranges \
+                                                      with `mode`"));
+            }
+        };
+        PortMappingLongSyntax {
+                host_ip: self.host_address.map(|addr| addr.to_string()),
+                mode: self.mode.clone(),
+                protocol: self.protocol.clone(),
+                published: self.host_ports,
+                target: target,
+            }
+            .serialize(serializer)
+    }
+
+    fn deserialize_long<M>(visitor: M) -> result::Result<Self, M::Error>
+        where M: de::MapVisitor
+    {
+        let mut mvd = de::value::MapVisitorDeserializer::new(visitor);
+        let long = PortMappingLongSyntax::deserialize(&mut mvd)?;
+        let host_address = match long.host_ip {
+            Some(ref addr) => {
+                let addr = IpAddr::from_str(addr).map_err(|_| {
+                        let msg = format!("invalid IP address '{}'", addr);
+                        <M::Error as de::Error>::custom(msg)
+                    })?;
+                Some(addr)
+            }
+            None => None,
+        };
+        Ok(PortMapping {
+            host_address: host_address,
+            host_ports: long.published,
+            container_ports: Ports::Port(long.target),
+            protocol: long.protocol,
+            mode: long.mode,
+            _hidden: (),
+        })
+    }
+}
+
+/// Serialize the `published` ports of the long syntax, using a number for
+/// a single port and a string for a range.
+fn serialize_published_ports<S>(ports: &Option<Ports>,
+                                serializer: &mut S)
+                                -> result::Result<(), S::Error>
+    where S: Serializer
+{
+    match *ports {
+        Some(Ports::Port(port)) => port.serialize(serializer),
+        Some(ref range) => range.to_string().serialize(serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Deserialize the `published` ports of the long syntax, which may be
+/// either a number or a string containing a port or a range of ports.
+fn deserialize_published_ports<D>(deserializer: &mut D)
+                                  -> result::Result<Option<Ports>, D::Error>
+    where D: Deserializer
+{
+    let ConvertToString(ports) = ConvertToString::deserialize(deserializer)?;
+    let ports = Ports::from_str(&ports)
+        .map_err(|err| <D::Error as de::Error>::custom(format!("{}", err)))?;
+    Ok(Some(ports))
+}
+
+/// Formats a `PortMapping` using the short syntax, which cannot represent
+/// `mode`.
 impl fmt::Display for PortMapping {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // We can't serialize a host_address without host_ports.
@@ -145,7 +260,11 @@ impl fmt::Display for PortMapping {
         if let Some(ports) = self.host_ports {
             write!(f, "{}:", ports)?;
         }
-        write!(f, "{}", self.container_ports)
+        write!(f, "{}", self.container_ports)?;
+        if let Some(ref protocol) = self.protocol {
+            write!(f, "/{}", protocol)?;
+        }
+        Ok(())
     }
 }
 
@@ -153,17 +272,25 @@ impl FromStr for PortMapping {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
+        // Strip off any protocol, as in `53:53/udp`.
+        let (mapping, protocol) = match s.rfind('/') {
+            Some(idx) => (&s[..idx], Some(s[idx + 1..].to_owned())),
+            None => (s, None),
+        };
+
         // Split backwards from the end of the string, in case the first
         // address field is an IPv6 address with embedded colons.  Hey,
         // it's not specified _never_ to happen.  Note that `fields` will
         // be in reverse order.
-        let fields: Vec<_> = s.rsplitn(3, ":").collect();
+        let fields: Vec<_> = mapping.rsplitn(3, ":").collect();
         match fields.len() {
             1 => {
                 Ok(PortMapping {
                     host_address: None,
                     host_ports: None,
                     container_ports: FromStr::from_str(fields[0])?,
+                    protocol: protocol,
+                    mode: None,
                     _hidden: (),
                 })
             }
@@ -172,6 +299,8 @@ impl FromStr for PortMapping {
                     host_address: None,
                     host_ports: Some(FromStr::from_str(fields[1])?),
                     container_ports: FromStr::from_str(fields[0])?,
+                    protocol: protocol,
+                    mode: None,
                     _hidden: (),
                 })
             }
@@ -184,6 +313,8 @@ impl FromStr for PortMapping {
                     host_address: Some(addr),
                     host_ports: Some(FromStr::from_str(fields[1])?),
                     container_ports: FromStr::from_str(fields[0])?,
+                    protocol: protocol,
+                    mode: None,
                     _hidden: (),
                 })
             }
@@ -205,14 +336,65 @@ fn port_mapping_should_have_a_string_representation() {
         host_address: Some(localhost),
         ..PortMapping::new(80, 80)
     };
+    let map4 = PortMapping {
+        protocol: Some("udp".to_owned()),
+        ..PortMapping::new(53, 53)
+    };
 
     let pairs = vec!(
         (map1, "80"),
         (map2, "8080-8089:3000-3009"),
         (map3, "127.0.0.1:80:80"),
+        (map4, "53:53/udp"),
     );
     for (map, s) in pairs {
         assert_eq!(map.to_string(), s);
         assert_eq!(map, PortMapping::from_str(s).unwrap());
     }
 }
+
+#[test]
+fn port_mapping_supports_long_syntax() {
+    let yaml = r#"---
+"ports":
+- "8080:80"
+- "mode": "host"
+  "protocol": "udp"
+  "published": 5353
+  "target": 53
+"#;
+    assert_roundtrip!(Service, yaml);
+
+    let service: Service = serde_yaml::from_str(yaml).unwrap();
+    let host_mode = service.ports[1].value().unwrap();
+    assert_eq!(host_mode.host_ports, Some(Ports::Port(5353)));
+    assert_eq!(host_mode.container_ports, Ports::Port(53));
+    assert_eq!(host_mode.mode, Some("host".to_owned()));
+}
+
+#[test]
+fn port_mapping_long_syntax_supports_strings_ranges_and_host_ips() {
+    let yaml = r#"---
+"ports":
+- "host_ip": "127.0.0.1"
+  "mode": "host"
+  "published": "8080-8081"
+  "target": 80
+"#;
+    assert_roundtrip!(Service, yaml);
+
+    let service: Service = serde_yaml::from_str(yaml).unwrap();
+    let mapping = service.ports[0].value().unwrap();
+    assert_eq!(mapping.host_address, Some(IpAddr::from_str("127.0.0.1").unwrap()));
+    assert_eq!(mapping.host_ports, Some(Ports::Range(8080, 8081)));
+
+    let yaml = r#"---
+"ports":
+- "mode": "host"
+  "published": "8080"
+  "target": 80
+"#;
+    let service: Service = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(service.ports[0].value().unwrap().host_ports,
+               Some(Ports::Port(8080)));
+}
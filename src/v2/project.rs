@@ -0,0 +1,277 @@
+//! Predicting the Docker names which `docker-compose` gives to the
+//! containers, networks and volumes in a file.
+
+use std::fs;
+use std::path::Path;
+
+use errors::*;
+use super::{File, Map, Service};
+
+/// A `File` together with the project name used to run it.  This resolves
+/// the concrete Docker name of every container, network and volume in the
+/// same way as `docker-compose`, including the implicit `default` network.
+/// All fields must already be interpolated.
+///
+/// ```
+/// use std::str::FromStr;
+/// use compose_yml::v2 as dc;
+///
+/// let file = dc::File::from_str(r#"---
+/// version: "2"
+/// services:
+///   web:
+///     image: "nginx"
+/// volumes:
+///   data: {}
+/// "#).unwrap();
+/// let project = dc::Project::new("myapp", file).unwrap();
+/// assert_eq!(project.container_name("web"), Some("myapp_web_1"));
+/// assert_eq!(project.network_name("default"), Some("myapp_default"));
+/// assert_eq!(project.volume_name("data"), Some("myapp_data"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    /// The normalized project name.
+    name: String,
+    /// The file we wrap.
+    file: File,
+    /// The Docker name of each service's container.
+    containers: Map<String, String>,
+    /// The Docker name of each network, including `default` if any service
+    /// uses it.
+    networks: Map<String, String>,
+    /// The Docker name of each volume.
+    volumes: Map<String, String>,
+}
+
+impl Project {
+    /// Wrap `file` using the project name `name`.  Like `docker-compose`,
+    /// we lowercase the name and remove any characters other than letters,
+    /// digits, `-` and `_`.
+    pub fn new(name: &str, file: File) -> Result<Project> {
+        let normalized = normalize_project_name(name);
+        if normalized.is_empty() {
+            return Err(Error::invalid_value("project name", name));
+        }
+
+        let mut containers = Map::new();
+        for (service_name, service) in &file.services {
+            containers.insert(service_name.to_owned(),
+                              container_name(&normalized, service_name, service)?);
+        }
+        let mut networks = Map::new();
+        if uses_default_network(&file) {
+            let docker_name = network_name(&normalized, "default", &file)
+                .expect("default network should always have a name")?;
+            networks.insert("default".to_owned(), docker_name);
+        }
+        for network_name_in_file in file.networks.keys() {
+            let docker_name = network_name(&normalized, network_name_in_file, &file)
+                .expect("declared network should always have a name")?;
+            networks.insert(network_name_in_file.to_owned(), docker_name);
+        }
+        let mut volumes = Map::new();
+        for volume_name_in_file in file.volumes.keys() {
+            let docker_name = volume_name(&normalized, volume_name_in_file, &file)
+                .expect("declared volume should always have a name");
+            volumes.insert(volume_name_in_file.to_owned(), docker_name);
+        }
+
+        Ok(Project {
+            name: normalized,
+            file: file,
+            containers: containers,
+            networks: networks,
+            volumes: volumes,
+        })
+    }
+
+    /// Read the file at `path`, and name the project after the file's
+    /// top-level `name` field, or else after the directory containing
+    /// the file, as `docker-compose` does.
+    pub fn from_path<P>(path: P) -> Result<Project>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let file = File::read_from_path(path)?;
+        let name = match file.name {
+            Some(ref name) => name.value()?.to_owned(),
+            None => {
+                let mkerr = || ErrorKind::ReadFile(path.to_owned());
+                let path = fs::canonicalize(path).chain_err(&mkerr)?;
+                let dir_name = path.parent().and_then(|dir| dir.file_name());
+                match dir_name {
+                    Some(dir_name) => dir_name.to_string_lossy().into_owned(),
+                    None => {
+                        let path = path.display().to_string();
+                        return Err(Error::invalid_value("project directory", path));
+                    }
+                }
+            }
+        };
+        Project::new(&name, file)
+    }
+
+    /// The normalized name of this project.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The file we wrap.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Unwrap the underlying file.
+    pub fn into_file(self) -> File {
+        self.file
+    }
+
+    /// The Docker name of the container for `service`, or `None` if there
+    /// is no such service.
+    pub fn container_name(&self, service: &str) -> Option<&str> {
+        self.containers.get(service).map(|name| &name[..])
+    }
+
+    /// The Docker name of `network`, or `None` if there is no such network.
+    /// The implicit `default` network is only included if at least one
+    /// service uses it.
+    pub fn network_name(&self, network: &str) -> Option<&str> {
+        self.networks.get(network).map(|name| &name[..])
+    }
+
+    /// The Docker name of `volume`, or `None` if there is no such volume.
+    pub fn volume_name(&self, volume: &str) -> Option<&str> {
+        self.volumes.get(volume).map(|name| &name[..])
+    }
+
+    /// The Docker names of all containers, indexed by service name.
+    pub fn container_names(&self) -> &Map<String, String> {
+        &self.containers
+    }
+
+    /// The Docker names of all networks, indexed by network name.
+    pub fn network_names(&self) -> &Map<String, String> {
+        &self.networks
+    }
+
+    /// The Docker names of all volumes, indexed by volume name.
+    pub fn volume_names(&self) -> &Map<String, String> {
+        &self.volumes
+    }
+}
+
+/// Normalize a project name the same way as `docker-compose`.
+fn normalize_project_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .filter(|&c| {
+            ((c as u32) < 128 && c.is_alphanumeric()) || c == '-' || c == '_'
+        })
+        .collect()
+}
+
+/// Does any service in `file` join the `default` network?  Services join
+/// it if they don't specify `network_mode` or any `networks`.
+fn uses_default_network(file: &File) -> bool {
+    file.services.values().any(|service| {
+        service.network_mode.is_none() &&
+        (service.networks.is_empty() || service.networks.contains_key("default"))
+    })
+}
+
+/// The Docker name of the container for `service`.
+pub fn container_name(project: &str, name: &str, service: &Service) -> Result<String> {
+    match service.container_name {
+        Some(ref container_name) => Ok(container_name.value()?.to_owned()),
+        None => Ok(format!("{}_{}_1", project, name)),
+    }
+}
+
+/// The Docker name of the network declared as `name` in `file`.
+pub fn network_name(project: &str, name: &str, file: &File) -> Option<Result<String>> {
+    if name == "default" && file.networks.get(name).is_none() {
+        return Some(Ok(format!("{}_default", project)));
+    }
+    file.networks.get(name).map(|network| {
+        match network.external {
+            Some(ref external) => {
+                match external.name {
+                    Some(ref ext_name) => Ok(ext_name.value()?.to_owned()),
+                    None => Ok(name.to_owned()),
+                }
+            }
+            None => Ok(format!("{}_{}", project, name)),
+        }
+    })
+}
+
+/// The Docker name of the volume declared as `name` in `file`.
+pub fn volume_name(project: &str, name: &str, file: &File) -> Option<String> {
+    file.volumes.get(name).map(|volume| {
+        if volume.external == Some(true) {
+            name.to_owned()
+        } else {
+            format!("{}_{}", project, name)
+        }
+    })
+}
+
+#[test]
+fn project_resolves_docker_names() {
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "2"
+services:
+  db:
+    image: "postgres"
+    container_name: "database"
+    networks: ["backend"]
+    volumes: ["pgdata:/var/lib/postgresql/data"]
+  web:
+    image: "nginx"
+  worker:
+    image: "example/worker"
+    network_mode: "host"
+networks:
+  backend: {}
+  shared:
+    external:
+      name: "shared_net"
+  legacy:
+    external: true
+volumes:
+  pgdata: {}
+  backups:
+    external: true
+"#)
+        .unwrap();
+    let project = Project::new("My App!", file).unwrap();
+    assert_eq!(project.name(), "myapp");
+    assert_eq!(project.container_name("db"), Some("database"));
+    assert_eq!(project.container_name("web"), Some("myapp_web_1"));
+    assert_eq!(project.container_name("missing"), None);
+    assert_eq!(project.network_name("default"), Some("myapp_default"));
+    assert_eq!(project.network_name("backend"), Some("myapp_backend"));
+    assert_eq!(project.network_name("shared"), Some("shared_net"));
+    assert_eq!(project.network_name("legacy"), Some("legacy"));
+    assert_eq!(project.volume_name("pgdata"), Some("myapp_pgdata"));
+    assert_eq!(project.volume_name("backups"), Some("backups"));
+    assert_eq!(project.container_names().len(), 3);
+    assert_eq!(project.network_names().len(), 4);
+    assert_eq!(project.volume_names().len(), 2);
+
+    let file = File::from_str(r#"---
+version: "2"
+services:
+  worker:
+    image: "example/worker"
+    network_mode: "none"
+"#)
+        .unwrap();
+    let project = Project::new("app", file).unwrap();
+    assert_eq!(project.network_name("default"), None);
+
+    assert!(Project::new("!!!", File::default()).is_err());
+}
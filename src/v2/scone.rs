@@ -0,0 +1,188 @@
+//! Typed settings for services which run inside SCONE enclaves.  These are
+//! only supported by the `scone` dialect.
+
+use std::fmt;
+use std::str::FromStr;
+
+use errors::*;
+use super::{InterpolateAll, MergeOverride, RawOr};
+use super::interpolation::InterpolatableValue;
+
+/// Define a type which holds a string of exactly `$len` hexadecimal
+/// digits.  Unless the type is marked as `secret`, it's displayed as-is.
+macro_rules! hex_string_type {
+    ($(#[$flag:meta])* pub struct $name:ident, $len:expr, $wanted:expr) => {
+        hex_string_type!($(#[$flag])* pub secret struct $name, $len, $wanted);
+
+        impl_interpolatable_value!($name);
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", &self.0)
+            }
+        }
+    };
+    ($(#[$flag:meta])* pub secret struct $name:ident, $len:expr, $wanted:expr) => {
+        $(#[$flag])*
+        #[derive(Clone, PartialEq, Eq)]
+        pub struct $name(String);
+
+        impl $name {
+            /// The hexadecimal digits, exactly as they were given.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<Self> {
+                let is_hex = s.chars().all(|c| c.is_digit(16));
+                if s.len() != $len || !is_hex {
+                    return Err(Error::invalid_value($wanted, s));
+                }
+                Ok($name(s.to_owned()))
+            }
+        }
+    };
+}
+
+hex_string_type! {
+    /// The SHA-256 hash of an enclave, as 64 hexadecimal digits.
+    #[derive(Debug)]
+    pub struct MrEnclave, 64, "mrenclave (64 hex digits)"
+}
+
+hex_string_type! {
+    /// The tag of a SCONE file system protection file, as 32 hexadecimal
+    /// digits.
+    #[derive(Debug)]
+    pub struct FspfTag, 32, "fspf_tag (32 hex digits)"
+}
+
+hex_string_type! {
+    /// The 256-bit key used to encrypt a SCONE file system protection
+    /// file, as 64 hexadecimal digits.  To keep keys out of logs, neither
+    /// `Debug` nor `Display` show the actual key.  Use `as_str` to get it.
+    /// A `RawOr<FspfKey>` does show the key, because that's how it's
+    /// written to files.
+    pub secret struct FspfKey, 64, "fspf_key (64 hex digits)"
+}
+
+impl fmt::Debug for FspfKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FspfKey(<redacted>)")
+    }
+}
+
+impl fmt::Display for FspfKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<redacted>")
+    }
+}
+
+impl InterpolatableValue for FspfKey {
+    fn iv_from_str(s: &str) -> Result<Self> {
+        FromStr::from_str(s)
+    }
+
+    /// This is what we write to files, so unlike `Display`, it includes
+    /// the actual key.
+    fn fmt_iv(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The SCONE settings for a service.  On disk, these are stored as
+/// separate keys on the service.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SconeConfig {
+    /// The expected hash of the service's enclave.
+    pub mrenclave: Option<RawOr<MrEnclave>>,
+    /// The path to the file system protection file inside the container.
+    pub fspf_path: Option<RawOr<String>>,
+    /// The key used to encrypt the file system protection file.
+    pub fspf_key: Option<RawOr<FspfKey>>,
+    /// The expected tag of the file system protection file.
+    pub fspf_tag: Option<RawOr<FspfTag>>,
+    /// If `"true"`, this service doesn't run inside an enclave.
+    pub not_scone: Option<RawOr<bool>>,
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    pub _hidden: (),
+}
+
+derive_interpolate_all_for!(SconeConfig, {
+    mrenclave, fspf_path, fspf_key, fspf_tag, not_scone, _hidden
+});
+
+derive_merge_override_for!(SconeConfig, {
+    mrenclave, fspf_path, fspf_key, fspf_tag, not_scone, _hidden
+});
+
+impl SconeConfig {
+    /// Does this service have any SCONE settings at all?
+    pub fn is_empty(&self) -> bool {
+        *self == SconeConfig::default()
+    }
+
+    /// Is this service marked as not running inside an enclave?  A
+    /// `not_scone` which hasn't been interpolated yet counts as false.
+    pub fn is_not_scone(&self) -> bool {
+        match self.not_scone {
+            Some(ref not_scone) => not_scone.value().ok() == Some(&true),
+            None => false,
+        }
+    }
+}
+
+#[test]
+fn scone_settings_are_typed() {
+    use serde_yaml;
+    use super::{Service, normalize_yaml, value};
+
+    let mrenclave = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+    let yaml = format!(r#"---
+"fspf_key": "{}"
+"fspf_path": "/fspf.pb"
+"fspf_tag": "0123456789abcdef0123456789abcdef"
+"mrenclave": "{}"
+"not_scone": "false"
+"#,
+                       mrenclave.to_uppercase(),
+                       mrenclave);
+    let service: Service = serde_yaml::from_str(&yaml).unwrap();
+    let written = serde_yaml::to_string(&service).unwrap();
+    assert_eq!(normalize_yaml(&written), normalize_yaml(&yaml));
+    let scone = &service.scone;
+    assert_eq!(scone.mrenclave.as_ref().unwrap().value().unwrap().as_str(),
+               mrenclave);
+    assert_eq!(scone.fspf_path, Some(value("/fspf.pb".to_owned())));
+    assert_eq!(scone.fspf_tag.as_ref().unwrap().value().unwrap().as_str(),
+               "0123456789abcdef0123456789abcdef");
+    assert!(!scone.is_not_scone());
+
+    // The key is only ever shown by `as_str` and when writing the file.
+    let key = scone.fspf_key.as_ref().unwrap().value().unwrap();
+    assert_eq!(key.as_str(), mrenclave.to_uppercase());
+    assert_eq!(key.to_string(), "<redacted>");
+    assert!(!format!("{:?}", scone).contains(&mrenclave.to_uppercase()));
+
+    let not_scone: Service = serde_yaml::from_str("---\n\"not_scone\": \"true\"\n")
+        .unwrap();
+    assert!(not_scone.scone.is_not_scone());
+    assert!(Service::default().scone.is_empty());
+
+    let invalid = ["---\n\"mrenclave\": \"abc\"\n",
+                   "---\n\"fspf_tag\": \"0123456789abcdefg123456789abcdef\"\n",
+                   "---\n\"fspf_key\": \"0123456789abcdef\"\n",
+                   "---\n\"not_scone\": \"maybe\"\n"];
+    for yaml in &invalid {
+        assert!(serde_yaml::from_str::<Service>(yaml).is_err(), "{}", yaml);
+    }
+}
@@ -0,0 +1,247 @@
+//! Checking that a file uses SCONE settings consistently.
+//!
+//! The JSON schemas for the `.scone` versions only check the type of each
+//! SCONE field.  This pass checks the invariants which hold between
+//! fields: which services need `mrenclave`, and which file system
+//! protection settings must be used together.  Secrets which aren't
+//! declared are already rejected when the file is parsed.
+
+use std::fmt;
+
+use super::{File, Service};
+use super::validate::escape_pointer_segment;
+
+/// A problem with the SCONE settings in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SconeProblem {
+    /// A SCONE-only field is used by a file whose version doesn't have the
+    /// `scone` dialect.  Holds the field name.
+    RequiresSconeDialect(String),
+    /// A service runs inside an enclave, but has no `mrenclave`.
+    MissingMrenclave,
+    /// A service uses `fspf_key` or `fspf_tag` without `fspf_path`.  Holds
+    /// the name of the field which was used.
+    IncompleteFspf(String),
+}
+
+impl fmt::Display for SconeProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SconeProblem::RequiresSconeDialect(ref field) => {
+                write!(f, "{} requires a .scone version", field)
+            }
+            SconeProblem::MissingMrenclave => {
+                write!(f, "services without not_scone must have mrenclave")
+            }
+            SconeProblem::IncompleteFspf(ref field) => {
+                write!(f, "{} must be used together with fspf_path", field)
+            }
+        }
+    }
+}
+
+/// A single problem found by `SconeReport`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SconeFinding {
+    /// The name of the service with the problem, or `None` for top-level
+    /// settings.
+    pub service: Option<String>,
+    /// A JSON pointer to the offending value, such as
+    /// `/services/web/fspf_tag`.  Problems caused by a missing field point
+    /// at the service.
+    pub path: String,
+    /// What's wrong.
+    pub problem: SconeProblem,
+}
+
+impl fmt::Display for SconeFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", &self.path, &self.problem)
+    }
+}
+
+/// The results of checking the SCONE settings in a `File`.
+///
+/// ```
+/// use std::str::FromStr;
+/// use compose_yml::v2 as dc;
+///
+/// let file = dc::File::from_str(r#"---
+/// version: "3.1.scone"
+/// services:
+///   web:
+///     image: "nginx"
+///     not_scone: "true"
+///   app:
+///     image: "example/app"
+/// "#).unwrap();
+/// let report = file.check_scone();
+/// assert_eq!(report.findings.len(), 1);
+/// assert_eq!(report.findings[0].to_string(),
+///            "/services/app: services without not_scone must have mrenclave");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SconeReport {
+    /// Everything we found, in the order we found it.
+    pub findings: Vec<SconeFinding>,
+}
+
+impl SconeReport {
+    /// Check the SCONE settings in `file`.  Fields which still require
+    /// interpolation are skipped, because we can't know what they contain.
+    pub fn for_file(file: &File) -> SconeReport {
+        let mut report = SconeReport::default();
+        let is_scone = file.version
+            .as_ref()
+            .map_or(false, |version| version.has_dialect("scone"));
+        if is_scone {
+            for (name, service) in &file.services {
+                report.check_service(name, service);
+            }
+        } else {
+            report.check_not_scone(file);
+        }
+        report
+    }
+
+    /// Did we find anything at all?
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Record a finding for the service `name`.  `field` may be empty.
+    fn add(&mut self, name: &str, field: &str, problem: SconeProblem) {
+        let mut path = format!("/services/{}", escape_pointer_segment(name));
+        if !field.is_empty() {
+            path.push('/');
+            path.push_str(field);
+        }
+        self.findings.push(SconeFinding {
+            service: Some(name.to_owned()),
+            path: path,
+            problem: problem,
+        });
+    }
+
+    /// Check the services in a file which uses the `scone` dialect.
+    fn check_service(&mut self, name: &str, service: &Service) {
+        // A `not_scone` which hasn't been interpolated yet might be true,
+        // so we only require `mrenclave` once we know it's false.
+        let in_enclave = match service.scone.not_scone {
+            Some(ref not_scone) => not_scone.value().ok() == Some(&false),
+            None => true,
+        };
+        if in_enclave && service.scone.mrenclave.is_none() {
+            self.add(name, "", SconeProblem::MissingMrenclave);
+        }
+
+        if service.scone.fspf_path.is_none() {
+            let fields = [("fspf_key", service.scone.fspf_key.is_some()),
+                          ("fspf_tag", service.scone.fspf_tag.is_some())];
+            for &(field, present) in &fields {
+                if present {
+                    let problem = SconeProblem::IncompleteFspf(field.to_owned());
+                    self.add(name, field, problem);
+                }
+            }
+        }
+    }
+
+    /// Report every SCONE field in a file which doesn't use the `scone`
+    /// dialect.
+    fn check_not_scone(&mut self, file: &File) {
+        for (name, service) in &file.services {
+            let fields = [("mrenclave", service.scone.mrenclave.is_some()),
+                          ("fspf_path", service.scone.fspf_path.is_some()),
+                          ("fspf_key", service.scone.fspf_key.is_some()),
+                          ("fspf_tag", service.scone.fspf_tag.is_some()),
+                          ("not_scone", service.scone.not_scone.is_some())];
+            for &(field, present) in &fields {
+                if present {
+                    let problem = SconeProblem::RequiresSconeDialect(field.to_owned());
+                    self.add(name, field, problem);
+                }
+            }
+        }
+        for (name, secret) in &file.secrets {
+            let fields = [("kind", secret.kind.is_some()),
+                          ("length", secret.length.is_some())];
+            for &(field, present) in &fields {
+                if present {
+                    self.findings.push(SconeFinding {
+                        service: None,
+                        path: format!("/secrets/{}/{}",
+                                      escape_pointer_segment(name),
+                                      field),
+                        problem: SconeProblem::RequiresSconeDialect(field.to_owned()),
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn scone_report_checks_invariants() {
+    use std::str::FromStr;
+    use super::value;
+
+    let mrenclave = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+    let yaml = format!(r#"---
+version: "3.1.scone"
+services:
+  db:
+    image: "postgres"
+    not_scone: "true"
+  web:
+    image: "example/web"
+    mrenclave: "{}"
+    fspf_tag: "0123456789abcdef0123456789abcdef"
+    secrets:
+      - "api_key"
+  cache:
+    image: "redis"
+    not_scone: "${{COMPOSE_YML_NOT_SCONE}}"
+  worker:
+    image: "example/worker"
+    fspf_path: "/fspf.pb"
+secrets:
+  api_key:
+    kind: "ascii"
+"#,
+                       mrenclave);
+    let file = File::from_str(&yaml).unwrap();
+    // We don't know whether `cache` runs in an enclave until `not_scone`
+    // is interpolated, so it isn't reported.
+    let findings: Vec<String> = file.check_scone()
+        .findings
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+    assert_eq!(findings,
+               vec!["/services/web/fspf_tag: fspf_tag must be used together with \
+                     fspf_path",
+                    "/services/worker: services without not_scone must have \
+                     mrenclave"]);
+
+    // Non-SCONE versions are rejected with a clear error when parsing...
+    let yaml = yaml.replace("3.1.scone", "3.1");
+    let err = File::from_str(&yaml).unwrap_err();
+    assert_eq!(err.to_string(),
+               "field /secrets/api_key/kind requires the scone dialect, but the \
+                file has version 3.1");
+
+    // ...and reported by the check if they're built by hand.
+    let mut file = File::default();
+    let mut service = Service::default();
+    service.scone.not_scone = Some(value(true));
+    file.services.insert("db".to_owned(), service);
+    let report = file.check_scone();
+    assert_eq!(report.findings,
+               vec![SconeFinding {
+                        service: Some("db".to_owned()),
+                        path: "/services/db/not_scone".to_owned(),
+                        problem: SconeProblem::RequiresSconeDialect("not_scone"
+                            .to_owned()),
+                    }]);
+}
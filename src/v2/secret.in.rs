@@ -16,20 +16,28 @@ pub struct Secret {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub external: Option<bool>,
 
-    /// The name of the Docker volume driver to use.  Defaults to
-    /// `"local"`.
+    /// The kind of value SCONE should generate for this secret.  Only
+    /// supported by the `scone` dialect.
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub kind: Option<String>,
-    /// The name of the Docker volume driver to use.  Defaults to
-    /// `"local"`.
+    pub kind: Option<RawOr<SecretKind>>,
+
+    /// The length of the generated value, which must lie within
+    /// `SecretKind::length_bounds`.  Defaults to `DEFAULT_SECRET_LENGTH`.
+    /// Only supported by the `scone` dialect.
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub length: Option<String>,
+    pub length: Option<RawOr<u32>>,
 
     /// Docker labels for this volume, specifying various sorts of
     /// custom metadata.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
+    #[serde(default, skip_serializing_if = "Map::is_empty",
             deserialize_with = "deserialize_map_or_key_value_list")]
-    pub labels: BTreeMap<String, RawOr<String>>,
+    pub labels: Map<String, RawOr<String>>,
+
+    /// The contents of `file`, once loaded by `inline_content`.  This is
+    /// never serialized, so loading a secret won't cause it to be written
+    /// out along with the rest of the file.
+    #[serde(default, skip_serializing, skip_deserializing)]
+    pub content: Option<SecretContent>,
 
     /// PRIVATE.  Mark this struct as having unknown fields for future
     /// compatibility.  This prevents direct construction and exhaustive
@@ -41,9 +49,264 @@ pub struct Secret {
 }
 
 derive_standard_impls_for!(Secret, {
-    file, external, kind, length, labels, _hidden
+    file, external, kind, length, labels, content, _hidden
 });
 
+/// The length of generated secrets which don't specify a `length`.
+pub const DEFAULT_SECRET_LENGTH: u32 = 32;
+
+/// The largest secret file we'll load, in bytes.  This is the same limit
+/// used by Docker swarm.
+pub const MAX_SECRET_SIZE: u64 = 500 * 1024;
+
+impl Secret {
+    /// Is this a secret whose value is generated, instead of being
+    /// external or loaded from a file?
+    pub fn is_generated(&self) -> bool {
+        self.kind.is_some() && self.file.is_none() && self.external != Some(true)
+    }
+
+    /// Check that `length` lies within the bounds allowed for `kind`.
+    /// `name` is the name of this secret.  Values which still need to be
+    /// interpolated can't be checked, so we skip them.
+    fn check_length(&self, name: &str) -> Result<()> {
+        let kind = match self.kind.as_ref().map(|kind| kind.value()) {
+            Some(Ok(kind)) => kind,
+            _ => return Ok(()),
+        };
+        let length = match self.length.as_ref().map(|length| length.value()) {
+            Some(Ok(&length)) => length,
+            _ => return Ok(()),
+        };
+        let (min, max) = kind.length_bounds();
+        if length < min || length > max {
+            let pointer = format!("/secrets/{}/length",
+                                  validate::escape_pointer_segment(name));
+            return Err(ErrorKind::SecretLengthOutOfRange(pointer, length, min, max)
+                .into());
+        }
+        Ok(())
+    }
+
+    /// Check that `file`, if present, exists and can't be read by every
+    /// user on the system.  Relative paths are looked up relative to
+    /// `base`.
+    pub fn check_file(&self, base: &Path) -> Result<()> {
+        let path = match self.file {
+            Some(ref file) => base.join(file),
+            None => return Ok(()),
+        };
+        let metadata = fs::metadata(&path)
+            .chain_err(|| ErrorKind::ReadFile(path.clone()))?;
+        if is_world_readable(&metadata) {
+            return Err(ErrorKind::InsecureSecretFile(path).into());
+        }
+        Ok(())
+    }
+
+    /// Load `content` from `file`, after checking it with `check_file`.
+    /// Files larger than `MAX_SECRET_SIZE` are rejected.  Unlike
+    /// `Config::inline_content`, we leave `file` alone, because `content`
+    /// is never serialized.
+    pub fn inline_content(&mut self, base: &Path) -> Result<()> {
+        self.check_file(base)?;
+        let path = match self.file {
+            Some(ref file) => base.join(file),
+            None => return Ok(()),
+        };
+        let mkerr = || ErrorKind::ReadFile(path.clone());
+        let f = fs::File::open(&path).chain_err(&mkerr)?;
+        // Check the size as we read, in case the file is growing.
+        let mut content = vec![];
+        io::Read::read_to_end(&mut io::Read::take(f, MAX_SECRET_SIZE + 1),
+                              &mut content)
+            .chain_err(&mkerr)?;
+        if content.len() as u64 > MAX_SECRET_SIZE {
+            let size = fs::metadata(&path)
+                .map(|metadata| metadata.len())
+                .unwrap_or(content.len() as u64);
+            let path = path.clone();
+            let limit = MAX_SECRET_SIZE;
+            return Err(ErrorKind::SecretFileTooLarge(path, size, limit).into());
+        }
+        self.content = Some(SecretContent::new(content));
+        Ok(())
+    }
+}
+
+/// Can every user on the system read the file described by `metadata`?
+#[cfg(unix)]
+fn is_world_readable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o004 != 0
+}
+
+/// Windows doesn't have Unix-style permissions, so we can't tell.
+#[cfg(not(unix))]
+fn is_world_readable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// The contents of a secret.  To keep secrets out of logs, the `Debug`
+/// and `Display` implementations never show the actual contents.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretContent(Vec<u8>);
+
+impl SecretContent {
+    /// Wrap the contents of a secret.
+    pub fn new(content: Vec<u8>) -> SecretContent {
+        SecretContent(content)
+    }
+
+    /// The contents of the secret.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Unwrap the contents of the secret.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl fmt::Debug for SecretContent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretContent(<redacted>)")
+    }
+}
+
+impl fmt::Display for SecretContent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<redacted>")
+    }
+}
+
+impl MergeOverride for SecretContent {}
+impl InterpolateAll for SecretContent {}
+
+/// A reference from a service to one of the secrets in the top-level
+/// `secrets` section, and where to mount it in the container.
+///
+/// ```
+/// use std::str::FromStr;
+/// use compose_yml::v2 as dc;
+///
+/// let secret = dc::ServiceSecret::from_str("db_password").unwrap();
+/// assert_eq!(secret.source, "db_password");
+/// assert_eq!(secret.target, None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceSecret {
+    /// The name of the secret in the top-level `secrets` section.
+    pub source: String,
+    /// The name of the file in `/run/secrets/` where the secret should be
+    /// mounted.  Defaults to `source`.
+    pub target: Option<String>,
+    /// The user ID which should own the mounted secret.
+    pub uid: Option<String>,
+    /// The group ID which should own the mounted secret.
+    pub gid: Option<String>,
+    /// The permissions of the mounted secret, such as `0o400`.
+    pub mode: Option<u32>,
+
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    pub _hidden: (),
+}
+
+impl ServiceSecret {
+    /// Refer to the secret named `source`, using the default mount
+    /// options.
+    pub fn new<S: Into<String>>(source: S) -> ServiceSecret {
+        ServiceSecret {
+            source: source.into(),
+            target: None,
+            uid: None,
+            gid: None,
+            mode: None,
+            _hidden: (),
+        }
+    }
+}
+
+impl_interpolatable_value!(ServiceSecret);
+
+/// The long syntax for a `ServiceSecret`, as a map.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServiceSecretLongSyntax {
+    /// The group ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gid: Option<String>,
+    /// The permissions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<u32>,
+    /// The name of the secret.
+    source: String,
+    /// The file name in `/run/secrets/`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    /// The user ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uid: Option<String>,
+}
+
+impl LongSyntax for ServiceSecret {
+    fn requires_long_syntax(&self) -> bool {
+        self.target.is_some() || self.uid.is_some() || self.gid.is_some() ||
+        self.mode.is_some()
+    }
+
+    fn serialize_long<S>(&self, serializer: &mut S) -> result::Result<(), S::Error>
+        where S: Serializer
+    {
+        ServiceSecretLongSyntax {
+                gid: self.gid.clone(),
+                mode: self.mode,
+                source: self.source.clone(),
+                target: self.target.clone(),
+                uid: self.uid.clone(),
+            }
+            .serialize(serializer)
+    }
+
+    fn deserialize_long<M>(visitor: M) -> result::Result<Self, M::Error>
+        where M: de::MapVisitor
+    {
+        let mut mvd = de::value::MapVisitorDeserializer::new(visitor);
+        let long = ServiceSecretLongSyntax::deserialize(&mut mvd)?;
+        Ok(ServiceSecret {
+            source: long.source,
+            target: long.target,
+            uid: long.uid,
+            gid: long.gid,
+            mode: long.mode,
+            _hidden: (),
+        })
+    }
+}
+
+/// Formats a `ServiceSecret` using the short syntax, which only includes
+/// `source`.
+impl fmt::Display for ServiceSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", &self.source)
+    }
+}
+
+impl FromStr for ServiceSecret {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Err(Error::invalid_value("secret", s));
+        }
+        Ok(ServiceSecret::new(s))
+    }
+}
 
 #[test]
 fn empty_volume_can_be_converted_from_and_to_yaml() {
@@ -64,6 +327,21 @@ fn volume_with_driver_can_be_converted_from_and_to_yaml() {
     assert_roundtrip!(Secret, yaml);
 }
 
+#[test]
+fn generated_secret_can_be_converted_from_and_to_yaml() {
+    let yaml = r#"---
+"kind": "hex"
+"length": "16"
+"#;
+    assert_roundtrip!(Secret, yaml);
+    let secret: Secret = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(secret.kind, Some(value(SecretKind::Hex)));
+    assert_eq!(secret.length, Some(value(16)));
+    assert!(secret.is_generated());
+    assert!(serde_yaml::from_str::<Secret>("---\n\"kind\": \"asci\"\n").is_err());
+    assert!(serde_yaml::from_str::<Secret>("---\n\"length\": \"abc\"\n").is_err());
+}
+
 #[test]
 fn external_volume_can_be_converted_from_and_to_yaml() {
     let yaml = r#"---
@@ -71,3 +349,67 @@ fn external_volume_can_be_converted_from_and_to_yaml() {
 "#;
     assert_roundtrip!(Secret, yaml);
 }
+
+#[test]
+fn service_secrets_support_short_and_long_syntax() {
+    let yaml = r#"---
+"secrets":
+- "api_key"
+- "mode": 256
+  "source": "db_password"
+  "target": "password"
+"#;
+    assert_roundtrip!(Service, yaml);
+
+    let service: Service = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(service.secrets[0].value().unwrap(),
+               &ServiceSecret::new("api_key"));
+    let password = service.secrets[1].value().unwrap();
+    assert_eq!(password.source, "db_password");
+    assert_eq!(password.mode, Some(0o400));
+
+    let err = serde_yaml::from_str::<Service>("---\n\"secrets\": [\"\"]\n")
+        .unwrap_err();
+    assert!(err.to_string().contains("secret"));
+}
+
+#[cfg(unix)]
+#[test]
+fn secret_files_are_checked_and_inlined() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = TempDir::new("compose_yml_secret_test");
+    let dir = tmp.path();
+    let path = dir.join("secret.txt");
+    let mut f = fs::File::create(&path).unwrap();
+    io::Write::write_all(&mut f, b"s3cret").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+    let mut secret = Secret {
+        file: Some("secret.txt".to_owned()),
+        ..Default::default()
+    };
+    secret.inline_content(dir).unwrap();
+    let content = secret.content.clone().unwrap();
+    assert_eq!(content.as_bytes(), b"s3cret");
+    assert_eq!(content.to_string(), "<redacted>");
+    assert!(!format!("{:?}", secret).contains("s3cret"));
+    // The contents are never written out.
+    assert!(!serde_yaml::to_string(&secret).unwrap().contains("s3cret"));
+
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+    let err = secret.check_file(dir).unwrap_err();
+    assert!(err.to_string().contains("readable by all users"));
+
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+    let f = fs::File::create(&path).unwrap();
+    f.set_len(MAX_SECRET_SIZE + 1).unwrap();
+    let err = secret.inline_content(dir).unwrap_err();
+    assert!(err.to_string().contains("limit"));
+
+    let missing = Secret {
+        file: Some("no_such_secret".to_owned()),
+        ..Default::default()
+    };
+    assert!(missing.check_file(dir).is_err());
+}
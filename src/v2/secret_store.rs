@@ -0,0 +1,195 @@
+//! Generating values for SCONE-style secrets locally, so that files which
+//! use generated secrets can be run and tested without a SCONE CAS.
+
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use errors::*;
+use super::{DEFAULT_SECRET_LENGTH, File, SecretContent, SecretKind};
+
+/// The characters used by `hex` secrets.
+const HEX_DIGITS: &'static [u8] = b"0123456789abcdef";
+
+/// Generated values for the secrets in a file, indexed by secret name.
+///
+/// ```
+/// extern crate compose_yml;
+/// extern crate rand;
+///
+/// use compose_yml::v2 as dc;
+/// use rand::{SeedableRng, XorShiftRng};
+/// use std::str::FromStr;
+///
+/// # fn main() {
+/// let file = dc::File::from_str(r#"---
+/// version: "3.1.scone"
+/// services:
+///   web:
+///     image: "nginx"
+/// secrets:
+///   token:
+///     kind: "hex"
+///     length: "16"
+/// "#).unwrap();
+/// let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+/// let store = dc::LocalSecretStore::generate(&file, &mut rng).unwrap();
+/// assert_eq!(store.get("token").unwrap().as_bytes().len(), 16);
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalSecretStore {
+    /// Our secrets, sorted by name.
+    secrets: BTreeMap<String, SecretContent>,
+}
+
+impl LocalSecretStore {
+    /// Create an empty store.
+    pub fn new() -> LocalSecretStore {
+        Default::default()
+    }
+
+    /// Generate a value for every generated secret in `file`, using `rng`.
+    /// Secrets which are external or loaded from a file are skipped.  We
+    /// visit secrets in sorted order, so a seeded `rng` will always
+    /// produce the same values.
+    pub fn generate<R>(file: &File, rng: &mut R) -> Result<LocalSecretStore>
+        where R: Rng
+    {
+        let sorted: BTreeMap<_, _> = file.secrets.iter().collect();
+        let mut store = LocalSecretStore::new();
+        for (name, secret) in sorted {
+            if !secret.is_generated() {
+                continue;
+            }
+            secret.check_length(name)?;
+            let kind = secret.kind
+                .as_ref()
+                .expect("generated secret should have a kind")
+                .value()?;
+            let length = match secret.length {
+                Some(ref length) => *length.value()?,
+                None => DEFAULT_SECRET_LENGTH,
+            };
+            store.insert(name, generate_value(kind, length, rng));
+        }
+        Ok(store)
+    }
+
+    /// Record the value of the secret `name`.
+    pub fn insert(&mut self, name: &str, content: SecretContent) {
+        self.secrets.insert(name.to_owned(), content);
+    }
+
+    /// Look up the value of the secret `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&SecretContent> {
+        self.secrets.get(name)
+    }
+
+    /// The number of secrets in the store.
+    pub fn len(&self) -> usize {
+        self.secrets.len()
+    }
+
+    /// Is the store empty?
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+
+    /// Write each secret to a file in `dir` with the same name as the
+    /// secret.  On Unix, the files can only be read by their owner.
+    pub fn write_to_dir<P>(&self, dir: P) -> Result<()>
+        where P: AsRef<Path>
+    {
+        for (name, content) in &self.secrets {
+            let path = dir.as_ref().join(name);
+            let mkerr = || ErrorKind::WriteFile(path.clone());
+            let mut f = create_private_file(&path).chain_err(&mkerr)?;
+            f.write_all(content.as_bytes()).chain_err(&mkerr)?;
+        }
+        Ok(())
+    }
+}
+
+/// Generate a random value of the specified `kind` and `length`.
+fn generate_value<R>(kind: &SecretKind, length: u32, rng: &mut R) -> SecretContent
+    where R: Rng
+{
+    let length = length as usize;
+    let bytes: Vec<u8> = match *kind {
+        // Printable characters from `!` to `~`.
+        SecretKind::Ascii => (0..length).map(|_| rng.gen_range(0x21, 0x7f)).collect(),
+        SecretKind::Binary => (0..length).map(|_| rng.gen::<u8>()).collect(),
+        SecretKind::Hex => {
+            (0..length)
+                .map(|_| HEX_DIGITS[rng.gen_range(0, HEX_DIGITS.len())])
+                .collect()
+        }
+    };
+    SecretContent::new(bytes)
+}
+
+/// Create a file which can only be read by its owner.
+#[cfg(unix)]
+fn create_private_file(path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+}
+
+/// Create a file.  Windows doesn't have Unix-style permissions.
+#[cfg(not(unix))]
+fn create_private_file(path: &Path) -> io::Result<fs::File> {
+    fs::File::create(path)
+}
+
+#[test]
+fn generated_secrets_are_deterministic() {
+    use rand::{SeedableRng, XorShiftRng};
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "3.1.scone"
+services:
+  web:
+    image: "nginx"
+secrets:
+  api_key:
+    kind: "ascii"
+  master_key:
+    kind: "binary"
+    length: "64"
+  session:
+    kind: "hex"
+    length: "24"
+  tls_cert:
+    file: "./cert.pem"
+  registry:
+    external: true
+"#)
+        .unwrap();
+    let generate = |seed| {
+        LocalSecretStore::generate(&file, &mut XorShiftRng::from_seed(seed)).unwrap()
+    };
+    let store = generate([1, 2, 3, 4]);
+    assert_eq!(store.len(), 3);
+    assert!(store.get("tls_cert").is_none());
+    assert!(store.get("registry").is_none());
+
+    let api_key = store.get("api_key").unwrap().as_bytes();
+    assert_eq!(api_key.len(), DEFAULT_SECRET_LENGTH as usize);
+    assert!(api_key.iter().all(|&b| b > b' ' && b < 0x7f));
+    assert_eq!(store.get("master_key").unwrap().as_bytes().len(), 64);
+    let session = store.get("session").unwrap().as_bytes();
+    assert_eq!(session.len(), 24);
+    assert!(session.iter().all(|b| HEX_DIGITS.contains(b)));
+
+    assert_eq!(store, generate([1, 2, 3, 4]));
+    assert!(store != generate([5, 6, 7, 8]));
+}
@@ -3,215 +3,193 @@
 // of how this works.
 
 /// A service which will be managed by `docker-compose`.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
-#[serde(deny_unknown_fields)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct Service {
     /// How to build an image for this service.
-    #[serde(default, skip_serializing_if = "Option::is_none",
-            serialize_with = "serialize_opt_string_or_struct",
-            deserialize_with = "deserialize_opt_string_or_struct")]
     pub build: Option<Build>,
 
     /// A list of capability names to grant to this container.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub cap_add: Vec<RawOr<String>>,
 
     /// A list of capability names to revoke from this container.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub cap_drop: Vec<RawOr<String>>,
 
     /// The command-line to run when launching the container.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub command: Option<CommandLine>,
 
     /// The name of an optional parent cgroup.  (Mysterious.)
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub cgroup_parent: Option<RawOr<String>>,
 
+    /// Configs from the top-level `configs` section which should be
+    /// mounted into this container.
+    pub configs: Vec<RawOr<ServiceConfig>>,
+
     /// An optional (global, non-scalable) container name.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub container_name: Option<RawOr<String>>,
 
     /// A list of devices to map into this container.
     ///
     /// TODO LOW: Add DevicePermissions and make both host and container
     /// mandatory.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub devices: Vec<RawOr<AliasedName>>,
 
-    /// A list of other containers to start first.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub depends_on: Vec<RawOr<String>>,
+    /// Other services to start first.  This may be written as either a
+    /// list of service names or, in version 2.1, a map from service names
+    /// to the conditions we should wait for.  Service names may be
+    /// interpolated.
+    ///
+    /// This used to be a `Vec<RawOr<String>>`.  To upgrade, replace
+    /// `depends_on.push(name)` with `depends_on.insert(name,
+    /// Dependency::default())`, and iterate over `depends_on.keys()`.
+    pub depends_on: Map<RawOr<String>, Dependency>,
+
+    /// How to deploy this service to a swarm.  Only supported by version
+    /// 3 files.
+    pub deploy: Option<Deploy>,
 
     /// DNS servers.
-    #[serde(default, skip_serializing_if = "Vec::is_empty",
-            deserialize_with = "deserialize_item_or_list")]
     pub dns: Vec<RawOr<String>>,
 
     /// Domains to search for hostnames.
-    #[serde(default, skip_serializing_if = "Vec::is_empty",
-            deserialize_with = "deserialize_item_or_list")]
     pub dns_search: Vec<RawOr<String>>,
 
     /// Locations to mount temporary file systems.
-    #[serde(default, skip_serializing_if = "Vec::is_empty",
-            deserialize_with = "deserialize_item_or_list")]
     pub tmpfs: Vec<RawOr<String>>,
 
     /// The entrypoint for the container (wraps `command`, basically).
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub entrypoint: Option<CommandLine>,
 
     /// Environment files used to supply variables to the container.  Note
     /// that this is `env_file` in the underlying Docker format, but the
     /// singular form looks weird at the API level.
-    #[serde(rename = "env_file",
-            default, skip_serializing_if = "Vec::is_empty",
-            deserialize_with = "deserialize_item_or_list")]
     pub env_files: Vec<RawOr<PathBuf>>,
 
     /// Environment variables and values to supply to the container.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
-            deserialize_with = "deserialize_map_or_key_value_list")]
-    pub environment: BTreeMap<String, RawOr<String>>,
+    pub environment: Map<String, RawOr<String>>,
 
     /// Expose a list of ports to any containers that link to us.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub expose: Vec<RawOr<String>>,
 
     /// Extend another service, either in this file or another.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub extends: Option<Extends>,
 
     /// Links to external containers.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub external_links: Vec<RawOr<AliasedName>>,
 
     /// Mappings for extra hosts in /etc/hosts.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub extra_hosts: Vec<RawOr<HostMapping>>,
 
+    /// How Docker should check whether this container is healthy.
+    pub healthcheck: Option<Healthcheck>,
+
     /// The name of the image to build or pull for this container.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub image: Option<RawOr<Image>>,
 
+    /// Should we run an init process inside the container, which forwards
+    /// signals and reaps zombie processes?
+    pub init: Option<bool>,
+
+    /// The container isolation technology to use.  This is mostly useful
+    /// on Windows, where it may be `default`, `process` or `hyperv`.
+    pub isolation: Option<RawOr<String>>,
+
     /// Docker labels for this container, specifying various sorts of
     /// custom metadata.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
-            deserialize_with = "deserialize_map_or_key_value_list")]
-    pub labels: BTreeMap<String, RawOr<String>>,
+    pub labels: Map<String, RawOr<String>>,
 
     /// Links to other services in this file.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub links: Vec<RawOr<AliasedName>>,
 
     /// Logging options for this container.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub logging: Option<Logging>,
 
     /// What networking mode should we use?
-    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub network_mode: Option<RawOr<NetworkMode>>,
 
     /// Networks to which this container is attached.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
-            deserialize_with = "deserialize_map_or_default_list")]
-    pub networks: BTreeMap<String, NetworkInterface>,
+    pub networks: Map<String, NetworkInterface>,
 
     /// What PID namespacing mode should we use?
-    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pid: Option<RawOr<PidMode>>,
 
     /// What ports do we want to map to our host system?
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub ports: Vec<RawOr<PortMapping>>,
 
+    /// The profiles in which this service should be started.  Services
+    /// without profiles are always started.  Only supported by the Compose
+    /// Spec.
+    pub profiles: Vec<RawOr<String>>,
+
+    /// Secrets from the top-level `secrets` section which should be
+    /// mounted into this container.
+    pub secrets: Vec<RawOr<ServiceSecret>>,
+
     /// Security options for AppArmor or SELinux.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub security_opt: Vec<RawOr<String>>,
 
     /// The name of the Unix signal which will be sent to stop this
     /// container.  Defaults to SIGTERM if not specified.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub stop_signal: Option<RawOr<String>>,
 
     // TODO LOW: ulimits
 
-    // TODO LOW: isolation (not documented at this point).
-
     /// Volumes associated with this service.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub volumes: Vec<RawOr<VolumeMount>>,
 
     /// Other places to get volumes from.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub volumes_from: Vec<RawOr<VolumesFrom>>,
 
     /// This will only apply to volumes with no host path and no mapping to
     /// a volume declared under the `volumes` key at the top level of this
     /// file.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub volume_driver: Option<RawOr<String>>,
 
     /// The relative number of CPU shares to give to this container.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub cpu_shares: Option<u32>,
 
     /// Limit the CFS CPU quota.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub cpu_quota: Option<u32>,
 
     // TODO LOW: cpuset
 
     /// The domain name to use for this container.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub domainname: Option<RawOr<String>>,
 
     /// The hostname to use for this container.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub hostname: Option<RawOr<String>>,
 
     /// What IPC namespacing mode should we use?
-    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub ipc: Option<RawOr<IpcMode>>,
 
     /// The MAC address to use for this container's network interface.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub mac_address: Option<RawOr<String>>,
 
     /// The maximum amount of memory which this container may use, in
     /// bytes.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mem_limit: Option<RawOr<MemorySize>>,
 
     /// The maximum amount of swap space which this container may use, in
     /// bytes.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub memswap_limit: Option<RawOr<MemorySize>>,
 
     /// The MAC address to use for this container's network interface.
-    #[serde(default, skip_serializing_if = "is_false")]
     pub privileged: bool,
 
     // TODO LOW: read_only (what is this, anyway?)
 
     /// What should we do when the container exits?
-    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub restart: Option<RawOr<RestartMode>>,
 
     /// The amount of shared memory to allocate for this container, in
     /// bytes.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub shm_size: Option<RawOr<MemorySize>>,
 
     /// Should STDIN be left open when running the container?  Corresponds
     /// to `docker run -i`.
-    #[serde(default, skip_serializing_if = "is_false")]
     pub stdin_open: bool,
 
     /// Should a TTY be be allocated for the container?  Corresponds to
     /// `docker run -t`.
-    #[serde(default, skip_serializing_if = "is_false")]
     pub tty: bool,
 
     /// The user name (or UID) of the user under which to execute the
@@ -219,63 +197,44 @@ pub struct Service {
     /// `:gid` to specific the group or group ID.
     ///
     /// TODO LOW: Parse out optional group field separately?
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<RawOr<String>>,
 
     /// The working directory to use for this container.  This is a string,
     /// because on Windows, it will use a different path representation
     /// than the host OS.
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub working_dir: Option<RawOr<String>>,
 
     /// Negative scores make a container less likely to be killed when the
     /// kernel can't find memory; positive scores make it more likely.
-    #[serde(skip_serializing_if = "Option::is_none")]
     oom_score_adj: Option<i16>,
 
     /// Extra groups to grant to the user.
-    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     group_add: Vec<String>,
 
-    /// Bogus documentation
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub mrenclave: Option<String>,
-    /// Bogus documentation
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub fspf_path: Option<String>,
-    /// Bogus documentation
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub fspf_key: Option<String>,
-    /// Bogus documentation
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub fspf_tag: Option<String>,
-    /// Bogus documentation
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub not_scone: Option<String>,
+    /// Settings for services which run inside SCONE enclaves.  These
+    /// are only supported by the `scone` dialect, and are written as
+    /// separate keys on the service.
+    pub scone: SconeConfig,
 
     /// PRIVATE.  Mark this struct as having unknown fields for future
     /// compatibility.  This prevents direct construction and exhaustive
     /// matching.  This needs to be be public because of
     /// http://stackoverflow.com/q/39277157/12089
     #[doc(hidden)]
-    #[serde(default, skip_serializing, skip_deserializing)]
     pub _hidden: (),
 }
 
 derive_standard_impls_for!(Service, {
-    fspf_path,
-    fspf_key,
-    fspf_tag,
-    mrenclave,
-    not_scone,
     build,
     cap_add,
     cap_drop,
     command,
     cgroup_parent,
+    configs,
     container_name,
     devices,
     depends_on,
+    deploy,
     dns,
     dns_search,
     tmpfs,
@@ -286,7 +245,10 @@ derive_standard_impls_for!(Service, {
     extends,
     external_links,
     extra_hosts,
+    healthcheck,
     image,
+    init,
+    isolation,
     labels,
     links,
     logging,
@@ -294,6 +256,8 @@ derive_standard_impls_for!(Service, {
     networks,
     pid,
     ports,
+    profiles,
+    secrets,
     security_opt,
     stop_signal,
     volumes,
@@ -316,19 +280,313 @@ derive_standard_impls_for!(Service, {
     working_dir,
     oom_score_adj,
     group_add,
+    scone,
     _hidden
 });
 
+/// The fields of a `Service`, as they appear in a file.  serde can't
+/// flatten one struct into another, so we (de)serialize services using
+/// this struct, and move the SCONE keys into `Service::scone` ourselves.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServiceFields {
+    #[serde(default, skip_serializing_if = "Option::is_none",
+            serialize_with = "serialize_opt_string_or_struct",
+            deserialize_with = "deserialize_opt_string_or_struct")]
+    build: Option<Build>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cap_add: Vec<RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cap_drop: Vec<RawOr<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<CommandLine>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cgroup_parent: Option<RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty",
+            serialize_with = "serialize_string_or_struct_list",
+            deserialize_with = "deserialize_string_or_struct_list")]
+    configs: Vec<RawOr<ServiceConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    container_name: Option<RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    devices: Vec<RawOr<AliasedName>>,
+    #[serde(default, skip_serializing_if = "Map::is_empty",
+            serialize_with = "serialize_map_or_default_list",
+            deserialize_with = "deserialize_map_or_default_list")]
+    depends_on: Map<RawOr<String>, Dependency>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deploy: Option<Deploy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty",
+            deserialize_with = "deserialize_item_or_list")]
+    dns: Vec<RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty",
+            deserialize_with = "deserialize_item_or_list")]
+    dns_search: Vec<RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty",
+            deserialize_with = "deserialize_item_or_list")]
+    tmpfs: Vec<RawOr<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entrypoint: Option<CommandLine>,
+    #[serde(rename = "env_file",
+            default, skip_serializing_if = "Vec::is_empty",
+            deserialize_with = "deserialize_item_or_list")]
+    env_files: Vec<RawOr<PathBuf>>,
+    #[serde(default, skip_serializing_if = "Map::is_empty",
+            deserialize_with = "deserialize_map_or_key_value_list")]
+    environment: Map<String, RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    expose: Vec<RawOr<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extends: Option<Extends>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    external_links: Vec<RawOr<AliasedName>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extra_hosts: Vec<RawOr<HostMapping>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    healthcheck: Option<Healthcheck>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<RawOr<Image>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    init: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    isolation: Option<RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Map::is_empty",
+            deserialize_with = "deserialize_map_or_key_value_list")]
+    labels: Map<String, RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    links: Vec<RawOr<AliasedName>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    logging: Option<Logging>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    network_mode: Option<RawOr<NetworkMode>>,
+    #[serde(default, skip_serializing_if = "Map::is_empty",
+            deserialize_with = "deserialize_map_or_default_list")]
+    networks: Map<String, NetworkInterface>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pid: Option<RawOr<PidMode>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty",
+            serialize_with = "serialize_string_or_struct_list",
+            deserialize_with = "deserialize_string_or_struct_list")]
+    ports: Vec<RawOr<PortMapping>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    profiles: Vec<RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty",
+            serialize_with = "serialize_string_or_struct_list",
+            deserialize_with = "deserialize_string_or_struct_list")]
+    secrets: Vec<RawOr<ServiceSecret>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    security_opt: Vec<RawOr<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_signal: Option<RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty",
+            serialize_with = "serialize_string_or_struct_list",
+            deserialize_with = "deserialize_string_or_struct_list")]
+    volumes: Vec<RawOr<VolumeMount>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    volumes_from: Vec<RawOr<VolumesFrom>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volume_driver: Option<RawOr<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_shares: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_quota: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    domainname: Option<RawOr<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hostname: Option<RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ipc: Option<RawOr<IpcMode>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac_address: Option<RawOr<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mem_limit: Option<RawOr<MemorySize>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    memswap_limit: Option<RawOr<MemorySize>>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    privileged: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    restart: Option<RawOr<RestartMode>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shm_size: Option<RawOr<MemorySize>>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    stdin_open: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    tty: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<RawOr<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    working_dir: Option<RawOr<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    oom_score_adj: Option<i16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    group_add: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mrenclave: Option<RawOr<MrEnclave>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fspf_path: Option<RawOr<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fspf_key: Option<RawOr<FspfKey>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fspf_tag: Option<RawOr<FspfTag>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    not_scone: Option<RawOr<bool>>,
+}
+
+impl Serialize for Service {
+    fn serialize<S>(&self, serializer: &mut S) -> result::Result<(), S::Error>
+        where S: Serializer
+    {
+        let service = self.clone();
+        ServiceFields {
+                build: service.build,
+                cap_add: service.cap_add,
+                cap_drop: service.cap_drop,
+                command: service.command,
+                cgroup_parent: service.cgroup_parent,
+                configs: service.configs,
+                container_name: service.container_name,
+                devices: service.devices,
+                depends_on: service.depends_on,
+                deploy: service.deploy,
+                dns: service.dns,
+                dns_search: service.dns_search,
+                tmpfs: service.tmpfs,
+                entrypoint: service.entrypoint,
+                env_files: service.env_files,
+                environment: service.environment,
+                expose: service.expose,
+                extends: service.extends,
+                external_links: service.external_links,
+                extra_hosts: service.extra_hosts,
+                healthcheck: service.healthcheck,
+                image: service.image,
+                init: service.init,
+                isolation: service.isolation,
+                labels: service.labels,
+                links: service.links,
+                logging: service.logging,
+                network_mode: service.network_mode,
+                networks: service.networks,
+                pid: service.pid,
+                ports: service.ports,
+                profiles: service.profiles,
+                secrets: service.secrets,
+                security_opt: service.security_opt,
+                stop_signal: service.stop_signal,
+                volumes: service.volumes,
+                volumes_from: service.volumes_from,
+                volume_driver: service.volume_driver,
+                cpu_shares: service.cpu_shares,
+                cpu_quota: service.cpu_quota,
+                domainname: service.domainname,
+                hostname: service.hostname,
+                ipc: service.ipc,
+                mac_address: service.mac_address,
+                mem_limit: service.mem_limit,
+                memswap_limit: service.memswap_limit,
+                privileged: service.privileged,
+                restart: service.restart,
+                shm_size: service.shm_size,
+                stdin_open: service.stdin_open,
+                tty: service.tty,
+                user: service.user,
+                working_dir: service.working_dir,
+                oom_score_adj: service.oom_score_adj,
+                group_add: service.group_add,
+                mrenclave: service.scone.mrenclave,
+                fspf_path: service.scone.fspf_path,
+                fspf_key: service.scone.fspf_key,
+                fspf_tag: service.scone.fspf_tag,
+                not_scone: service.scone.not_scone,
+            }
+            .serialize(serializer)
+    }
+}
+
+impl Deserialize for Service {
+    fn deserialize<D>(deserializer: &mut D) -> result::Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let fields = ServiceFields::deserialize(deserializer)?;
+        Ok(Service {
+            build: fields.build,
+            cap_add: fields.cap_add,
+            cap_drop: fields.cap_drop,
+            command: fields.command,
+            cgroup_parent: fields.cgroup_parent,
+            configs: fields.configs,
+            container_name: fields.container_name,
+            devices: fields.devices,
+            depends_on: fields.depends_on,
+            deploy: fields.deploy,
+            dns: fields.dns,
+            dns_search: fields.dns_search,
+            tmpfs: fields.tmpfs,
+            entrypoint: fields.entrypoint,
+            env_files: fields.env_files,
+            environment: fields.environment,
+            expose: fields.expose,
+            extends: fields.extends,
+            external_links: fields.external_links,
+            extra_hosts: fields.extra_hosts,
+            healthcheck: fields.healthcheck,
+            image: fields.image,
+            init: fields.init,
+            isolation: fields.isolation,
+            labels: fields.labels,
+            links: fields.links,
+            logging: fields.logging,
+            network_mode: fields.network_mode,
+            networks: fields.networks,
+            pid: fields.pid,
+            ports: fields.ports,
+            profiles: fields.profiles,
+            secrets: fields.secrets,
+            security_opt: fields.security_opt,
+            stop_signal: fields.stop_signal,
+            volumes: fields.volumes,
+            volumes_from: fields.volumes_from,
+            volume_driver: fields.volume_driver,
+            cpu_shares: fields.cpu_shares,
+            cpu_quota: fields.cpu_quota,
+            domainname: fields.domainname,
+            hostname: fields.hostname,
+            ipc: fields.ipc,
+            mac_address: fields.mac_address,
+            mem_limit: fields.mem_limit,
+            memswap_limit: fields.memswap_limit,
+            privileged: fields.privileged,
+            restart: fields.restart,
+            shm_size: fields.shm_size,
+            stdin_open: fields.stdin_open,
+            tty: fields.tty,
+            user: fields.user,
+            working_dir: fields.working_dir,
+            oom_score_adj: fields.oom_score_adj,
+            group_add: fields.group_add,
+            scone: SconeConfig {
+                mrenclave: fields.mrenclave,
+                fspf_path: fields.fspf_path,
+                fspf_key: fields.fspf_key,
+                fspf_tag: fields.fspf_tag,
+                not_scone: fields.not_scone,
+                _hidden: (),
+            },
+            _hidden: (),
+        })
+    }
+}
+
 impl Service {
     /// Inline all our external resources, such as `env_files`, looking up
     /// paths relative to `base`.
     pub fn inline_all(&mut self, base: &Path) -> Result<()> {
-        let mut new_env = BTreeMap::new();
+        let mut new_env = Map::new();
         for rel_path in &self.env_files {
             let env_file = EnvFile::load(&base.join(&rel_path.value()?))?;
-            new_env.append(&mut env_file.to_environment()?);
+            new_env.extend(env_file.to_environment()?);
         }
-        new_env.append(&mut self.environment.clone());
+        new_env.extend(self.environment.clone());
         self.environment = new_env;
         self.env_files.clear();
         Ok(())
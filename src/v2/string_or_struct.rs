@@ -7,6 +7,8 @@ use std::fmt::Display;
 use std::marker::PhantomData;
 use std::str::FromStr;
 
+use super::interpolation::{InterpolatableValue, RawOr, raw, value};
+
 /// Handle a value which may either a struct that deserializes as type `T`,
 /// or a bare string that can be turned into a type `T` using
 /// `FromStr::from_str`.  We do this in a clever way that allows us to be
@@ -144,3 +146,100 @@ pub fn serialize_opt_string_or_struct<T, S>(value: &Option<T>,
         Some(ref v) => serializer.serialize_some(Wrap(v)),
     }
 }
+
+/// Values which have a compact string syntax, plus a "long syntax" map
+/// which supports additional options.  Ports and volumes work this way as
+/// of version 3.2 of the file format.
+pub trait LongSyntax: InterpolatableValue + Sized {
+    /// Does this value use any options which can only be represented
+    /// using the long syntax?  If not, we write the string syntax.
+    fn requires_long_syntax(&self) -> bool;
+
+    /// Serialize this value using the long syntax.
+    fn serialize_long<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+        where S: Serializer;
+
+    /// Deserialize a value written using the long syntax.
+    fn deserialize_long<M>(visitor: M) -> Result<Self, M::Error>
+        where M: de::MapVisitor;
+}
+
+/// A single entry in a list of values supporting `LongSyntax`.
+struct LongSyntaxItem<T: LongSyntax>(RawOr<T>);
+
+impl<T: LongSyntax> Deserialize for LongSyntaxItem<T> {
+    fn deserialize<D>(d: &mut D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        /// Declare an internal visitor type to handle our input.
+        struct LongSyntaxItemVisitor<T>(PhantomData<T>);
+
+        impl<T: LongSyntax> de::Visitor for LongSyntaxItemVisitor<T> {
+            type Value = LongSyntaxItem<T>;
+
+            fn visit_str<E>(&mut self, s: &str) -> Result<Self::Value, E>
+                where E: de::Error
+            {
+                raw(s).map(LongSyntaxItem).map_err(|err| {
+                    de::Error::custom(format!("{}", err))
+                })
+            }
+
+            fn visit_u64<E>(&mut self, n: u64) -> Result<Self::Value, E>
+                where E: de::Error
+            {
+                // Bare port numbers are often written without quotes.
+                self.visit_str(&n.to_string())
+            }
+
+            fn visit_map<M>(&mut self, visitor: M) -> Result<Self::Value, M::Error>
+                where M: de::MapVisitor
+            {
+                T::deserialize_long(visitor).map(|v| LongSyntaxItem(value(v)))
+            }
+        }
+
+        d.deserialize(LongSyntaxItemVisitor(PhantomData))
+    }
+}
+
+/// Deserialize a list in which each entry may use either the string
+/// syntax or the long syntax.
+pub fn deserialize_string_or_struct_list<T, D>(d: &mut D)
+                                               -> Result<Vec<RawOr<T>>, D::Error>
+    where T: LongSyntax,
+          D: Deserializer
+{
+    let items: Vec<LongSyntaxItem<T>> = Deserialize::deserialize(d)?;
+    Ok(items.into_iter().map(|LongSyntaxItem(v)| v).collect())
+}
+
+/// Serialize a list of values, using the string syntax for each entry
+/// unless it requires the long syntax.
+#[cfg_attr(feature="clippy", allow(ptr_arg))]
+pub fn serialize_string_or_struct_list<T, S>(values: &Vec<RawOr<T>>,
+                                             serializer: &mut S)
+                                             -> Result<(), S::Error>
+    where T: LongSyntax,
+          S: Serializer
+{
+    /// A wrapper which overrides how each entry is serialized, as in
+    /// `serialize_opt_string_or_struct`.
+    struct Wrap<'a, T>(&'a RawOr<T>) where T: 'a + LongSyntax;
+
+    impl<'a, T> Serialize for Wrap<'a, T>
+        where T: 'a + LongSyntax
+    {
+        fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+            where S: Serializer
+        {
+            match self.0.value() {
+                Ok(v) if v.requires_long_syntax() => v.serialize_long(serializer),
+                _ => self.0.serialize(serializer),
+            }
+        }
+    }
+
+    let wrapped: Vec<Wrap<T>> = values.iter().map(Wrap).collect();
+    wrapped.serialize(serializer)
+}
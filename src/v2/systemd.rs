@@ -0,0 +1,372 @@
+//! Generating systemd `.service` units which run each service directly
+//! with `docker` or `podman`, for single-host machines which don't have
+//! `docker-compose` installed.
+
+use std::fmt;
+use std::mem;
+
+use errors::*;
+use super::{DependencyCondition, DockerRunCommand, File, HostVolume, RestartMode,
+            Service, value};
+use super::validate::escape_pointer_segment;
+
+/// The container engine we use by default.
+const DEFAULT_EXECUTABLE: &'static str = "/usr/bin/docker";
+
+/// A systemd unit which runs a single service.
+///
+/// ```
+/// use std::str::FromStr;
+/// use compose_yml::v2 as dc;
+///
+/// let file = dc::File::from_str(r#"---
+/// version: "2"
+/// services:
+///   web:
+///     image: "nginx"
+///     restart: "always"
+/// "#).unwrap();
+/// let web = file.services.get("web").unwrap();
+/// let unit = dc::SystemdUnit::for_service("web", web).unwrap();
+/// assert_eq!(unit.name, "web.service");
+/// assert_eq!(unit.restart, Some("always".to_owned()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemdUnit {
+    /// The file name of the unit, such as `web.service`.
+    pub name: String,
+    /// A human-readable description of the unit.
+    pub description: String,
+    /// Units which must be started before this one.
+    pub after: Vec<String>,
+    /// Units which must be running for this one to run.
+    pub requires: Vec<String>,
+    /// A command to run before starting the container, which removes any
+    /// container left over from a previous run.  Failures are ignored.
+    pub exec_start_pre: Vec<String>,
+    /// The command which runs the container in the foreground.
+    pub exec_start: Vec<String>,
+    /// The command which stops the container.
+    pub exec_stop: Vec<String>,
+    /// When systemd should restart the container, such as `on-failure`.
+    pub restart: Option<String>,
+    /// The period over which systemd counts starts of the container.
+    /// This is `infinity` when `start_limit_burst` is set, so that systemd
+    /// counts every start for the lifetime of the unit, as `on-failure:N`
+    /// does, and `0` when the container should be restarted forever, which
+    /// turns off systemd's default limit of 5 starts in 10 seconds.
+    pub start_limit_interval: Option<String>,
+    /// How many times systemd may start the container, counting the first
+    /// start, before giving up.  Unlike `docker`, systemd also counts starts
+    /// requested by hand.
+    pub start_limit_burst: Option<u32>,
+    /// The signal systemd uses to stop the container.
+    pub kill_signal: Option<String>,
+    /// JSON pointers to every field of the service which couldn't be
+    /// included in the unit, such as `/services/web/build`.
+    pub unsupported: Vec<String>,
+    /// PRIVATE.  Mark this struct as having unknown fields for future
+    /// compatibility.  This prevents direct construction and exhaustive
+    /// matching.  This needs to be be public because of
+    /// http://stackoverflow.com/q/39277157/12089
+    #[doc(hidden)]
+    pub _hidden: (),
+}
+
+impl SystemdUnit {
+    /// Build a unit for every service in `file`.
+    pub fn for_file(file: &File) -> Result<Vec<SystemdUnit>> {
+        file.services
+            .iter()
+            .map(|(name, service)| SystemdUnit::for_service(name, service))
+            .collect()
+    }
+
+    /// Build a unit for the service named `name`, using `docker`.
+    pub fn for_service(name: &str, service: &Service) -> Result<SystemdUnit> {
+        SystemdUnit::with_executable(name, service, DEFAULT_EXECUTABLE)
+    }
+
+    /// Build a unit for the service named `name`, running containers
+    /// using `executable`, which may be `docker` or a compatible tool like
+    /// `podman`.  All fields must already be interpolated.
+    pub fn with_executable(name: &str,
+                           service: &Service,
+                           executable: &str)
+                           -> Result<SystemdUnit> {
+        // We need to know the container's name to stop it, so give it one
+        // if it doesn't already have one.
+        let mut service = service.clone();
+        if service.container_name.is_none() {
+            service.container_name = Some(value(name.to_owned()));
+        }
+        // `docker run` refuses to combine `--restart` with `--rm`, and
+        // systemd handles restarts for us anyway.
+        let restart_mode = service.restart.take();
+        // systemd runs commands in `/`, and `docker` won't expand `~`, so
+        // we can't pass relative bind mounts along.
+        let mut relative_volumes = vec![];
+        for (i, volume) in mem::replace(&mut service.volumes, vec![])
+            .into_iter()
+            .enumerate() {
+            let relative = match volume.value()?.host {
+                Some(HostVolume::Path(ref path)) => !path.is_absolute(),
+                Some(HostVolume::UserRelativePath(_)) => true,
+                _ => false,
+            };
+            if relative {
+                relative_volumes.push(format!("/services/{}/volumes/{}",
+                                              escape_pointer_segment(name),
+                                              i));
+            } else {
+                service.volumes.push(volume);
+            }
+        }
+        let container = service.container_name
+            .as_ref()
+            .expect("container_name should be set")
+            .value()?
+            .to_owned();
+
+        let run = DockerRunCommand::run(name, &service)?;
+        let mut exec_start = run.args;
+        exec_start[0] = executable.to_owned();
+        // Remove the container when it exits, so the next start can reuse
+        // its name.
+        exec_start.insert(2, "--rm".to_owned());
+
+        // We handle `depends_on` ourselves.
+        let depends_on =
+            format!("/services/{}/depends_on", escape_pointer_segment(name));
+        let mut unsupported: Vec<String> =
+            run.unsupported.into_iter().filter(|p| p != &depends_on).collect();
+        unsupported.extend(relative_volumes);
+
+        let mut after = vec!["network-online.target".to_owned()];
+        let mut requires = vec![];
+        if executable.ends_with("docker") {
+            after.push("docker.service".to_owned());
+            requires.push("docker.service".to_owned());
+        }
+        for (dependency, options) in &service.depends_on {
+            let dependency = dependency.value()?;
+            after.push(format!("{}.service", dependency));
+            requires.push(format!("{}.service", dependency));
+            // systemd only waits for the dependency to start, so it can't
+            // wait for it to become healthy or to exit.
+            if let Some(ref condition) = options.condition {
+                if *condition.value()? != DependencyCondition::ServiceStarted {
+                    unsupported.push(format!("{}/{}/condition",
+                                             depends_on,
+                                             escape_pointer_segment(dependency)));
+                }
+            }
+        }
+
+        let (restart, start_limit_burst) = match restart_mode {
+            None => (None, None),
+            Some(ref restart) => {
+                match *restart.value()? {
+                    RestartMode::No => (Some("no"), None),
+                    // Allow the first start, plus `retries` restarts.
+                    RestartMode::OnFailure(retries) => {
+                        (Some("on-failure"), retries.map(|r| r + 1))
+                    }
+                    // systemd only stops a unit when asked to, so this is
+                    // the same as `always`.
+                    RestartMode::Always |
+                    RestartMode::UnlessStopped => (Some("always"), None),
+                }
+            }
+        };
+        let start_limit_interval = match (restart, start_limit_burst) {
+            (_, Some(_)) => Some("infinity"),
+            (Some("on-failure"), None) |
+            (Some("always"), None) => Some("0"),
+            _ => None,
+        };
+        let kill_signal = match service.stop_signal {
+            Some(ref signal) => Some(signal.value()?.to_owned()),
+            None => None,
+        };
+
+        Ok(SystemdUnit {
+            name: format!("{}.service", name),
+            description: format!("docker-compose service {}", name),
+            after: after,
+            requires: requires,
+            exec_start_pre: vec![executable.to_owned(),
+                                 "rm".to_owned(),
+                                 "-f".to_owned(),
+                                 container.clone()],
+            exec_start: exec_start,
+            exec_stop: vec![executable.to_owned(), "stop".to_owned(), container],
+            restart: restart.map(|r| r.to_owned()),
+            start_limit_interval: start_limit_interval.map(|i| i.to_owned()),
+            start_limit_burst: start_limit_burst,
+            kill_signal: kill_signal,
+            unsupported: unsupported,
+            _hidden: (),
+        })
+    }
+}
+
+/// Formats the unit file.
+impl fmt::Display for SystemdUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[Unit]")?;
+        writeln!(f, "Description={}", &self.description)?;
+        if !self.after.is_empty() {
+            writeln!(f, "After={}", self.after.join(" "))?;
+        }
+        if !self.requires.is_empty() {
+            writeln!(f, "Requires={}", self.requires.join(" "))?;
+        }
+        if let Some(ref interval) = self.start_limit_interval {
+            writeln!(f, "StartLimitIntervalSec={}", interval)?;
+        }
+        if let Some(burst) = self.start_limit_burst {
+            writeln!(f, "StartLimitBurst={}", burst)?;
+        }
+        writeln!(f, "")?;
+        writeln!(f, "[Service]")?;
+        writeln!(f, "ExecStartPre=-{}", command_line(&self.exec_start_pre))?;
+        writeln!(f, "ExecStart={}", command_line(&self.exec_start))?;
+        writeln!(f, "ExecStop={}", command_line(&self.exec_stop))?;
+        if let Some(ref restart) = self.restart {
+            writeln!(f, "Restart={}", restart)?;
+        }
+        if let Some(ref signal) = self.kill_signal {
+            writeln!(f, "KillSignal={}", signal)?;
+        }
+        writeln!(f, "")?;
+        writeln!(f, "[Install]")?;
+        writeln!(f, "WantedBy=multi-user.target")
+    }
+}
+
+/// Format `args` as a systemd command line.
+fn command_line(args: &[String]) -> String {
+    let quoted: Vec<String> = args.iter().map(|arg| systemd_quote(arg)).collect();
+    quoted.join(" ")
+}
+
+/// Quote `arg` for use in a systemd command line.  systemd expands `%`
+/// specifiers and `$` variables even inside quotes, so we escape those
+/// too.
+fn systemd_quote(arg: &str) -> String {
+    let is_safe = |c: char| {
+        ((c as u32) < 128 && c.is_alphanumeric()) || "-_./:=@+,".contains(c)
+    };
+    let escaped = arg.replace("%", "%%").replace("$", "$$");
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        escaped
+    } else {
+        format!("\"{}\"",
+                escaped.replace("\\", "\\\\").replace("\"", "\\\""))
+    }
+}
+
+#[test]
+fn systemd_unit_runs_service_in_foreground() {
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "2"
+services:
+  db:
+    image: "postgres"
+  web:
+    image: "example/web"
+    depends_on: ["db"]
+    environment:
+      GREETING: "hello world"
+      PRICE: "$$5"
+    restart: "on-failure:3"
+    stop_signal: "SIGINT"
+"#)
+        .unwrap();
+    let units = SystemdUnit::for_file(&file).unwrap();
+    assert_eq!(units.len(), 2);
+    let web = units.iter().find(|u| u.name == "web.service").unwrap();
+    assert!(web.unsupported.is_empty());
+    assert_eq!(web.to_string(),
+               concat!("[Unit]\n",
+                       "Description=docker-compose service web\n",
+                       "After=network-online.target docker.service db.service\n",
+                       "Requires=docker.service db.service\n",
+                       "StartLimitIntervalSec=infinity\n",
+                       "StartLimitBurst=4\n",
+                       "\n",
+                       "[Service]\n",
+                       "ExecStartPre=-/usr/bin/docker rm -f web\n",
+                       "ExecStart=/usr/bin/docker run --rm --name web ",
+                       "-e \"GREETING=hello world\" -e \"PRICE=$$5\" ",
+                       "--stop-signal SIGINT example/web\n",
+                       "ExecStop=/usr/bin/docker stop web\n",
+                       "Restart=on-failure\n",
+                       "KillSignal=SIGINT\n",
+                       "\n",
+                       "[Install]\n",
+                       "WantedBy=multi-user.target\n"));
+
+    let db = file.services.get("db").unwrap();
+    let podman = SystemdUnit::with_executable("db", db, "/usr/bin/podman").unwrap();
+    assert_eq!(podman.after, vec!["network-online.target"]);
+    assert_eq!(podman.exec_stop, vec!["/usr/bin/podman", "stop", "db"]);
+}
+
+#[test]
+fn systemd_unit_reports_dependency_conditions() {
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "2.1"
+services:
+  db:
+    image: "postgres"
+  cache:
+    image: "redis"
+  web:
+    image: "example/web"
+    depends_on:
+      cache:
+        condition: "service_started"
+      db:
+        condition: "service_healthy"
+"#)
+        .unwrap();
+    let web = file.services.get("web").unwrap();
+    let unit = SystemdUnit::for_service("web", web).unwrap();
+    assert_eq!(unit.requires,
+               vec!["docker.service", "cache.service", "db.service"]);
+    assert_eq!(unit.unsupported, vec!["/services/web/depends_on/db/condition"]);
+}
+
+#[test]
+fn systemd_unit_reports_relative_binds_and_restarts_forever() {
+    use std::str::FromStr;
+
+    let file = File::from_str(r#"---
+version: "2"
+services:
+  web:
+    image: "example/web"
+    restart: "on-failure"
+    volumes:
+      - "./src:/app"
+      - "/srv/logs:/logs"
+      - "~/cache:/cache"
+"#)
+        .unwrap();
+    let web = file.services.get("web").unwrap();
+    let unit = SystemdUnit::for_service("web", web).unwrap();
+    assert_eq!(unit.unsupported,
+               vec!["/services/web/volumes/0", "/services/web/volumes/2"]);
+    assert_eq!(unit.exec_start,
+               vec!["/usr/bin/docker", "run", "--rm", "--name", "web", "-v",
+                    "/srv/logs:/logs", "example/web"]);
+    let text = unit.to_string();
+    assert!(text.contains("StartLimitIntervalSec=0\n"));
+    assert!(!text.contains("StartLimitBurst"));
+}
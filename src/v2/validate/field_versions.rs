@@ -0,0 +1,301 @@
+//! Checking that each field in a file is supported by the file's version.
+//!
+//! The official schemas will also reject these fields, but only with a
+//! generic complaint about additional properties.  Checking them ourselves
+//! lets us explain exactly which version is needed.
+
+use errors::*;
+use super::super::{File, Service, Version, VersionRequirement};
+use super::super::string_or_struct::LongSyntax;
+use super::escape_pointer_segment;
+
+/// Checks the fields of a single file against its version.
+struct FieldVersionChecker<'a> {
+    /// The version of the file we're checking, or `None` if it follows
+    /// the unversioned Compose Spec.
+    version: Option<&'a Version>,
+}
+
+impl<'a> FieldVersionChecker<'a> {
+    /// Fail if `present` is true and `version` doesn't satisfy `required`.
+    fn check(&self,
+             field: &str,
+             present: bool,
+             required: &VersionRequirement)
+             -> Result<()> {
+        let satisfied = match self.version {
+            Some(version) => required.is_satisfied_by(version),
+            None => required.is_satisfied_by_compose_spec(),
+        };
+        if present && !satisfied {
+            Err(ErrorKind::FieldRequiresVersion(field.to_owned(),
+                                                required.to_owned(),
+                                                self.version.cloned())
+                .into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check the top-level fields of `file`, and each of its services.
+    fn check_file(&self, file: &File) -> Result<()> {
+        let v3_1 = VersionRequirement::AtLeast(Version::new(3, 1));
+        let v2_1 = VersionRequirement::AtLeast(Version::new(2, 1));
+        let scone = VersionRequirement::Dialect("scone".to_owned());
+
+        self.check("/name", file.name.is_some(), &VersionRequirement::ComposeSpec)?;
+        self.check("/secrets", !file.secrets.is_empty(), &v3_1)?;
+        for (name, secret) in &file.secrets {
+            let pointer = format!("/secrets/{}", escape_pointer_segment(name));
+            self.check(&format!("{}/kind", pointer),
+                       secret.kind.is_some(),
+                       &scone)?;
+            self.check(&format!("{}/length", pointer),
+                       secret.length.is_some(),
+                       &scone)?;
+        }
+        let v3_3 = VersionRequirement::AtLeast(Version::new(3, 3));
+        let v3_5 = VersionRequirement::AtLeast(Version::new(3, 5));
+        self.check("/configs", !file.configs.is_empty(), &v3_3)?;
+        for (name, config) in &file.configs {
+            let pointer = format!("/configs/{}", escape_pointer_segment(name));
+            self.check(&format!("{}/name", pointer), config.name.is_some(), &v3_5)?;
+            self.check(&format!("{}/content", pointer),
+                       config.content.is_some(),
+                       &VersionRequirement::ComposeSpec)?;
+        }
+        for (name, volume) in &file.volumes {
+            let pointer = format!("/volumes/{}/labels",
+                                  escape_pointer_segment(name));
+            self.check(&pointer, !volume.labels.is_empty(), &v2_1)?;
+        }
+        for (name, network) in &file.networks {
+            let pointer = format!("/networks/{}/labels",
+                                  escape_pointer_segment(name));
+            self.check(&pointer, !network.labels.is_empty(), &v2_1)?;
+        }
+        for (name, service) in &file.services {
+            self.check_service(name, service)?;
+        }
+        Ok(())
+    }
+
+    /// Check the fields of the service `name`.
+    fn check_service(&self, name: &str, service: &Service) -> Result<()> {
+        let v3 = VersionRequirement::AtLeast(Version::new(3, 0));
+        let pre_v3 = VersionRequirement::Before(Version::new(3, 0));
+        let v2_1_only = VersionRequirement::Between(Version::new(2, 1),
+                                                    Version::new(3, 0));
+        let v3_2 = VersionRequirement::AtLeast(Version::new(3, 2));
+        let v3_6 = VersionRequirement::AtLeast(Version::new(3, 6));
+        let v3_7 = VersionRequirement::AtLeast(Version::new(3, 7));
+        let v3_5 = VersionRequirement::AtLeast(Version::new(3, 5));
+        let isolation = VersionRequirement::AnyOf(vec![v2_1_only.clone(), v3_5]);
+        let scone = VersionRequirement::Dialect("scone".to_owned());
+
+        let pointer = format!("/services/{}", escape_pointer_segment(name));
+        let field = |f: &str| format!("{}/{}", pointer, f);
+
+        let v3_1 = VersionRequirement::AtLeast(Version::new(3, 1));
+        let v3_3 = VersionRequirement::AtLeast(Version::new(3, 3));
+        self.check(&field("configs"), !service.configs.is_empty(), &v3_3)?;
+        self.check(&field("secrets"), !service.secrets.is_empty(), &v3_1)?;
+        self.check(&field("init"), service.init.is_some(), &v3_7)?;
+        self.check(&field("isolation"), service.isolation.is_some(), &isolation)?;
+        self.check(&field("profiles"),
+                   !service.profiles.is_empty(),
+                   &VersionRequirement::ComposeSpec)?;
+        for (i, port) in service.ports.iter().enumerate() {
+            let long = port.value().map(|p| p.requires_long_syntax()).unwrap_or(false);
+            self.check(&field(&format!("ports/{}", i)), long, &v3_2)?;
+        }
+        for (i, volume) in service.volumes.iter().enumerate() {
+            let volume = match volume.value() {
+                Ok(volume) => volume,
+                Err(_) => continue,
+            };
+            self.check(&field(&format!("volumes/{}", i)),
+                       volume.requires_long_syntax(),
+                       &v3_2)?;
+            self.check(&field(&format!("volumes/{}/tmpfs/size", i)),
+                       volume.tmpfs_size.is_some(),
+                       &v3_6)?;
+        }
+
+        for (dep_name, dep) in &service.depends_on {
+            let dep_field = format!("depends_on/{}/condition",
+                                    escape_pointer_segment(&dep_name.to_string()));
+            self.check(&field(&dep_field), dep.condition.is_some(), &v2_1_only)?;
+        }
+        if let Some(ref healthcheck) = service.healthcheck {
+            let v2_1 = VersionRequirement::AtLeast(Version::new(2, 1));
+            let v3_4 = VersionRequirement::AtLeast(Version::new(3, 4));
+            self.check(&field("healthcheck"), true, &v2_1)?;
+            self.check(&field("healthcheck/start_period"),
+                       healthcheck.start_period.is_some(),
+                       &v3_4)?;
+        }
+        self.check(&field("deploy"), service.deploy.is_some(), &v3)?;
+
+        self.check(&field("extends"), service.extends.is_some(), &pre_v3)?;
+        self.check(&field("volumes_from"),
+                   !service.volumes_from.is_empty(),
+                   &pre_v3)?;
+        self.check(&field("volume_driver"),
+                   service.volume_driver.is_some(),
+                   &pre_v3)?;
+        self.check(&field("cpu_shares"), service.cpu_shares.is_some(), &pre_v3)?;
+        self.check(&field("cpu_quota"), service.cpu_quota.is_some(), &pre_v3)?;
+        self.check(&field("mem_limit"), service.mem_limit.is_some(), &pre_v3)?;
+        self.check(&field("memswap_limit"),
+                   service.memswap_limit.is_some(),
+                   &pre_v3)?;
+        self.check(&field("oom_score_adj"),
+                   service.oom_score_adj.is_some(),
+                   &pre_v3)?;
+        self.check(&field("group_add"), !service.group_add.is_empty(), &pre_v3)?;
+
+        self.check(&field("mrenclave"), service.scone.mrenclave.is_some(), &scone)?;
+        self.check(&field("fspf_path"), service.scone.fspf_path.is_some(), &scone)?;
+        self.check(&field("fspf_key"), service.scone.fspf_key.is_some(), &scone)?;
+        self.check(&field("fspf_tag"), service.scone.fspf_tag.is_some(), &scone)?;
+        self.check(&field("not_scone"), service.scone.not_scone.is_some(), &scone)?;
+        Ok(())
+    }
+}
+
+/// Make sure that every field used in `file` is supported by its version.
+pub fn check_field_versions(file: &File) -> Result<()> {
+    FieldVersionChecker { version: file.version.as_ref() }.check_file(file)
+}
+
+#[test]
+fn check_field_versions_handles_newer_fields_and_the_compose_spec() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "3.6"
+services:
+  web:
+    image: "nginx"
+    init: true
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    assert_eq!(err.to_string(),
+               "field /services/web/init requires version 3.7 or later, \
+                but the file has version 3.6");
+
+    let yaml = r#"---
+version: "3.1"
+services:
+  web:
+    image: "nginx"
+    ports:
+      - target: 80
+        published: 8080
+        mode: host
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    assert_eq!(err.to_string(),
+               "field /services/web/ports/0 requires version 3.2 or later, \
+                but the file has version 3.1");
+
+    // Files without a version may use anything except vendor dialects.
+    let yaml = r#"---
+services:
+  web:
+    image: "nginx"
+    init: true
+    mem_limit: "1g"
+    mrenclave: "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    assert_eq!(err.to_string(),
+               "field /services/web/mrenclave requires the scone dialect, \
+                but the file has no version (Compose Spec)");
+}
+
+#[test]
+fn check_field_versions_reports_field_and_required_version() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "3"
+services:
+  web:
+    image: "nginx"
+    mem_limit: "1g"
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    match *err.kind() {
+        ErrorKind::FieldRequiresVersion(ref field, ref required, ref version) => {
+            assert_eq!(field, "/services/web/mem_limit");
+            assert_eq!(required, &VersionRequirement::Before(Version::new(3, 0)));
+            assert_eq!(version, &Some(Version::new(3, 0)));
+        }
+        ref kind => panic!("unexpected error: {}", kind),
+    }
+    assert_eq!(err.to_string(),
+               "field /services/web/mem_limit requires a version earlier than 3, \
+                but the file has version 3");
+
+    let yaml = r#"---
+version: "2"
+services:
+  web:
+    image: "nginx"
+    depends_on:
+      db:
+        condition: service_healthy
+  db:
+    image: "postgres"
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    assert_eq!(err.to_string(),
+               "field /services/web/depends_on/db/condition requires version 2.1 \
+                or later and earlier than 3, but the file has version 2");
+}
+
+#[test]
+fn check_field_versions_handles_healthchecks() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "2"
+services:
+  web:
+    image: "nginx"
+    healthcheck:
+      test: "curl -f http://localhost/"
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    assert_eq!(err.to_string(),
+               "field /services/web/healthcheck requires version 2.1 or later, \
+                but the file has version 2");
+
+    let yaml = r#"---
+version: "3.3"
+services:
+  web:
+    image: "nginx"
+    healthcheck:
+      test: "curl -f http://localhost/"
+      start_period: 30s
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    assert_eq!(err.to_string(),
+               "field /services/web/healthcheck/start_period requires version 3.4 \
+                or later, but the file has version 3.3");
+
+    let yaml = r#"---
+version: "2.1"
+services:
+  web:
+    image: "nginx"
+    healthcheck:
+      test: ["CMD", "curl", "-f", "http://localhost/"]
+      interval: 30s
+      retries: 3
+"#;
+    File::from_str(yaml).unwrap();
+}
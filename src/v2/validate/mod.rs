@@ -3,30 +3,58 @@
 
 use serde::Serialize;
 use serde_json;
-use std::ops::Deref;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
 use url::Url;
-use valico;
+use valico::json_schema::{Scope, ValidationState};
 
 use errors::*;
-use super::File;
+use super::{File, Version};
+use self::field_versions::check_field_versions;
+use self::references::check_references;
+use self::secrets::check_secret_lengths;
+use self::source_map::SourceMap;
+pub use self::source_map::{SourceLocation, escape_pointer_segment};
 
-/// Schema for `docker-compose.yml` version 2.0.
-const COMPOSE_2_0_SCHEMA_STR: &'static str = include_str!("config_schema_v2.0.json");
+mod field_versions;
+mod references;
+mod secrets;
+mod source_map;
 
-/// Schema for `docker-compose.yml` version 2.1.
-const COMPOSE_2_1_SCHEMA_STR: &'static str = include_str!("config_schema_v2.1.json");
+/// The built-in schema for each supported version of `docker-compose.yml`.
+/// This is the only list of supported versions, so everything else which
+/// needs one should use `SCHEMA_SOURCES`.
+const VERSIONED_SCHEMA_STRS: &'static [(&'static str, &'static str)] =
+    &[("2", include_str!("config_schema_v2.0.json")),
+      ("2.1", include_str!("config_schema_v2.1.json")),
+      ("3", include_str!("config_schema_v3.0.json")),
+      ("3.scone", include_str!("config_schema_v3.scone.json")),
+      ("3.1", include_str!("config_schema_v3.1.json")),
+      ("3.1.scone", include_str!("config_schema_v3.1.scone.json")),
+      ("3.2", include_str!("config_schema_v3.2.json")),
+      ("3.3", include_str!("config_schema_v3.3.json")),
+      ("3.4", include_str!("config_schema_v3.4.json")),
+      ("3.5", include_str!("config_schema_v3.5.json")),
+      ("3.6", include_str!("config_schema_v3.6.json")),
+      ("3.7", include_str!("config_schema_v3.7.json")),
+      ("3.8", include_str!("config_schema_v3.8.json"))];
 
-/// Schema for `docker-compose.yml` version 3.0.
-const COMPOSE_3_0_SCHEMA_STR: &'static str = include_str!("config_schema_v3.0.json");
+/// Schema for unversioned files which follow the Compose Spec.
+const COMPOSE_SPEC_SCHEMA_STR: &'static str = include_str!("compose_spec.json");
 
-/// Schema for `docker-compose.yml` version 3.scone.
-const COMPOSE_3_SCONE_SCHEMA_STR: &'static str = include_str!("config_schema_v3.scone.json");
-
-/// Schema for `docker-compose.yml` version 3.1.
-const COMPOSE_3_1_SCHEMA_STR: &'static str = include_str!("config_schema_v3.1.json");
-
-/// Schema for `docker-compose.yml` version 3.1.scone.
-const COMPOSE_3_1_SCONE_SCHEMA_STR: &'static str = include_str!("config_schema_v3.1.scone.json");
+lazy_static! {
+    /// The unparsed built-in schemas, indexed by version.
+    static ref SCHEMA_SOURCES: BTreeMap<Version, &'static str> = {
+        let mut sources = BTreeMap::new();
+        for &(version, json) in VERSIONED_SCHEMA_STRS {
+            let version: Version =
+                version.parse().expect("built-in version should parse");
+            sources.insert(version, json);
+        }
+        sources
+    };
+}
 
 /// Load and parse a built-in JSON file, panicking if it contains invalid
 /// JSON.
@@ -37,64 +65,341 @@ fn load_schema_json(json: &'static str) -> serde_json::Value {
     }
 }
 
-lazy_static! {
-    /// Parsed schema for `docker-compose.yml` version 2.0.
-    static ref COMPOSE_2_0_SCHEMA: serde_json::Value =
-        load_schema_json(COMPOSE_2_0_SCHEMA_STR);
+/// A single place where a `docker-compose.yml` file failed to conform to
+/// the official schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// A JSON pointer to the offending value, such as
+    /// `/services/web/ports/0`.
+    pub pointer: String,
+
+    /// A human-readable description of the problem.
+    pub message: String,
 
-    /// Parsed schema for `docker-compose.yml` version 2.1.
-    static ref COMPOSE_2_1_SCHEMA: serde_json::Value =
-        load_schema_json(COMPOSE_2_1_SCHEMA_STR);
+    /// Where the offending value was defined, if we parsed this file from
+    /// YAML source.
+    pub location: Option<SourceLocation>,
+}
 
-    /// Parsed schema for `docker-compose.yml` version 3.0.
-    static ref COMPOSE_3_0_SCHEMA: serde_json::Value =
-        load_schema_json(COMPOSE_3_0_SCHEMA_STR);
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.pointer.is_empty() {
+            write!(f, "/")?;
+        } else {
+            write!(f, "{}", &self.pointer)?;
+        }
+        if let Some(loc) = self.location {
+            write!(f, " (line {}, column {})", loc.line, loc.column)?;
+        }
+        write!(f, ": {}", &self.message)
+    }
+}
 
-    /// Parsed schema for `docker-compose.yml` version 3.scone.
-    static ref COMPOSE_3_SCONE_SCHEMA: serde_json::Value =
-        load_schema_json(COMPOSE_3_SCONE_SCHEMA_STR);
+/// Convert a `ValidationState` into a list of `SchemaViolation` values,
+/// using `source_map` (if available) to look up source locations.
+fn violations_from_state(state: &ValidationState,
+                         source_map: Option<&SourceMap>)
+                         -> Vec<SchemaViolation> {
+    let locate = |pointer: &str| source_map.and_then(|map| map.locate(pointer));
+    let mut violations = vec![];
+    for err in &state.errors {
+        let message = match err.get_detail() {
+            Some(detail) => format!("{}: {}", err.get_title(), detail),
+            None => err.get_title().to_owned(),
+        };
+        violations.push(SchemaViolation {
+            pointer: err.get_path().to_owned(),
+            message: message,
+            location: locate(err.get_path()),
+        });
+    }
+    for url in &state.missing {
+        violations.push(SchemaViolation {
+            pointer: "".to_owned(),
+            message: format!("missing schema {}", url),
+            location: None,
+        });
+    }
+    violations
+}
 
-    /// Parsed schema for `docker-compose.yml` version 3.1.
-    static ref COMPOSE_3_1_SCHEMA: serde_json::Value =
-        load_schema_json(COMPOSE_3_1_SCHEMA_STR);
+/// Parse the built-in schema for the specified version of the
+/// `docker-compose.yml` format, or for the Compose Spec if `version` is
+/// `None`.
+fn builtin_schema(version: Option<&Version>) -> Result<serde_json::Value> {
+    let json = match version {
+        Some(version) => {
+            match SCHEMA_SOURCES.get(version) {
+                Some(json) => *json,
+                None => {
+                    let version = version.to_string();
+                    return Err(ErrorKind::UnsupportedVersion(version).into());
+                }
+            }
+        }
+        None => COMPOSE_SPEC_SCHEMA_STR,
+    };
+    Ok(load_schema_json(json))
+}
+
+/// Schemas which have been compiled by `valico`, indexed by the version
+/// of `docker-compose.yml` which they describe.
+///
+/// Compiling a schema is much more expensive than validating a file
+/// against it, so we only want to do it once.  Unfortunately, a compiled
+/// `valico` `Scope` uses `Rc` internally, and so it can't be shared between
+/// threads.  Instead, we keep one cache per thread.
+struct SchemaCache {
+    /// The `valico` scope which owns all our compiled schemas.
+    scope: Scope,
+    /// The IDs of the schemas we've compiled so far, by version.  The
+    /// Compose Spec schema is stored under `None`.
+    ids: BTreeMap<Option<Version>, Url>,
+}
+
+impl SchemaCache {
+    /// Create a new, empty cache.
+    fn new() -> SchemaCache {
+        SchemaCache {
+            scope: Scope::new(),
+            ids: BTreeMap::new(),
+        }
+    }
+
+    /// Compile the schema for `version` if we haven't already done so.
+    fn compile(&mut self, version: Option<&Version>) -> Result<()> {
+        let key = version.cloned();
+        if self.ids.contains_key(&key) {
+            return Ok(());
+        }
+        let schema_value = builtin_schema(version)?;
+        let name = match version {
+            Some(version) => format!("config_schema_v{}.json", version),
+            None => "compose_spec.json".to_owned(),
+        };
+        let id = Url::parse(&format!("http://example.com/{}", name))
+            .expect("internal schema URL should be valid");
+        if let Err(err) = self.scope.compile_with_id(&id, schema_value, false) {
+            panic!("cannot parse built-in schema: {:?}", err);
+        }
+        self.ids.insert(key, id);
+        Ok(())
+    }
+
+    /// Validate `value` against the schema for `version`, compiling it
+    /// first if necessary.
+    fn validate(&mut self,
+                version: Option<&Version>,
+                value: &serde_json::Value)
+                -> Result<ValidationState> {
+        self.compile(version)?;
+        let id = &self.ids[&version.cloned()];
+        let schema = self.scope
+            .resolve(id)
+            .expect("compiled schema should be present in scope");
+        Ok(schema.validate(value))
+    }
+}
+
+thread_local! {
+    /// The compiled schemas for the current thread.
+    static SCHEMA_CACHE: RefCell<SchemaCache> = RefCell::new(SchemaCache::new());
+}
 
-    /// Parsed schema for `docker-compose.yml` version 3.1.scone.
-    static ref COMPOSE_3_1_SCONE_SCHEMA: serde_json::Value =
-        load_schema_json(COMPOSE_3_1_SCONE_SCHEMA_STR);
+/// A reusable handle for validating `File` values against the official
+/// JSON schemas provided by `docker-compose`.
+///
+/// A `Validator` doesn't own any compiled schemas itself.  Compiled
+/// `valico` schemas can't be sent between threads, so every `Validator`
+/// (and `File::read`, which uses one internally) shares a cache belonging
+/// to the current thread.  Schemas are compiled the first time a thread
+/// needs them and kept for the lifetime of the thread, so it's cheap to
+/// validate many files in a row, and creating a `Validator` costs
+/// nothing.  A `Validator` may be freely cloned and shared between
+/// threads, but each thread will still compile the schemas it needs once,
+/// unless it calls `precompile` first.
+///
+/// ```
+/// use std::str::FromStr;
+/// use compose_yml::v2 as dc;
+///
+/// let validator = dc::Validator::new();
+/// let file = dc::File::from_str("version: \"2\"\nservices: {}\n").unwrap();
+/// validator.validate(&file).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+#[allow(missing_copy_implementations)]
+pub struct Validator {
+    /// A placeholder to prevent this struct from being directly
+    /// constructed.
+    _hidden: (),
+}
+
+impl Validator {
+    /// Create a new `Validator`.
+    pub fn new() -> Validator {
+        Default::default()
+    }
+
+    /// Compile the schemas for all supported versions on the current
+    /// thread ahead of time, so that the first call to `validate` on this
+    /// thread doesn't need to pay for it.  This has no effect on other
+    /// threads.
+    pub fn precompile(&self) {
+        SCHEMA_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            for version in SCHEMA_SOURCES.keys() {
+                cache.compile(Some(version))
+                    .expect("built-in version should be supported");
+            }
+            cache.compile(None).expect("Compose Spec should be supported");
+        })
+    }
+
+    /// Validate `file` against the schema for its version.
+    pub fn validate(&self, file: &File) -> Result<()> {
+        self.validate_with_source(file, None)
+    }
+
+    /// Validate `file` against the schema for its version.  If we know the
+    /// YAML `source` from which `file` was parsed, we use it to report line
+    /// and column numbers for any errors.
+    ///
+    /// Before checking the schema, we make sure that every field is
+    /// supported by the file's version, and return a
+    /// `FieldRequiresVersion` error if one isn't.  We also make sure that
+    /// services only refer to configs and secrets which are declared in the
+    /// file, returning `UndefinedReference` otherwise, and that generated
+    /// secrets have lengths allowed by their kinds, returning
+    /// `SecretLengthOutOfRange` otherwise.  These checks go beyond the
+    /// official schemas, and `File::read` and `File::write` run them, too.
+    pub fn validate_with_source(&self,
+                                file: &File,
+                                source: Option<&str>)
+                                -> Result<()> {
+        let mut serializer = serde_json::value::Serializer::new();
+        file.serialize(&mut serializer)
+            .chain_err(|| ErrorKind::ValidationFailed)?;
+        let value = serializer.unwrap();
+        let version = file.version.as_ref();
+        SCHEMA_CACHE.with(|cache| cache.borrow_mut().compile(version))?;
+        check_field_versions(file)?;
+        check_references(file)?;
+        check_secret_lengths(file)?;
+        let validation_state = SCHEMA_CACHE.with(|cache| {
+                cache.borrow_mut().validate(version, &value)
+            })?;
+        if validation_state.is_strictly_valid() {
+            Ok(())
+        } else {
+            // We only scan the source once we know we have errors to
+            // report.  If the source can't be scanned, we just omit
+            // locations.
+            let source_map = source.and_then(|yaml| SourceMap::new(yaml).ok());
+            let violations =
+                violations_from_state(&validation_state, source_map.as_ref());
+            Err(Error::from(ErrorKind::DoesNotConformToSchema(violations)))
+                .chain_err(|| ErrorKind::ValidationFailed)
+        }
+    }
 }
 
 /// Validate a `File` against the official JSON schema provided by
 /// `docker-compose`.
 pub fn validate_file(file: &File) -> Result<()> {
-    let schema_value = match &file.version[..] {
-        "2" => COMPOSE_2_0_SCHEMA.deref(),
-        "2.1" => COMPOSE_2_1_SCHEMA.deref(),
-        "3" => COMPOSE_3_0_SCHEMA.deref(),
-        "3.scone" => COMPOSE_3_SCONE_SCHEMA.deref(),
-        "3.1" => COMPOSE_3_1_SCHEMA.deref(),
-        "3.1.scone" => COMPOSE_3_1_SCONE_SCHEMA.deref(),
-        vers => return Err(ErrorKind::UnsupportedVersion(vers.to_owned()).into()),
-    };
+    Validator::new().validate(file)
+}
 
-    let mut scope = valico::json_schema::Scope::new();
-    let id = Url::parse("http://example.com/config_schema.json")
-        .expect("internal schema URL should be valid");
-    let schema_result =
-        scope.compile_and_return_with_id(&id, schema_value.clone(), false);
-    let schema = match schema_result {
-        Ok(schema) => schema,
-        Err(err) => panic!("cannot parse built-in schema: {:?}", err),
-    };
+/// Validate a `File` against the official JSON schema provided by
+/// `docker-compose`, using `source` to report error locations.
+pub fn validate_file_with_source(file: &File, source: Option<&str>) -> Result<()> {
+    Validator::new().validate_with_source(file, source)
+}
 
-    let mut serializer = serde_json::value::Serializer::new();
-    file.serialize(&mut serializer)
-        .chain_err(|| ErrorKind::ValidationFailed)?;
-    let value = serializer.unwrap();
-    let validation_state = schema.validate(&value);
-    if validation_state.is_strictly_valid() {
-        Ok(())
-    } else {
-        let res: Result<()> = Err(validation_state.into());
-        res.chain_err(|| ErrorKind::ValidationFailed)
+#[test]
+fn validate_file_reports_pointers_and_locations() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "3"
+services:
+  web:
+    image: "nginx"
+    cap_add:
+      - "NET_ADMIN"
+      - "NET_ADMIN"
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    match *err.kind() {
+        ErrorKind::ValidationFailed => {}
+        ref kind => panic!("unexpected error: {}", kind),
     }
+    let violations = err.schema_violations().expect("should have violations");
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].pointer, "/services/web/cap_add");
+    assert_eq!(violations[0].location,
+               Some(SourceLocation { line: 6, column: 5 }));
+}
+
+#[test]
+fn validate_file_checks_dependency_conditions_in_version_2_1() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "2.1"
+services:
+  db:
+    image: "postgres"
+  web:
+    image: "nginx"
+    depends_on:
+      db:
+        condition: "service_healthy"
+"#;
+    File::from_str(yaml).unwrap();
+
+    // This condition was only added by the Compose Spec.
+    let yaml = r#"---
+version: "2.1"
+services:
+  db:
+    image: "postgres"
+  web:
+    image: "nginx"
+    depends_on:
+      db:
+        condition: "service_completed_successfully"
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    assert!(err.schema_violations().is_some());
+}
+
+#[test]
+fn validator_can_be_shared_between_threads() {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::thread;
+
+    let validator = Arc::new(Validator::new());
+    let handles: Vec<_> = (0..4).map(|_| {
+        let validator = validator.clone();
+        thread::spawn(move || {
+            let file = File::from_str("version: \"2\"\nservices: {}\n").unwrap();
+            // Validate twice to exercise both compiling and caching.
+            validator.validate(&file).unwrap();
+            validator.validate(&file).unwrap();
+        })
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn precompile_compiles_every_built_in_schema() {
+    Validator::new().precompile();
+    SCHEMA_CACHE.with(|cache| {
+        // One schema per version, plus the Compose Spec.
+        assert_eq!(cache.borrow().ids.len(), VERSIONED_SCHEMA_STRS.len() + 1);
+    });
 }
@@ -0,0 +1,86 @@
+//! Checking that the names used by services refer to things declared
+//! elsewhere in the file.
+
+use errors::*;
+use super::super::File;
+use super::escape_pointer_segment;
+
+/// Make sure that every config and secret used by a service is declared in
+/// the top-level `configs` and `secrets` sections.  References which still
+/// need to be interpolated can't be checked, so we skip them.
+pub fn check_references(file: &File) -> Result<()> {
+    for (name, service) in &file.services {
+        for (i, config) in service.configs.iter().enumerate() {
+            let config = match config.value() {
+                Ok(config) => config,
+                Err(_) => continue,
+            };
+            if !file.configs.contains_key(&config.source) {
+                return Err(undefined(name, "configs", i, "config", &config.source));
+            }
+        }
+        for (i, secret) in service.secrets.iter().enumerate() {
+            let secret = match secret.value() {
+                Ok(secret) => secret,
+                Err(_) => continue,
+            };
+            if !file.secrets.contains_key(&secret.source) {
+                return Err(undefined(name, "secrets", i, "secret", &secret.source));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Report that entry `i` in the `field` list of the service `service`
+/// refers to an undefined `kind` named `source`.
+fn undefined(service: &str, field: &str, i: usize, kind: &str, source: &str) -> Error {
+    let pointer = format!("/services/{}/{}/{}",
+                          escape_pointer_segment(service),
+                          field,
+                          i);
+    ErrorKind::UndefinedReference(pointer, kind.to_owned(), source.to_owned()).into()
+}
+
+#[test]
+fn check_references_reports_undefined_configs() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "3.3"
+services:
+  web:
+    image: "nginx"
+    configs:
+      - "nginx_conf"
+      - "site_conf"
+configs:
+  nginx_conf:
+    file: "./nginx.conf"
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    assert_eq!(err.to_string(),
+               "/services/web/configs/1 refers to undefined config 'site_conf'");
+}
+
+#[test]
+fn check_references_reports_undefined_secrets() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "3.1"
+services:
+  web:
+    image: "nginx"
+    secrets:
+      - "api_key"
+      - source: "db_password"
+        target: "password"
+secrets:
+  api_key:
+    file: "./api_key.txt"
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    assert_eq!(err.to_string(),
+               "/services/web/secrets/1 refers to undefined secret 'db_password'");
+}
@@ -0,0 +1,32 @@
+//! Checking the settings of generated secrets.
+
+use errors::*;
+use super::super::File;
+
+/// Make sure that the `length` of every generated secret is allowed for
+/// its `kind`.
+pub fn check_secret_lengths(file: &File) -> Result<()> {
+    for (name, secret) in &file.secrets {
+        secret.check_length(name)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn check_secret_lengths_reports_out_of_range_lengths() {
+    use std::str::FromStr;
+
+    let yaml = r#"---
+version: "3.1.scone"
+services:
+  web:
+    image: "nginx"
+secrets:
+  token:
+    kind: "hex"
+    length: "0"
+"#;
+    let err = File::from_str(yaml).unwrap_err();
+    assert_eq!(err.to_string(),
+               "/secrets/token/length is 0, but must be between 1 and 4096");
+}
@@ -0,0 +1,198 @@
+//! Mapping JSON pointers back to locations in the original YAML source.
+//!
+//! When we validate a `File`, we validate a JSON representation which we
+//! serialized from our own data structures, so the validator can only tell
+//! us _where_ in the document a problem occurred, using a JSON pointer like
+//! `/services/web/ports/0`.  To make that useful to humans, we re-scan the
+//! original YAML text and remember where each node started.
+
+use std::collections::BTreeMap;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+use errors::*;
+
+/// Escape a single segment of a JSON pointer, as described in RFC 6901.
+pub fn escape_pointer_segment(segment: &str) -> String {
+    segment.replace("~", "~0").replace("/", "~1")
+}
+
+/// A location in a YAML source file.  Both `line` and `column` start
+/// counting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The line number, starting at 1.
+    pub line: usize,
+    /// The column number, starting at 1.
+    pub column: usize,
+}
+
+impl From<Marker> for SourceLocation {
+    fn from(mark: Marker) -> SourceLocation {
+        // `yaml_rust` starts counting lines at 1 but columns at 0.
+        SourceLocation {
+            line: mark.line(),
+            column: mark.col() + 1,
+        }
+    }
+}
+
+/// A table mapping JSON pointers to the places in a YAML document where
+/// the corresponding nodes were defined.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    /// The location of each node we've seen, indexed by JSON pointer.
+    locations: BTreeMap<String, SourceLocation>,
+}
+
+impl SourceMap {
+    /// Scan `yaml` and build a `SourceMap` for the first document it
+    /// contains.
+    pub fn new(yaml: &str) -> Result<SourceMap> {
+        let mut builder = SourceMapBuilder::default();
+        let mut parser = Parser::new(yaml.chars());
+        parser.load(&mut builder, false)
+            .map_err(|err| Error::invalid_value("YAML", format!("{}", err)))?;
+        Ok(SourceMap { locations: builder.locations })
+    }
+
+    /// Find the location of the node identified by `pointer`.  If we don't
+    /// know about this node (perhaps because it was created by normalizing
+    /// the original document), fall back to the nearest ancestor we do know
+    /// about.
+    pub fn locate(&self, pointer: &str) -> Option<SourceLocation> {
+        let mut pointer = pointer;
+        loop {
+            if let Some(loc) = self.locations.get(pointer) {
+                return Some(*loc);
+            }
+            match pointer.rfind('/') {
+                Some(idx) => pointer = &pointer[..idx],
+                None => return None,
+            }
+        }
+    }
+}
+
+/// The collection we're currently inside of while scanning YAML events.
+#[derive(Debug)]
+enum Frame {
+    /// A mapping with the specified JSON pointer.  If we've just seen a key,
+    /// we store it in `key` until we see the corresponding value.
+    Mapping {
+        pointer: String,
+        key: Option<String>,
+    },
+    /// A sequence with the specified JSON pointer, and the index of the next
+    /// element we expect to see.
+    Sequence {
+        pointer: String,
+        index: usize,
+    },
+    /// A collection used as a mapping key, which we don't try to track.
+    Ignored,
+}
+
+/// Receives events from the YAML parser and records node locations.
+#[derive(Debug, Default)]
+struct SourceMapBuilder {
+    /// Stack of collections which we're currently inside.
+    stack: Vec<Frame>,
+    /// The locations we've found so far.
+    locations: BTreeMap<String, SourceLocation>,
+    /// Have we already seen the root node of our document?
+    seen_root: bool,
+}
+
+impl SourceMapBuilder {
+    /// Called when a new node starts.  Returns the JSON pointer for that
+    /// node, or `None` if the node is a mapping key or otherwise should not
+    /// be tracked.
+    fn start_node(&mut self, event: &Event, mark: Marker) -> Option<String> {
+        let pointer = match self.stack.last_mut() {
+            None if self.seen_root => return None,
+            None => {
+                self.seen_root = true;
+                "".to_owned()
+            }
+            Some(&mut Frame::Ignored) => return None,
+            Some(&mut Frame::Mapping { ref pointer, ref mut key }) => {
+                match key.take() {
+                    // This node is a value, and we already recorded the
+                    // location of its key, which is what humans look at.
+                    Some(k) => format!("{}/{}", pointer, escape_pointer_segment(&k)),
+                    // This node is a key.
+                    None => {
+                        let k = match *event {
+                            Event::Scalar(ref s, _, _, _) => s.to_owned(),
+                            _ => "?".to_owned(),
+                        };
+                        let key_pointer =
+                            format!("{}/{}", pointer, escape_pointer_segment(&k));
+                        self.locations.entry(key_pointer).or_insert(mark.into());
+                        *key = Some(k);
+                        return None;
+                    }
+                }
+            }
+            Some(&mut Frame::Sequence { ref pointer, ref mut index }) => {
+                let p = format!("{}/{}", pointer, index);
+                *index += 1;
+                p
+            }
+        };
+        self.locations.entry(pointer.clone()).or_insert(mark.into());
+        Some(pointer)
+    }
+}
+
+impl MarkedEventReceiver for SourceMapBuilder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(..) | Event::Alias(_) => {
+                self.start_node(&event, mark);
+            }
+            Event::MappingStart(_) => {
+                let frame = match self.start_node(&event, mark) {
+                    Some(pointer) => Frame::Mapping { pointer: pointer, key: None },
+                    None => Frame::Ignored,
+                };
+                self.stack.push(frame);
+            }
+            Event::SequenceStart(_) => {
+                let frame = match self.start_node(&event, mark) {
+                    Some(pointer) => Frame::Sequence { pointer: pointer, index: 0 },
+                    None => Frame::Ignored,
+                };
+                self.stack.push(frame);
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn source_map_locates_nested_nodes() {
+    let yaml = r#"---
+version: "2"
+services:
+  web:
+    image: "nginx"
+    ports:
+      - "80:80"
+      - "443:443"
+"#;
+    let map = SourceMap::new(yaml).unwrap();
+    assert_eq!(map.locate("/version"),
+               Some(SourceLocation { line: 2, column: 1 }));
+    assert_eq!(map.locate("/services/web"),
+               Some(SourceLocation { line: 4, column: 3 }));
+    assert_eq!(map.locate("/services/web/ports/1"),
+               Some(SourceLocation { line: 8, column: 9 }));
+    // Unknown nodes fall back to their nearest known ancestor.
+    assert_eq!(map.locate("/services/web/build/context"),
+               Some(SourceLocation { line: 4, column: 3 }));
+}
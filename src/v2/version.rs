@@ -0,0 +1,301 @@
+//! Versions of the `docker-compose.yml` file format.
+
+use regex::Regex;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::result;
+use std::str::FromStr;
+
+use errors::*;
+use super::helpers::ConvertToString;
+use super::interpolation::InterpolateAll;
+use super::merge_override::MergeOverride;
+
+/// The version of the `docker-compose.yml` format used by a file, such as
+/// `2.1` or `3.1.scone`.  Files which follow the newer Compose Spec have
+/// no version at all.
+///
+/// Versions are ordered by `major`, then `minor`, then `dialect`, so you
+/// can use the normal comparison operators to check whether a file is
+/// recent enough to use a feature.  `3` and `3.0` are equal, but we
+/// remember which one was parsed, so that we write it back out the same
+/// way.
+///
+/// ```
+/// use compose_yml::v2 as dc;
+/// let version: dc::Version = "3.1.scone".parse().unwrap();
+/// assert_eq!(version.major, 3);
+/// assert_eq!(version.minor, 1);
+/// assert_eq!(version.dialect, Some("scone".to_owned()));
+/// assert!(version >= dc::Version::new(3, 0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Version {
+    /// The major version number.  Files with different major versions
+    /// describe services quite differently.
+    pub major: u32,
+    /// The minor version number, which is 0 if it was omitted.
+    pub minor: u32,
+    /// A vendor-specific extension of the format, such as `scone`.
+    pub dialect: Option<String>,
+    /// Was a zero `minor` written out explicitly, as in `3.0`?
+    explicit_minor: bool,
+}
+
+impl Version {
+    /// Create a new version with no dialect.
+    pub fn new(major: u32, minor: u32) -> Version {
+        Version {
+            major: major,
+            minor: minor,
+            dialect: None,
+            explicit_minor: false,
+        }
+    }
+
+    /// Return a copy of this version using the specified dialect.
+    pub fn with_dialect<S: Into<String>>(&self, dialect: S) -> Version {
+        Version { dialect: Some(dialect.into()), ..self.clone() }
+    }
+
+    /// Does this version use the specified dialect?
+    pub fn has_dialect(&self, dialect: &str) -> bool {
+        self.dialect.as_ref().map_or(false, |d| d == dialect)
+    }
+
+    /// The fields we use when comparing versions.
+    fn key(&self) -> (u32, u32, Option<&str>) {
+        (self.major, self.minor, self.dialect.as_ref().map(|d| &d[..]))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Version) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Hash for Version {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
+impl Default for Version {
+    fn default() -> Version {
+        Version::new(2, 0)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Omit a zero minor version unless it was written out, because
+        // that's how `docker-compose` names its own schemas.
+        write!(f, "{}", self.major)?;
+        if self.minor != 0 || self.explicit_minor {
+            write!(f, ".{}", self.minor)?;
+        }
+        if let Some(ref dialect) = self.dialect {
+            write!(f, ".{}", dialect)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        lazy_static! {
+            static ref VERSION: Regex =
+                Regex::new(r#"^([0-9]+)(?:\.([0-9]+))?(?:\.([a-z][a-z0-9]*))?$"#)
+                    .unwrap();
+        }
+        let caps = VERSION.captures(s).ok_or_else(|| {
+            Error::invalid_value("version", s)
+        })?;
+        let mkerr = || Error::invalid_value("version", s);
+        let major = caps.at(1).unwrap().parse().map_err(|_| mkerr())?;
+        let minor = match caps.at(2) {
+            Some(minor) => minor.parse().map_err(|_| mkerr())?,
+            None => 0,
+        };
+        Ok(Version {
+            major: major,
+            minor: minor,
+            dialect: caps.at(3).map(|d| d.to_owned()),
+            explicit_minor: caps.at(2).is_some(),
+        })
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S>(&self, serializer: &mut S) -> result::Result<(), S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl Deserialize for Version {
+    fn deserialize<D>(deserializer: &mut D) -> result::Result<Self, D::Error>
+        where D: Deserializer
+    {
+        // People often write `version: 2` or `version: 2.1` without
+        // quotes, so accept numbers as well as strings.
+        let ConvertToString(s) = ConvertToString::deserialize(deserializer)?;
+        Version::from_str(&s).map_err(|err| {
+            <D::Error as de::Error>::custom(format!("{}", err))
+        })
+    }
+}
+
+// Versions never contain environment variables, and an override file's
+// version replaces the original's.
+impl InterpolateAll for Version {}
+impl MergeOverride for Version {}
+
+/// Which versions of the file format support a particular field?
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionRequirement {
+    /// The field was added in this version.
+    AtLeast(Version),
+    /// The field was removed in this version.
+    Before(Version),
+    /// The field was added in the first version, and removed in the
+    /// second.
+    Between(Version, Version),
+    /// The field is only supported by the named dialect.
+    Dialect(String),
+    /// The field is only supported by files which follow the Compose Spec,
+    /// which is used when a file has no `version` at all.
+    ComposeSpec,
+    /// The field is supported if any of these requirements is met.
+    AnyOf(Vec<VersionRequirement>),
+}
+
+impl VersionRequirement {
+    /// Can a file of the specified `version` use this field?
+    pub fn is_satisfied_by(&self, version: &Version) -> bool {
+        match *self {
+            VersionRequirement::AtLeast(ref min) => version >= min,
+            VersionRequirement::Before(ref max) => version < max,
+            VersionRequirement::Between(ref min, ref max) => {
+                version >= min && version < max
+            }
+            VersionRequirement::Dialect(ref dialect) => version.has_dialect(dialect),
+            VersionRequirement::ComposeSpec => false,
+            VersionRequirement::AnyOf(ref reqs) => {
+                reqs.iter().any(|r| r.is_satisfied_by(version))
+            }
+        }
+    }
+
+    /// Can a file which follows the unversioned Compose Spec use this
+    /// field?  The Compose Spec supports everything from the numbered
+    /// versions except vendor dialects.
+    pub fn is_satisfied_by_compose_spec(&self) -> bool {
+        match *self {
+            VersionRequirement::Dialect(_) => false,
+            VersionRequirement::AnyOf(ref reqs) => {
+                reqs.iter().any(|r| r.is_satisfied_by_compose_spec())
+            }
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for VersionRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VersionRequirement::AtLeast(ref min) => {
+                write!(f, "version {} or later", min)
+            }
+            VersionRequirement::Before(ref max) => {
+                write!(f, "a version earlier than {}", max)
+            }
+            VersionRequirement::Between(ref min, ref max) => {
+                write!(f, "version {} or later and earlier than {}", min, max)
+            }
+            VersionRequirement::Dialect(ref dialect) => {
+                write!(f, "the {} dialect", dialect)
+            }
+            VersionRequirement::ComposeSpec => write!(f, "the Compose Spec"),
+            VersionRequirement::AnyOf(ref reqs) => {
+                for (i, req) in reqs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " or ")?;
+                    }
+                    write!(f, "{}", req)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[test]
+fn version_can_be_parsed_and_displayed() {
+    let examples = [("2", Version::new(2, 0)),
+                    ("2.1", Version::new(2, 1)),
+                    ("3", Version::new(3, 0)),
+                    ("3.scone", Version::new(3, 0).with_dialect("scone")),
+                    ("3.1.scone", Version::new(3, 1).with_dialect("scone"))];
+    for &(s, ref version) in &examples {
+        assert_eq!(&Version::from_str(s).unwrap(), version);
+        assert_eq!(version.to_string(), s);
+    }
+    let v3_0 = Version::from_str("3.0").unwrap();
+    assert_eq!(v3_0, Version::new(3, 0));
+    assert_eq!(v3_0.to_string(), "3.0");
+    assert_eq!(v3_0.with_dialect("scone").to_string(), "3.0.scone");
+    assert!(Version::from_str("").is_err());
+    assert!(Version::from_str("v2").is_err());
+    assert!(Version::from_str("2.x.1").is_err());
+}
+
+#[test]
+fn versions_can_be_compared() {
+    assert!(Version::new(2, 0) < Version::new(2, 1));
+    assert!(Version::new(2, 1) < Version::new(3, 0));
+    assert!(Version::new(3, 0).with_dialect("scone") > Version::new(3, 0));
+    assert!(Version::new(3, 0).with_dialect("scone") < Version::new(3, 1));
+
+    let pre_v3 = VersionRequirement::Before(Version::new(3, 0));
+    assert!(pre_v3.is_satisfied_by(&Version::new(2, 1)));
+    assert!(!pre_v3.is_satisfied_by(&Version::new(3, 0).with_dialect("scone")));
+    assert_eq!(pre_v3.to_string(), "a version earlier than 3");
+    assert!(pre_v3.is_satisfied_by_compose_spec());
+
+    let v2_1_only = VersionRequirement::Between(Version::new(2, 1),
+                                                Version::new(3, 0));
+    let v3_5 = VersionRequirement::AtLeast(Version::new(3, 5));
+    let isolation = VersionRequirement::AnyOf(vec![v2_1_only, v3_5]);
+    assert!(isolation.is_satisfied_by(&Version::new(2, 1)));
+    assert!(!isolation.is_satisfied_by(&Version::new(3, 4)));
+    assert!(isolation.is_satisfied_by(&Version::new(3, 8)));
+    assert_eq!(isolation.to_string(),
+               "version 2.1 or later and earlier than 3 or version 3.5 or later");
+    assert!(!VersionRequirement::ComposeSpec
+        .is_satisfied_by(&Version::new(3, 8)));
+    assert!(VersionRequirement::ComposeSpec.is_satisfied_by_compose_spec());
+    assert!(!VersionRequirement::Dialect("scone".to_owned())
+        .is_satisfied_by_compose_spec());
+}
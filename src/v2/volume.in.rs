@@ -18,9 +18,9 @@ pub struct Volume {
     ///
     /// TODO LOW: Clear on merge if `driver` changes, like we do for
     /// `Logging` options.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
+    #[serde(default, skip_serializing_if = "Map::is_empty",
             deserialize_with = "deserialize_map_or_key_value_list")]
-    pub driver_opts: BTreeMap<String, RawOr<String>>,
+    pub driver_opts: Map<String, RawOr<String>>,
 
     /// If this is true, then the volume was created outside of
     /// `docker-compose`.  This option is mutually exclusive with the
@@ -30,9 +30,9 @@ pub struct Volume {
 
     /// Docker labels for this volume, specifying various sorts of
     /// custom metadata.
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty",
+    #[serde(default, skip_serializing_if = "Map::is_empty",
             deserialize_with = "deserialize_map_or_key_value_list")]
-    pub labels: BTreeMap<String, RawOr<String>>,
+    pub labels: Map<String, RawOr<String>>,
 
     /// PRIVATE.  Mark this struct as having unknown fields for future
     /// compatibility.  This prevents direct construction and exhaustive
@@ -127,6 +127,21 @@ pub struct VolumeMount {
     pub container: String,
     /// What should the permissions of this volume be in the container?
     pub permissions: VolumePermissions,
+    /// What kind of mount is this?  This is normally inferred from `host`,
+    /// but it's required by the long syntax, and it's the only way to
+    /// request a `tmpfs` or `npipe` mount.
+    pub mount_type: Option<MountType>,
+    /// The consistency requirements for a bind mount on Docker for Mac:
+    /// `consistent`, `cached` or `delegated`.  Requires the long syntax.
+    pub consistency: Option<String>,
+    /// Should we avoid copying data from the container into a newly
+    /// created volume?  Requires the long syntax.
+    pub nocopy: bool,
+    /// The mount propagation mode for a bind mount, such as `rprivate`.
+    /// Requires the long syntax.
+    pub propagation: Option<String>,
+    /// The size of a `tmpfs` mount, in bytes.  Requires the long syntax.
+    pub tmpfs_size: Option<u64>,
 
     /// PRIVATE.  Mark this struct as having unknown fields for future
     /// compatibility.  This prevents direct construction and exhaustive
@@ -150,6 +165,11 @@ impl VolumeMount {
             host: Some(HostVolume::Path(host.into())),
             container: container.into(),
             permissions: Default::default(),
+            mount_type: None,
+            consistency: None,
+            nocopy: false,
+            propagation: None,
+            tmpfs_size: None,
             _hidden: (),
         }
     }
@@ -167,6 +187,11 @@ impl VolumeMount {
             host: Some(HostVolume::Name(name.into())),
             container: container.into(),
             permissions: Default::default(),
+            mount_type: None,
+            consistency: None,
+            nocopy: false,
+            propagation: None,
+            tmpfs_size: None,
             _hidden: (),
         }
     }
@@ -180,6 +205,11 @@ impl VolumeMount {
             host: None,
             container: container.into(),
             permissions: Default::default(),
+            mount_type: None,
+            consistency: None,
+            nocopy: false,
+            propagation: None,
+            tmpfs_size: None,
             _hidden: (),
         }
     }
@@ -187,6 +217,150 @@ impl VolumeMount {
 
 impl_interpolatable_value!(VolumeMount);
 
+/// The long syntax for a `VolumeMount`, as a map.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VolumeMountLongSyntax {
+    /// Options for bind mounts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bind: Option<BindOptions>,
+    /// Bind mount consistency on Docker for Mac.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    consistency: Option<String>,
+    /// Should the mount be read-only?
+    #[serde(default, skip_serializing_if = "is_false")]
+    read_only: bool,
+    /// The host path or volume name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    /// The path in the container.
+    target: String,
+    /// Options for `tmpfs` mounts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tmpfs: Option<TmpfsOptions>,
+    /// The kind of mount.
+    #[serde(rename = "type")]
+    mount_type: String,
+    /// Options for volume mounts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    volume: Option<VolumeOptions>,
+}
+
+/// Long syntax options for bind mounts.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BindOptions {
+    /// The mount propagation mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    propagation: Option<String>,
+}
+
+/// Long syntax options for `tmpfs` mounts.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TmpfsOptions {
+    /// The size of the mount, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+}
+
+/// Long syntax options for volume mounts.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VolumeOptions {
+    /// Should we avoid copying data from the container?
+    #[serde(default, skip_serializing_if = "is_false")]
+    nocopy: bool,
+}
+
+impl VolumeMount {
+    /// The type of this mount, inferring it from `host` if necessary.
+    fn effective_mount_type(&self) -> MountType {
+        match (&self.mount_type, &self.host) {
+            (&Some(ref mount_type), _) => mount_type.clone(),
+            (&None, &Some(HostVolume::Path(_))) |
+            (&None, &Some(HostVolume::UserRelativePath(_))) => MountType::Bind,
+            (&None, &Some(HostVolume::Name(_))) |
+            (&None, &None) => MountType::Volume,
+        }
+    }
+}
+
+impl LongSyntax for VolumeMount {
+    fn requires_long_syntax(&self) -> bool {
+        let short_type = match self.effective_mount_type() {
+            MountType::Volume | MountType::Bind => true,
+            MountType::Tmpfs | MountType::NamedPipe => false,
+        };
+        !short_type || self.consistency.is_some() || self.nocopy ||
+        self.propagation.is_some() || self.tmpfs_size.is_some()
+    }
+
+    fn serialize_long<S>(&self, serializer: &mut S) -> result::Result<(), S::Error>
+        where S: Serializer
+    {
+        let bind = self.propagation
+            .as_ref()
+            .map(|p| BindOptions { propagation: Some(p.to_owned()) });
+        let tmpfs = self.tmpfs_size.map(|size| TmpfsOptions { size: Some(size) });
+        let volume = if self.nocopy {
+            Some(VolumeOptions { nocopy: true })
+        } else {
+            None
+        };
+        VolumeMountLongSyntax {
+                bind: bind,
+                consistency: self.consistency.clone(),
+                read_only: self.permissions == VolumePermissions::ReadOnly,
+                source: self.host.as_ref().map(|h| h.to_string()),
+                target: self.container.clone(),
+                tmpfs: tmpfs,
+                mount_type: self.effective_mount_type().to_string(),
+                volume: volume,
+            }
+            .serialize(serializer)
+    }
+
+    fn deserialize_long<M>(visitor: M) -> result::Result<Self, M::Error>
+        where M: de::MapVisitor
+    {
+        let mut mvd = de::value::MapVisitorDeserializer::new(visitor);
+        let long = VolumeMountLongSyntax::deserialize(&mut mvd)?;
+        let mkerr = |err: Error| -> M::Error { de::Error::custom(format!("{}", err)) };
+        let mount_type = MountType::from_str(&long.mount_type).map_err(&mkerr)?;
+        let host = match (&mount_type, long.source) {
+            (_, None) => None,
+            (&MountType::Volume, Some(name)) => Some(HostVolume::Name(name)),
+            (_, Some(source)) => {
+                // Bind mounts and pipes always refer to host paths, even if
+                // they don't look like paths.
+                match HostVolume::from_str(&source).map_err(&mkerr)? {
+                    HostVolume::Name(name) => Some(HostVolume::Path(name.into())),
+                    host => Some(host),
+                }
+            }
+        };
+        let permissions = if long.read_only {
+            VolumePermissions::ReadOnly
+        } else {
+            VolumePermissions::ReadWrite
+        };
+        Ok(VolumeMount {
+            host: host,
+            container: long.target,
+            permissions: permissions,
+            mount_type: Some(mount_type),
+            consistency: long.consistency,
+            nocopy: long.volume.map_or(false, |v| v.nocopy),
+            propagation: long.bind.and_then(|b| b.propagation),
+            tmpfs_size: long.tmpfs.and_then(|t| t.size),
+            _hidden: (),
+        })
+    }
+}
+
+/// Formats a `VolumeMount` using the short syntax, which cannot represent
+/// any of the options which require the long syntax.
 impl fmt::Display for VolumeMount {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // We can't have permissions on a purely internal volume, if I'm
@@ -221,6 +395,11 @@ impl FromStr for VolumeMount {
                     host: None,
                     container: items[0].to_owned(),
                     permissions: Default::default(),
+                    mount_type: None,
+                    consistency: None,
+                    nocopy: false,
+                    propagation: None,
+                    tmpfs_size: None,
                     _hidden: (),
                 })
             }
@@ -229,6 +408,11 @@ impl FromStr for VolumeMount {
                     host: Some(FromStr::from_str(items[0])?),
                     container: items[1].to_owned(),
                     permissions: Default::default(),
+                    mount_type: None,
+                    consistency: None,
+                    nocopy: false,
+                    propagation: None,
+                    tmpfs_size: None,
                     _hidden: (),
                 })
             }
@@ -237,6 +421,11 @@ impl FromStr for VolumeMount {
                     host: Some(FromStr::from_str(items[0])?),
                     container: items[1].to_owned(),
                     permissions: FromStr::from_str(items[2])?,
+                    mount_type: None,
+                    consistency: None,
+                    nocopy: false,
+                    propagation: None,
+                    tmpfs_size: None,
                     _hidden: (),
                 })
             }
@@ -295,3 +484,29 @@ fn windows_volume_mounts_should_have_string_representations() {
         assert_eq!(mode, VolumeMount::from_str(s).unwrap());
     }
 }
+
+#[test]
+fn volume_mounts_support_long_syntax() {
+    let yaml = r#"---
+"volumes":
+- "data:/var/lib/data"
+- "nocopy": true
+  "source": "cache"
+  "target": "/cache"
+  "type": "volume"
+- "target": "/tmp"
+  "tmpfs":
+    "size": 1000000
+  "type": "tmpfs"
+"#;
+    assert_roundtrip!(Service, yaml);
+
+    let service: Service = serde_yaml::from_str(yaml).unwrap();
+    let cache = service.volumes[1].value().unwrap();
+    assert_eq!(cache.host, Some(HostVolume::Name("cache".to_owned())));
+    assert!(cache.nocopy);
+    let tmp = service.volumes[2].value().unwrap();
+    assert_eq!(tmp.host, None);
+    assert_eq!(tmp.mount_type, Some(MountType::Tmpfs));
+    assert_eq!(tmp.tmpfs_size, Some(1000000));
+}